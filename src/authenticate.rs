@@ -0,0 +1,447 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{parse_ber_tlvs, BerTlv, BerTlvError};
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::{Authenticate, AuthenticateOdd};
+
+/// AuthenticationContext: b3-b1 of P2 of AUTHENTICATE; ref 11.1.16 / ETSI TS 102 221 V15.0.0 and
+/// 7.1.2 / ETSI TS 131 102
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum AuthenticationContext {
+    Gsm = 0b000,
+    Umts = 0b001,
+    VgcsVbs = 0b010,
+    Gba = 0b100,
+    Mbms = 0b101,
+    LocalKeyEstablishment = 0b110,
+}
+
+/// AuthenticateMode: the procedure that the command data of AUTHENTICATE represents.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthenticateMode {
+    Gsm,
+    Umts,
+    GbaBootstrapping,
+    GbaNafDerivation,
+}
+
+/// AuthenticateCommand: ref 11.1.16 / ETSI TS 102 221 V15.0.0
+pub struct AuthenticateCommand {
+    mode: AuthenticateMode,
+    p2: u8,
+    command_data: Vec<u8>,
+}
+
+/// OddAuthenticateCommand: AUTHENTICATE with the odd instruction code, whose command data and response data
+/// are BER-TLV data objects; ref 11.1.16 / ETSI TS 102 221 V15.0.0 and 5.2 / ISO/IEC 7816-4
+pub struct OddAuthenticateCommand {
+    p2: u8,
+    command_data: Vec<u8>,
+}
+
+/// AuthenticateResponse: the successful response data of AUTHENTICATE; ref 7.1.2 / ETSI TS 131 102
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthenticateResponse {
+    /// GSM context: SRES and Kc
+    Gsm { sres: Vec<u8>, kc: Vec<u8> },
+    /// 3G context, successful (tag 'DB'): RES, CK, IK and Kc if the service is available
+    Umts {
+        res: Vec<u8>,
+        ck: Vec<u8>,
+        ik: Vec<u8>,
+        kc: Option<Vec<u8>>,
+    },
+    /// 3G or GBA bootstrapping context, synchronisation failure (tag 'DC'): AUTS
+    SynchronisationFailure { auts: Vec<u8> },
+    /// GBA bootstrapping, successful (tag 'DB'): RES
+    GbaBootstrapping { res: Vec<u8> },
+    /// GBA NAF derivation, successful (tag 'DB'): Ks_ext_NAF
+    GbaNafDerivation { ks_ext_naf: Vec<u8> },
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum AuthenticateError {
+    #[error(
+        "too long data of AUTHENTICATE; each data must be within [0, 255] bytes but {0} bytes"
+    )]
+    TooLongData(usize),
+    #[error("unexpected end of the AUTHENTICATE response at offset {0}")]
+    UnexpectedEnd(usize),
+    #[error("unexpected tag of the AUTHENTICATE response: '{0:02X}'")]
+    UnexpectedTag(u8),
+    #[error("invalid BER-TLV in the AUTHENTICATE response: {0}")]
+    InvalidBerTlv(#[from] BerTlvError),
+}
+
+const SUCCESSFUL_TAG: u8 = 0xdb;
+const SYNCHRONISATION_FAILURE_TAG: u8 = 0xdc;
+const GBA_BOOTSTRAPPING_TAG: u8 = 0xdd;
+const GBA_NAF_DERIVATION_TAG: u8 = 0xde;
+
+/// Creates AUTHENTICATE in GSM context with the RAND.
+pub fn new_gsm_authenticate_command(rand: &[u8; 16]) -> AuthenticateCommand {
+    AuthenticateCommand {
+        mode: AuthenticateMode::Gsm,
+        p2: specific_reference(AuthenticationContext::Gsm),
+        command_data: length_prefixed(&[rand]),
+    }
+}
+
+/// Creates AUTHENTICATE in 3G context with the RAND and the AUTN.
+pub fn new_umts_authenticate_command(rand: &[u8; 16], autn: &[u8; 16]) -> AuthenticateCommand {
+    AuthenticateCommand {
+        mode: AuthenticateMode::Umts,
+        p2: specific_reference(AuthenticationContext::Umts),
+        command_data: length_prefixed(&[rand, autn]),
+    }
+}
+
+/// Creates AUTHENTICATE in GBA context for the bootstrapping mode with the RAND and the AUTN.
+pub fn new_gba_bootstrapping_command(rand: &[u8; 16], autn: &[u8; 16]) -> AuthenticateCommand {
+    let mut command_data = Vec::from([GBA_BOOTSTRAPPING_TAG]);
+    command_data.extend(length_prefixed(&[rand, autn]));
+    AuthenticateCommand {
+        mode: AuthenticateMode::GbaBootstrapping,
+        p2: specific_reference(AuthenticationContext::Gba),
+        command_data,
+    }
+}
+
+/// Creates AUTHENTICATE in GBA context for the NAF derivation mode with the NAF_ID and the IMPI.
+pub fn new_gba_naf_derivation_command(
+    naf_id: &[u8],
+    impi: &[u8],
+) -> Result<AuthenticateCommand, AuthenticateError> {
+    for data in [naf_id, impi] {
+        if data.len() > 0xff {
+            return Err(AuthenticateError::TooLongData(data.len()));
+        }
+    }
+
+    let mut command_data = Vec::from([GBA_NAF_DERIVATION_TAG]);
+    command_data.extend(length_prefixed(&[naf_id, impi]));
+    Ok(AuthenticateCommand {
+        mode: AuthenticateMode::GbaNafDerivation,
+        p2: specific_reference(AuthenticationContext::Gba),
+        command_data,
+    })
+}
+
+/// Creates AUTHENTICATE with the odd instruction code in the context with the BER-TLV data objects, e.g. the
+/// authentication data of EAP; the command data must be within 255 bytes.
+pub fn new_odd_authenticate_command(
+    context: AuthenticationContext,
+    data_objects: &[BerTlv],
+) -> Result<OddAuthenticateCommand, AuthenticateError> {
+    let command_data: Vec<u8> = data_objects.iter().flat_map(|tlv| tlv.to_bytes()).collect();
+    if command_data.len() > 0xff {
+        return Err(AuthenticateError::TooLongData(command_data.len()));
+    }
+    Ok(OddAuthenticateCommand {
+        p2: specific_reference(context),
+        command_data,
+    })
+}
+
+/// P2 b8 = 1 denotes the specific reference data, i.e. the application specific key.
+fn specific_reference(context: AuthenticationContext) -> u8 {
+    0b10000000 | context as u8
+}
+
+fn length_prefixed(fields: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for field in fields {
+        bytes.push(field.len() as u8);
+        bytes.extend_from_slice(field);
+    }
+    bytes
+}
+
+/// Reads the length-value fields from the offset.
+fn read_length_prefixed(
+    data: &[u8],
+    mut offset: usize,
+    count: usize,
+) -> Result<Vec<Vec<u8>>, AuthenticateError> {
+    let mut fields = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = *data
+            .get(offset)
+            .ok_or(AuthenticateError::UnexpectedEnd(offset))? as usize;
+        let end = offset + 1 + len;
+        if data.len() < end {
+            return Err(AuthenticateError::UnexpectedEnd(data.len()));
+        }
+        fields.push(data[offset + 1..end].to_vec());
+        offset = end;
+    }
+    Ok(fields)
+}
+
+impl AuthenticateCommand {
+    pub fn get_mode(&self) -> AuthenticateMode {
+        self.mode
+    }
+
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn get_command_data(&self) -> &[u8] {
+        &self.command_data
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &Authenticate {},
+            0x00,
+            self.p2,
+            Some(0x00),
+            Some(&self.command_data),
+        )
+    }
+
+    /// Parses the response data of this command according to its mode.
+    pub fn parse_response(&self, data: &[u8]) -> Result<AuthenticateResponse, AuthenticateError> {
+        parse_authenticate_response(self.mode, data)
+    }
+}
+
+impl OddAuthenticateCommand {
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn get_command_data(&self) -> &[u8] {
+        &self.command_data
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &AuthenticateOdd {},
+            0x00,
+            self.p2,
+            Some(0x00),
+            Some(&self.command_data),
+        )
+    }
+
+    /// Parses the response data of this command as the BER-TLV data objects.
+    pub fn parse_response(&self, data: &[u8]) -> Result<Vec<BerTlv>, AuthenticateError> {
+        Ok(parse_ber_tlvs(data)?)
+    }
+}
+
+pub fn parse_authenticate_response(
+    mode: AuthenticateMode,
+    data: &[u8],
+) -> Result<AuthenticateResponse, AuthenticateError> {
+    if mode == AuthenticateMode::Gsm {
+        let mut fields = read_length_prefixed(data, 0, 2)?;
+        let kc = fields.pop().unwrap();
+        let sres = fields.pop().unwrap();
+        return Ok(AuthenticateResponse::Gsm { sres, kc });
+    }
+
+    let tag = *data.first().ok_or(AuthenticateError::UnexpectedEnd(0))?;
+    match (mode, tag) {
+        (
+            AuthenticateMode::Umts | AuthenticateMode::GbaBootstrapping,
+            SYNCHRONISATION_FAILURE_TAG,
+        ) => {
+            let auts = read_length_prefixed(data, 1, 1)?.pop().unwrap();
+            Ok(AuthenticateResponse::SynchronisationFailure { auts })
+        }
+        (AuthenticateMode::Umts, SUCCESSFUL_TAG) => {
+            let mut fields = read_length_prefixed(data, 1, 3)?;
+            let consumed: usize = 1 + fields.iter().map(|f| 1 + f.len()).sum::<usize>();
+            let kc = if data.len() > consumed {
+                Some(read_length_prefixed(data, consumed, 1)?.pop().unwrap())
+            } else {
+                None
+            };
+            let ik = fields.pop().unwrap();
+            let ck = fields.pop().unwrap();
+            let res = fields.pop().unwrap();
+            Ok(AuthenticateResponse::Umts { res, ck, ik, kc })
+        }
+        (AuthenticateMode::GbaBootstrapping, SUCCESSFUL_TAG) => {
+            let res = read_length_prefixed(data, 1, 1)?.pop().unwrap();
+            Ok(AuthenticateResponse::GbaBootstrapping { res })
+        }
+        (AuthenticateMode::GbaNafDerivation, SUCCESSFUL_TAG) => {
+            let ks_ext_naf = read_length_prefixed(data, 1, 1)?.pop().unwrap();
+            Ok(AuthenticateResponse::GbaNafDerivation { ks_ext_naf })
+        }
+        (_, tag) => Err(AuthenticateError::UnexpectedTag(tag)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::authenticate::{
+        new_gba_bootstrapping_command, new_gba_naf_derivation_command,
+        new_gsm_authenticate_command, new_odd_authenticate_command, new_umts_authenticate_command,
+        AuthenticateError, AuthenticateResponse, AuthenticationContext,
+    };
+    use crate::ber_tlv::{new_ber_tlv, BerTlvError};
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+
+    #[test]
+    fn should_construct_authenticate_commands() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let bytes = new_gsm_authenticate_command(&[0x11; 16])
+            .to_command_apdu(&class)
+            .to_bytes()
+            .unwrap();
+        assert_eq!(bytes[..6], [0x00, 0x88, 0x00, 0x80, 0x11, 0x10]);
+        assert_eq!(bytes.len(), 5 + 17 + 1);
+
+        let command = new_umts_authenticate_command(&[0x11; 16], &[0x22; 16]);
+        let bytes = command.to_command_apdu(&class).to_bytes().unwrap();
+        assert_eq!(bytes[..6], [0x00, 0x88, 0x00, 0x81, 0x22, 0x10]);
+        assert_eq!(bytes[22..24], [0x10, 0x22]);
+
+        let command = new_gba_bootstrapping_command(&[0x11; 16], &[0x22; 16]);
+        assert_eq!(command.get_p2(), 0x84);
+        assert_eq!(command.get_command_data()[..2], [0xdd, 0x10]);
+
+        let command = new_gba_naf_derivation_command(b"naf", b"impi").unwrap();
+        assert_eq!(
+            command.get_command_data(),
+            &[0xde, 0x03, 0x6e, 0x61, 0x66, 0x04, 0x69, 0x6d, 0x70, 0x69]
+        );
+        assert_eq!(
+            new_gba_naf_derivation_command(&[0x00; 256], b"impi")
+                .err()
+                .unwrap(),
+            AuthenticateError::TooLongData(256)
+        );
+    }
+
+    #[test]
+    fn should_parse_authenticate_responses() {
+        let command = new_gsm_authenticate_command(&[0x11; 16]);
+        let mut data = Vec::from([0x04, 0x01, 0x02, 0x03, 0x04, 0x08]);
+        data.extend_from_slice(&[0xaa; 8]);
+        assert_eq!(
+            command.parse_response(&data).unwrap(),
+            AuthenticateResponse::Gsm {
+                sres: Vec::from([0x01, 0x02, 0x03, 0x04]),
+                kc: Vec::from([0xaa; 8]),
+            }
+        );
+
+        let command = new_umts_authenticate_command(&[0x11; 16], &[0x22; 16]);
+        let mut data = Vec::from([0xdb, 0x08]);
+        data.extend_from_slice(&[0x01; 8]);
+        data.push(0x10);
+        data.extend_from_slice(&[0x02; 16]);
+        data.push(0x10);
+        data.extend_from_slice(&[0x03; 16]);
+        assert_eq!(
+            command.parse_response(&data).unwrap(),
+            AuthenticateResponse::Umts {
+                res: Vec::from([0x01; 8]),
+                ck: Vec::from([0x02; 16]),
+                ik: Vec::from([0x03; 16]),
+                kc: None,
+            }
+        );
+        data.push(0x08);
+        data.extend_from_slice(&[0x04; 8]);
+        assert!(matches!(
+            command.parse_response(&data).unwrap(),
+            AuthenticateResponse::Umts { kc: Some(_), .. }
+        ));
+
+        let mut data = Vec::from([0xdc, 0x0e]);
+        data.extend_from_slice(&[0x05; 14]);
+        assert_eq!(
+            command.parse_response(&data).unwrap(),
+            AuthenticateResponse::SynchronisationFailure {
+                auts: Vec::from([0x05; 14])
+            }
+        );
+
+        let command = new_gba_naf_derivation_command(b"naf", b"impi").unwrap();
+        assert_eq!(
+            command.parse_response(&[0xdb, 0x02, 0x06, 0x07]).unwrap(),
+            AuthenticateResponse::GbaNafDerivation {
+                ks_ext_naf: Vec::from([0x06, 0x07])
+            }
+        );
+    }
+
+    #[test]
+    fn should_authenticate_with_odd_instruction() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let command = new_odd_authenticate_command(
+            AuthenticationContext::Umts,
+            &[new_ber_tlv(0x80, Vec::from([0x01, 0x02]))],
+        )
+        .unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0x89, 0x00, 0x81, 0x04, 0x80, 0x02, 0x01, 0x02, 0x00])
+        );
+        assert_eq!(
+            command
+                .parse_response(&[0x80, 0x01, 0x05, 0x81, 0x00])
+                .unwrap(),
+            Vec::from([
+                new_ber_tlv(0x80, Vec::from([0x05])),
+                new_ber_tlv(0x81, Vec::new())
+            ])
+        );
+        assert_eq!(
+            command.parse_response(&[0x80, 0x02, 0x05]).unwrap_err(),
+            AuthenticateError::InvalidBerTlv(BerTlvError::UnexpectedEnd(3))
+        );
+        assert_eq!(
+            new_odd_authenticate_command(
+                AuthenticationContext::Gba,
+                &[new_ber_tlv(0x80, Vec::from([0x00; 0xfd]))]
+            )
+            .err()
+            .unwrap(),
+            AuthenticateError::TooLongData(0x100)
+        );
+    }
+
+    #[test]
+    fn should_fail_parse_malformed_authenticate_response() {
+        let command = new_umts_authenticate_command(&[0x11; 16], &[0x22; 16]);
+        assert_eq!(
+            command.parse_response(&[0xdb, 0x08, 0x01]).unwrap_err(),
+            AuthenticateError::UnexpectedEnd(3)
+        );
+        assert_eq!(
+            command.parse_response(&[0xdd, 0x00]).unwrap_err(),
+            AuthenticateError::UnexpectedTag(0xdd)
+        );
+        assert_eq!(
+            command.parse_response(&[]).unwrap_err(),
+            AuthenticateError::UnexpectedEnd(0)
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use thiserror::Error;
+
+/// BerTlv: BER-TLV data object; ref ISO/IEC 7816-4 and 11.1.1.3 / ETSI TS 102 221 V15.0.0
+///
+/// The tag is held as the big-endian integer of the tag bytes, e.g. '62' as 0x62 and 'DF6A' as 0xdf6a.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BerTlv {
+    tag: u32,
+    value: Vec<u8>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum BerTlvError {
+    #[error("unexpected end of the BER-TLV data at offset {0}")]
+    UnexpectedEnd(usize),
+    #[error("unsupported length of the BER-TLV; the first byte of the length field is '{0:#04x}'")]
+    UnsupportedLength(u8),
+    #[error("unsupported tag of the BER-TLV; the tag is longer than 4 bytes")]
+    UnsupportedTag,
+}
+
+pub fn new_ber_tlv(tag: u32, value: Vec<u8>) -> BerTlv {
+    BerTlv { tag, value }
+}
+
+/// Parses a BER-TLV data object at the beginning of the bytes and returns it with the number of consumed bytes.
+pub fn parse_ber_tlv(bytes: &[u8]) -> Result<(BerTlv, usize), BerTlvError> {
+    let (tag, len, offset) = parse_ber_tlv_header(bytes)?;
+    let end = offset + len;
+    if bytes.len() < end {
+        return Err(BerTlvError::UnexpectedEnd(bytes.len()));
+    }
+
+    Ok((
+        BerTlv {
+            tag,
+            value: bytes[offset..end].to_vec(),
+        },
+        end,
+    ))
+}
+
+/// Parses the tag and the length of the data object whose value may not follow completely, e.g. in the
+/// first block of SET DATA, and returns them with the size of the header.
+pub(crate) fn parse_ber_tlv_header(bytes: &[u8]) -> Result<(u32, usize, usize), BerTlvError> {
+    let mut offset = 0;
+
+    let first = *bytes.first().ok_or(BerTlvError::UnexpectedEnd(offset))?;
+    let mut tag = first as u32;
+    offset += 1;
+    if first & 0x1f == 0x1f {
+        loop {
+            let b = *bytes
+                .get(offset)
+                .ok_or(BerTlvError::UnexpectedEnd(offset))?;
+            if offset >= 4 {
+                return Err(BerTlvError::UnsupportedTag);
+            }
+            tag = (tag << 8) | b as u32;
+            offset += 1;
+            if b & 0x80 == 0 {
+                break;
+            }
+        }
+    }
+
+    let (len, len_size) = decode_length(&bytes[offset..], offset)?;
+    Ok((tag, len, offset + len_size))
+}
+
+/// Parses the sequence of BER-TLV data objects. '00' and 'FF' between the data objects are regarded as padding.
+pub fn parse_ber_tlvs(bytes: &[u8]) -> Result<Vec<BerTlv>, BerTlvError> {
+    let mut tlvs = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        if bytes[offset] == 0x00 || bytes[offset] == 0xff {
+            offset += 1;
+            continue;
+        }
+        let (tlv, consumed) = match parse_ber_tlv(&bytes[offset..]) {
+            Ok(parsed) => parsed,
+            Err(BerTlvError::UnexpectedEnd(at)) => {
+                return Err(BerTlvError::UnexpectedEnd(offset + at))
+            }
+            Err(e) => return Err(e),
+        };
+        tlvs.push(tlv);
+        offset += consumed;
+    }
+    Ok(tlvs)
+}
+
+/// Returns the first data object that has the given tag.
+pub fn find_ber_tlv(tlvs: &[BerTlv], tag: u32) -> Option<&BerTlv> {
+    tlvs.iter().find(|tlv| tlv.tag == tag)
+}
+
+pub(crate) fn decode_length(bytes: &[u8], offset: usize) -> Result<(usize, usize), BerTlvError> {
+    let first = *bytes.first().ok_or(BerTlvError::UnexpectedEnd(offset))?;
+    if first < 0x80 {
+        return Ok((first as usize, 1));
+    }
+
+    let num_of_bytes = match first {
+        0x81 => 1,
+        0x82 => 2,
+        0x83 => 3,
+        _ => return Err(BerTlvError::UnsupportedLength(first)),
+    };
+    if bytes.len() < 1 + num_of_bytes {
+        return Err(BerTlvError::UnexpectedEnd(offset + bytes.len()));
+    }
+
+    let mut len = 0usize;
+    for b in &bytes[1..=num_of_bytes] {
+        len = (len << 8) | *b as usize;
+    }
+    Ok((len, 1 + num_of_bytes))
+}
+
+pub(crate) fn encode_length(len: usize) -> Vec<u8> {
+    match len {
+        0..=0x7f => Vec::from([len as u8]),
+        0x80..=0xff => Vec::from([0x81, len as u8]),
+        0x100..=0xffff => Vec::from([0x82, (len >> 8) as u8, len as u8]),
+        _ => Vec::from([0x83, (len >> 16) as u8, (len >> 8) as u8, len as u8]),
+    }
+}
+
+pub(crate) fn encode_tag(tag: u32) -> Vec<u8> {
+    let bytes = tag.to_be_bytes();
+    let skip = bytes.iter().take(3).take_while(|b| **b == 0).count();
+    bytes[skip..].to_vec()
+}
+
+impl BerTlv {
+    pub fn get_tag(&self) -> u32 {
+        self.tag
+    }
+
+    pub fn get_value(&self) -> &[u8] {
+        &self.value
+    }
+
+    /// Returns whether the data object is constructed; that is b6 of the first tag byte is set.
+    pub fn is_constructed(&self) -> bool {
+        let first = encode_tag(self.tag)[0];
+        first & 0x20 != 0
+    }
+
+    /// Parses the value as the nested BER-TLV data objects.
+    pub fn get_children(&self) -> Result<Vec<BerTlv>, BerTlvError> {
+        parse_ber_tlvs(&self.value)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = encode_tag(self.tag);
+        bytes.extend(encode_length(self.value.len()));
+        bytes.extend_from_slice(&self.value);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ber_tlv::{find_ber_tlv, new_ber_tlv, parse_ber_tlv, parse_ber_tlvs, BerTlvError};
+
+    #[test]
+    fn should_parse_ber_tlv_successfully() {
+        let (tlv, consumed) =
+            parse_ber_tlv(&[0x62, 0x06, 0x82, 0x01, 0x38, 0x83, 0x01, 0x3f, 0x90]).unwrap();
+        assert_eq!(consumed, 8);
+        assert_eq!(tlv.get_tag(), 0x62);
+        assert!(tlv.is_constructed());
+
+        let children = tlv.get_children().unwrap();
+        assert_eq!(children.len(), 2);
+        assert_eq!(find_ber_tlv(&children, 0x83).unwrap().get_value(), &[0x3f]);
+
+        let (tlv, _) = parse_ber_tlv(&[0xdf, 0x6a, 0x81, 0x01, 0xaa]).unwrap();
+        assert_eq!(tlv.get_tag(), 0xdf6a);
+        assert_eq!(tlv.get_value(), &[0xaa]);
+        assert!(!tlv.is_constructed());
+    }
+
+    #[test]
+    fn should_parse_ber_tlvs_with_padding() {
+        let tlvs = parse_ber_tlvs(&[0x4f, 0x01, 0xa0, 0xff, 0xff, 0x50, 0x00, 0xff]).unwrap();
+        assert_eq!(
+            tlvs,
+            Vec::from([
+                new_ber_tlv(0x4f, Vec::from([0xa0])),
+                new_ber_tlv(0x50, Vec::new())
+            ])
+        );
+    }
+
+    #[test]
+    fn should_fail_parse_ber_tlv_with_truncated_data() {
+        assert_eq!(
+            parse_ber_tlvs(&[0x4f, 0x01, 0xa0, 0x50, 0x03, 0x01]).unwrap_err(),
+            BerTlvError::UnexpectedEnd(6)
+        );
+        assert_eq!(
+            parse_ber_tlv(&[0x4f, 0x84, 0x00, 0x00, 0x00, 0x01]).unwrap_err(),
+            BerTlvError::UnsupportedLength(0x84)
+        );
+    }
+
+    #[test]
+    fn should_encode_ber_tlv() {
+        assert_eq!(
+            new_ber_tlv(0xdf6a, Vec::from([0x01])).to_bytes(),
+            Vec::from([0xdf, 0x6a, 0x01, 0x01])
+        );
+        assert_eq!(
+            new_ber_tlv(0x80, vec![0; 0x80]).to_bytes()[..3],
+            [0x80, 0x81, 0x80]
+        );
+        assert_eq!(
+            new_ber_tlv(0x00, Vec::new()).to_bytes(),
+            Vec::from([0x00, 0x00])
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::command_apdu::{
+    new_command_apdu_builder, CommandAPDU, CommandAPDUError, OwnedCommandAPDU,
+};
+use crate::response_apdu::{new_response_apdu, ResponseAPDU};
+use crate::session::{Session, SessionError};
+
+const MAX_SEGMENT_SIZE: usize = 0xff;
+const MAX_SHORT_MAX_RESPONSE_BYTE_SIZE: u32 = 0x100;
+
+/// b5 of the interindustry class byte and the class byte of ETSI TS 102 221; ref 5.4.1 / ISO/IEC 7816-4
+/// and Table 10.3 / ETSI TS 102 221 V15.0.0
+const COMMAND_CHAINING_BIT: u8 = 0b00010000;
+
+/// The class byte 'AX' of 3GPP TS 51.011 has no chaining bit.
+const GSM_CLASS: u8 = 0b10100000;
+
+/// The class byte 'FF' is invalid; ref 5.4.1 / ISO/IEC 7816-4
+const INVALID_CLASS: u8 = 0xff;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ChainingError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("{0}")]
+    CommandAPDU(#[from] CommandAPDUError),
+    #[error(
+        "command chaining is not allowed for the class byte '{0:02X}'; it must be interindustry or of ETSI TS 102 221, and it must not be 'FF' with the chaining bit"
+    )]
+    ChainingNotAllowed(u8),
+    #[error("the UICC does not support the command chaining")]
+    ChainingNotSupported,
+    #[error("unsuccessful status word of the chained command: '{0:04X}'")]
+    UnsuccessfulStatusWord(u16),
+}
+
+/// Splits the command into the chained commands of the command data up to 255 bytes; ref 5.3.3 / ISO/IEC
+/// 7816-4 and 10.1.1 / ETSI TS 102 221 V15.0.0
+///
+/// b5 of the class byte is set except for the last command, which keeps the class byte and Le of the
+/// command; Le of more than 256 bytes is limited to 256 as the rest is available by GET RESPONSE. The
+/// chaining bit is defined for the interindustry classes ('0X' to '7X') and for the classes of ETSI TS 102
+/// 221 ('8X', 'CX' and 'EX') of both the standard and the extended logical channels, so the class 'AX' is
+/// refused. 'EF' is refused too, as the chaining bit makes it the invalid class byte 'FF'.
+pub fn new_chained_commands(command: &CommandAPDU) -> Result<Vec<OwnedCommandAPDU>, ChainingError> {
+    split_command(&command.to_owned_command_apdu()?)
+}
+
+/// Splits the owned command into the chained commands; see [`new_chained_commands`].
+pub fn split_command(command: &OwnedCommandAPDU) -> Result<Vec<OwnedCommandAPDU>, ChainingError> {
+    let data = match command.get_command_data() {
+        Some(data) if data.len() > MAX_SEGMENT_SIZE => data,
+        _ => return Ok(Vec::from([command.clone()])),
+    };
+    let cla = command.get_cla();
+    if cla & 0b11100000 == GSM_CLASS || cla | COMMAND_CHAINING_BIT == INVALID_CLASS {
+        return Err(ChainingError::ChainingNotAllowed(cla));
+    }
+
+    let segments: Vec<&[u8]> = data.chunks(MAX_SEGMENT_SIZE).collect();
+    let last = segments.len() - 1;
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let builder = new_command_apdu_builder()
+                .ins(command.get_ins())
+                .p1(command.get_p1())
+                .p2(command.get_p2())
+                .data(segment);
+            if i < last {
+                return builder.cla(cla | COMMAND_CHAINING_BIT).build();
+            }
+            match command.get_max_response_byte_size() {
+                Some(ne) => builder
+                    .cla(cla)
+                    .le(ne.min(MAX_SHORT_MAX_RESPONSE_BYTE_SIZE))
+                    .build(),
+                None => builder.cla(cla).build(),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ChainingError::from)
+}
+
+/// Transmits the command by the chained commands and returns the response that reassembles the data of
+/// all the responses with the status word of the last one. '6884' means that the UICC does not support the
+/// command chaining.
+pub fn transmit_chained(
+    session: &mut Session,
+    command: &CommandAPDU,
+) -> Result<ResponseAPDU, ChainingError> {
+    let commands = new_chained_commands(command)?;
+    let last = commands.len() - 1;
+    let mut data = Vec::new();
+    for (i, command) in commands.iter().enumerate() {
+        let response = session.transmit_owned(command)?;
+        data.extend_from_slice(response.get_data());
+        if i == last {
+            return Ok(new_response_apdu(
+                data,
+                response.get_sw1(),
+                response.get_sw2(),
+            ));
+        }
+        match response.get_status_word() {
+            _ if response.is_normal_ending() => {}
+            0x6884 => return Err(ChainingError::ChainingNotSupported),
+            sw => return Err(ChainingError::UnsuccessfulStatusWord(sw)),
+        }
+    }
+    unreachable!("the chained commands are never empty")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::chaining::{new_chained_commands, split_command, transmit_chained, ChainingError};
+    use crate::class::{
+        new_extended_class, new_standard_class, ClassTypeForExtendedLogicalChannels,
+        ClassTypeForStandardLogicalChannels, SecureMessagingIndicationForExtendedLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::command_apdu::{new_extended_command_apdu, parse_command_apdu};
+    use crate::instruction::{SetData, UpdateBinary};
+    use crate::session::{new_session, SessionConfig};
+    use crate::testing::new_scripted_card;
+
+    #[test]
+    fn should_split_command_into_chained_commands() {
+        let class = new_extended_class(
+            ClassTypeForExtendedLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForExtendedLogicalChannels::NoSM,
+            2,
+        )
+        .unwrap();
+        let data = [0x55; 600];
+        let command =
+            new_extended_command_apdu(&class, &UpdateBinary {}, 0x00, 0x00, Some(0), Some(&data));
+        let commands = new_chained_commands(&command).unwrap();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0].get_cla(), 0x52);
+        assert_eq!(commands[1].get_cla(), 0x52);
+        assert_eq!(commands[2].get_cla(), 0x42);
+        assert_eq!(commands[0].get_max_response_byte_size(), None);
+        assert_eq!(commands[2].get_max_response_byte_size(), Some(256));
+        assert_eq!(commands[2].get_command_data().unwrap().len(), 600 - 510);
+        assert!(commands.iter().all(|c| !c.is_extended()));
+
+        // UPDATE BINARY of 3GPP TS 51.011
+        let mut bytes = Vec::from([0xa0, 0xd6, 0x00, 0x00, 0x00, 0x02, 0x58]);
+        bytes.extend_from_slice(&data);
+        assert_eq!(
+            split_command(&parse_command_apdu(&bytes).unwrap()).unwrap_err(),
+            ChainingError::ChainingNotAllowed(0xa0)
+        );
+    }
+
+    #[test]
+    fn should_chain_commands_of_ts_102_221_classes() {
+        let data = [0x55; 300];
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            1,
+        )
+        .unwrap();
+        let command = new_extended_command_apdu(&class, &SetData {}, 0x00, 0x00, None, Some(&data));
+        let commands = new_chained_commands(&command).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].get_cla(), 0x91);
+        assert_eq!(commands[1].get_cla(), 0x81);
+
+        for (sm, cla) in [
+            (
+                SecureMessagingIndicationForExtendedLogicalChannels::NoSM,
+                0xc7,
+            ),
+            (
+                SecureMessagingIndicationForExtendedLogicalChannels::CommandHeaderNotAuthenticated,
+                0xe7,
+            ),
+        ] {
+            let class =
+                new_extended_class(ClassTypeForExtendedLogicalChannels::TS102_221, sm, 7).unwrap();
+            let command =
+                new_extended_command_apdu(&class, &SetData {}, 0x00, 0x00, None, Some(&data));
+            let commands = new_chained_commands(&command).unwrap();
+            assert_eq!(commands[0].get_cla(), cla | 0x10);
+            assert_eq!(commands[1].get_cla(), cla);
+        }
+
+        // the secure messaging on the logical channel 19, i.e. b4 to b1 of '1111', would be chained by 'FF'
+        let class = new_extended_class(
+            ClassTypeForExtendedLogicalChannels::TS102_221,
+            SecureMessagingIndicationForExtendedLogicalChannels::CommandHeaderNotAuthenticated,
+            15,
+        )
+        .unwrap();
+        let command = new_extended_command_apdu(&class, &SetData {}, 0x00, 0x00, None, Some(&data));
+        assert_eq!(
+            new_chained_commands(&command).unwrap_err(),
+            ChainingError::ChainingNotAllowed(0xef)
+        );
+    }
+
+    #[test]
+    fn should_transmit_chained_commands() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            1,
+        )
+        .unwrap();
+        let data = [0x55; 300];
+        let command =
+            new_extended_command_apdu(&class, &UpdateBinary {}, 0x00, 0x00, None, Some(&data));
+        let mut card = new_scripted_card(&[&[0x90, 0x00], &[0x90, 0x00], &[0x68, 0x84]]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        let response = transmit_chained(&mut session, &command).unwrap();
+        assert_eq!(response.get_status_word(), 0x9000);
+        assert_eq!(
+            transmit_chained(&mut session, &command).unwrap_err(),
+            ChainingError::ChainingNotSupported
+        );
+        assert_eq!(card.commands[0][..5], [0x11, 0xd6, 0x00, 0x00, 0xff]);
+        assert_eq!(card.commands[1][..5], [0x01, 0xd6, 0x00, 0x00, 0x2d]);
+    }
+}
@@ -57,6 +57,11 @@ pub fn new_standard_class(
     })
 }
 
+/// Creates the class of the basic logical channel without secure messaging, which is always valid.
+pub fn new_basic_class(typ: ClassTypeForStandardLogicalChannels) -> Class {
+    Class { byte: typ as u8 }
+}
+
 pub fn new_extended_class(
     typ: ClassTypeForExtendedLogicalChannels,
     secure_messaging_indication: SecureMessagingIndicationForExtendedLogicalChannels,
@@ -82,8 +87,9 @@ impl Class {
 #[cfg(test)]
 mod test {
     use crate::class::{
-        new_extended_class, new_standard_class, ClassError, ClassTypeForExtendedLogicalChannels,
-        ClassTypeForStandardLogicalChannels, SecureMessagingIndicationForExtendedLogicalChannels,
+        new_basic_class, new_extended_class, new_standard_class, ClassError,
+        ClassTypeForExtendedLogicalChannels, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForExtendedLogicalChannels,
         SecureMessagingIndicationForStandardLogicalChannels,
     };
 
@@ -97,6 +103,14 @@ mod test {
         assert_eq!(result.unwrap().get_byte(), 0b00000000);
     }
 
+    #[test]
+    fn should_new_basic_class_successfully() {
+        assert_eq!(
+            new_basic_class(ClassTypeForStandardLogicalChannels::TS102_221).get_byte(),
+            0b10000000
+        );
+    }
+
     #[test]
     fn should_new_extended_class_successfully() {
         let result = new_extended_class(
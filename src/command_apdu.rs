@@ -4,16 +4,23 @@ use thiserror::Error;
 use crate::class::Class;
 use crate::instruction::Instruction;
 
-/// CommandAPDU: ref 10.1.0 / ETSI TS 102 221 V15.0.0
+const MAX_SHORT_COMMAND_DATA_LENGTH: usize = 0xff;
+const MAX_SHORT_EXPECTED_LENGTH: u32 = 0x100;
+const MAX_EXTENDED_COMMAND_DATA_LENGTH: usize = 0xffff;
+
+/// CommandAPDU: ref 10.1.0 / ETSI TS 102 221 V15.0.0 and 5.1 / ISO/IEC 7816-4
 pub struct CommandAPDU<'a> {
     class: &'a Class,
     instruction: &'a dyn Instruction,
     p1: u8,
     p2: u8,
-    max_response_byte_size: Option<u8>,
+    /// Ne, the maximum number of the response bytes that Le encodes
+    max_response_byte_size: Option<u32>,
     command_data: Option<&'a [u8]>,
 }
 
+/// Creates the command APDU of the short Le; Le '00' means 256 bytes. The empty command data is regarded as
+/// absent, as Lc cannot encode zero bytes.
 pub fn new_command_apdu<'a>(
     class: &'a Class,
     instruction: &'a dyn Instruction,
@@ -27,45 +34,315 @@ pub fn new_command_apdu<'a>(
         instruction,
         p1,
         p2,
-        max_response_byte_size: le,
-        command_data,
+        max_response_byte_size: le.map(|le| if le == 0 { 0x100 } else { le as u32 }),
+        command_data: command_data.filter(|data| !data.is_empty()),
     }
 }
 
+/// Creates the command APDU that may need the extended Lc and Le; Le '0000' means 65536 bytes. It is encoded
+/// in the short form as long as the command data and Le fit in it, and the empty command data is regarded as
+/// absent.
+pub fn new_extended_command_apdu<'a>(
+    class: &'a Class,
+    instruction: &'a dyn Instruction,
+    p1: u8,
+    p2: u8,
+    le: Option<u16>,
+    command_data: Option<&'a [u8]>,
+) -> CommandAPDU<'a> {
+    CommandAPDU {
+        class,
+        instruction,
+        p1,
+        p2,
+        max_response_byte_size: le.map(|le| if le == 0 { 0x10000 } else { le as u32 }),
+        command_data: command_data.filter(|data| !data.is_empty()),
+    }
+}
+
+/// Returns whether the command bytes are encoded by the extended Lc or Le, i.e. '00' follows the header and
+/// two or more bytes follow it.
+pub fn is_extended_length(command: &[u8]) -> bool {
+    command.len() >= 7 && command[4] == 0x00
+}
+
+/// OwnedCommandAPDU: the command APDU that owns its header and body, e.g. to be queued, sent across the
+/// threads or returned from the functions; `CommandAPDU` is kept for the paths without the allocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedCommandAPDU {
+    cla: u8,
+    ins: u8,
+    p1: u8,
+    p2: u8,
+    /// Ne, the maximum number of the response bytes that Le encodes
+    max_response_byte_size: Option<u32>,
+    command_data: Option<Vec<u8>>,
+}
+
+/// CommandAPDUBuilder builds `OwnedCommandAPDU` from the raw bytes of the header and the body; the class
+/// byte 'FF' and the instruction bytes '6X' and '9X' are invalid; ref 5.1 / ISO/IEC 7816-4
+#[derive(Debug, Default)]
+pub struct CommandAPDUBuilder {
+    cla: u8,
+    ins: u8,
+    p1: u8,
+    p2: u8,
+    max_response_byte_size: Option<u32>,
+    command_data: Option<Vec<u8>>,
+}
+
 #[derive(Debug, Error, PartialEq)]
 pub enum CommandAPDUError {
     #[error("failed to construct command APDU bytes: {0}")]
     FailedBytesConstruction(String),
-    #[error("illegal length of the command data; this must be within [0, 255], but {0}")]
+    #[error("illegal length of the command data; this must be within [0, 65535], but {0}")]
     IllegalCommandDataLength(usize),
+    #[error("illegal Ne; this must be within [1, 65536], but {0}")]
+    IllegalMaxResponseByteSize(u32),
+    #[error("malformed command APDU of {0} bytes")]
+    MalformedCommandAPDU(usize),
+    #[error("invalid class byte 'FF'")]
+    InvalidClassByte,
+    #[error("invalid instruction byte '{0:02X}'; '6X' and '9X' are reserved for the status words")]
+    InvalidInstructionByte(u8),
+}
+
+pub fn new_command_apdu_builder() -> CommandAPDUBuilder {
+    CommandAPDUBuilder::default()
+}
+
+/// Parses the command bytes of the short or the extended form into `OwnedCommandAPDU`; ref 5.1 / ISO/IEC
+/// 7816-4
+pub fn parse_command_apdu(bytes: &[u8]) -> Result<OwnedCommandAPDU, CommandAPDUError> {
+    let len = bytes.len();
+    if len < 4 {
+        return Err(CommandAPDUError::MalformedCommandAPDU(len));
+    }
+    let short_ne = |b: u8| if b == 0 { 0x100 } else { b as u32 };
+    let extended_ne = |b: &[u8]| match u16::from_be_bytes([b[0], b[1]]) {
+        0 => 0x10000,
+        ne => ne as u32,
+    };
+    let (command_data, max_response_byte_size) = match len {
+        4 => (None, None),
+        5 => (None, Some(short_ne(bytes[4]))),
+        _ if bytes[4] != 0x00 => {
+            let nc = bytes[4] as usize;
+            match len - 5 {
+                n if n == nc => (Some(&bytes[5..]), None),
+                n if n == nc + 1 => (Some(&bytes[5..len - 1]), Some(short_ne(bytes[len - 1]))),
+                _ => return Err(CommandAPDUError::MalformedCommandAPDU(len)),
+            }
+        }
+        7 => (None, Some(extended_ne(&bytes[5..]))),
+        _ if len > 7 => {
+            let nc = u16::from_be_bytes([bytes[5], bytes[6]]) as usize;
+            match len - 7 {
+                n if n == nc => (Some(&bytes[7..]), None),
+                n if n == nc + 2 => (
+                    Some(&bytes[7..len - 2]),
+                    Some(extended_ne(&bytes[len - 2..])),
+                ),
+                _ => return Err(CommandAPDUError::MalformedCommandAPDU(len)),
+            }
+        }
+        _ => return Err(CommandAPDUError::MalformedCommandAPDU(len)),
+    };
+    Ok(OwnedCommandAPDU {
+        cla: bytes[0],
+        ins: bytes[1],
+        p1: bytes[2],
+        p2: bytes[3],
+        max_response_byte_size,
+        command_data: command_data.map(|data| data.to_vec()),
+    })
+}
+
+fn is_extended(command_data: Option<&[u8]>, max_response_byte_size: Option<u32>) -> bool {
+    command_data.is_some_and(|data| data.len() > MAX_SHORT_COMMAND_DATA_LENGTH)
+        || max_response_byte_size.is_some_and(|ne| ne > MAX_SHORT_EXPECTED_LENGTH)
+}
+
+/// Encodes the command in the short form, or in the extended form where Lc is '00' and 2 bytes, and Le is
+/// 2 bytes after Lc or '00' and 2 bytes without Lc; ref 5.1 / ISO/IEC 7816-4
+fn encode(
+    header: [u8; 4],
+    command_data: Option<&[u8]>,
+    max_response_byte_size: Option<u32>,
+) -> Result<Vec<u8>, CommandAPDUError> {
+    let mut bytes = Vec::from(header);
+    let extended = is_extended(command_data, max_response_byte_size);
+
+    if let Some(command_data_bytes) = command_data {
+        let command_data_bytes_len = command_data_bytes.len();
+        if command_data_bytes_len > MAX_EXTENDED_COMMAND_DATA_LENGTH {
+            return Err(CommandAPDUError::IllegalCommandDataLength(
+                command_data_bytes_len,
+            ));
+        }
+        if extended {
+            bytes.push(0x00);
+            bytes.extend_from_slice(&(command_data_bytes_len as u16).to_be_bytes());
+        } else {
+            bytes.push(command_data_bytes_len as u8);
+        }
+        bytes.extend_from_slice(command_data_bytes);
+    }
+
+    if let Some(max_response_byte_size) = max_response_byte_size {
+        if extended {
+            if command_data.is_none() {
+                bytes.push(0x00);
+            }
+            // 65536 is encoded as '0000'
+            bytes.extend_from_slice(&(max_response_byte_size as u16).to_be_bytes());
+        } else {
+            // 256 is encoded as '00'
+            bytes.push(max_response_byte_size as u8);
+        }
+    }
+
+    Ok(bytes)
 }
 
 impl<'a> CommandAPDU<'a> {
+    /// Returns Ne, the maximum number of the response bytes; `None` when Le is absent.
+    pub fn get_max_response_byte_size(&self) -> Option<u32> {
+        self.max_response_byte_size
+    }
+
+    /// Returns whether the command needs the extended Lc and Le: the command data is longer than 255 bytes
+    /// or Ne is more than 256 bytes.
+    pub fn is_extended(&self) -> bool {
+        is_extended(self.command_data, self.max_response_byte_size)
+    }
+
     pub fn to_bytes(&self) -> Result<Vec<u8>, CommandAPDUError> {
         let instruction_byte = match self.instruction.get_byte(self.class) {
             Ok(b) => b,
             Err(e) => return Err(CommandAPDUError::FailedBytesConstruction(e.to_string())),
         };
+        encode(
+            [self.class.get_byte(), instruction_byte, self.p1, self.p2],
+            self.command_data,
+            self.max_response_byte_size,
+        )
+    }
+
+    /// Copies the command into `OwnedCommandAPDU`, validating the instruction for the class.
+    pub fn to_owned_command_apdu(&self) -> Result<OwnedCommandAPDU, CommandAPDUError> {
+        let instruction_byte = match self.instruction.get_byte(self.class) {
+            Ok(b) => b,
+            Err(e) => return Err(CommandAPDUError::FailedBytesConstruction(e.to_string())),
+        };
+        Ok(OwnedCommandAPDU {
+            cla: self.class.get_byte(),
+            ins: instruction_byte,
+            p1: self.p1,
+            p2: self.p2,
+            max_response_byte_size: self.max_response_byte_size,
+            command_data: self.command_data.map(|data| data.to_vec()),
+        })
+    }
+}
 
-        let mut bytes = Vec::from([self.class.get_byte(), instruction_byte, self.p1, self.p2]);
+impl CommandAPDUBuilder {
+    pub fn cla(mut self, cla: u8) -> Self {
+        self.cla = cla;
+        self
+    }
 
-        if self.command_data.is_some() {
-            let command_data_bytes = self.command_data.unwrap();
-            let command_data_bytes_len = command_data_bytes.len();
-            if command_data_bytes_len > 255 {
-                return Err(CommandAPDUError::IllegalCommandDataLength(
-                    command_data_bytes_len,
-                ));
+    pub fn ins(mut self, ins: u8) -> Self {
+        self.ins = ins;
+        self
+    }
+
+    pub fn p1(mut self, p1: u8) -> Self {
+        self.p1 = p1;
+        self
+    }
+
+    pub fn p2(mut self, p2: u8) -> Self {
+        self.p2 = p2;
+        self
+    }
+
+    /// Sets the command data; the empty data is regarded as absent.
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.command_data = Some(data.to_vec()).filter(|data| !data.is_empty());
+        self
+    }
+
+    /// Sets Ne, the maximum number of the response bytes, within [1, 65536].
+    pub fn le(mut self, max_response_byte_size: u32) -> Self {
+        self.max_response_byte_size = Some(max_response_byte_size);
+        self
+    }
+
+    pub fn build(self) -> Result<OwnedCommandAPDU, CommandAPDUError> {
+        if self.cla == 0xff {
+            return Err(CommandAPDUError::InvalidClassByte);
+        }
+        if matches!(self.ins & 0xf0, 0x60 | 0x90) {
+            return Err(CommandAPDUError::InvalidInstructionByte(self.ins));
+        }
+        if let Some(data) = &self.command_data {
+            if data.len() > MAX_EXTENDED_COMMAND_DATA_LENGTH {
+                return Err(CommandAPDUError::IllegalCommandDataLength(data.len()));
             }
-            bytes.push(command_data_bytes_len as u8);
-            bytes.extend_from_slice(command_data_bytes);
         }
-
-        if self.max_response_byte_size.is_some() {
-            bytes.push(self.max_response_byte_size.unwrap())
+        if let Some(ne) = self.max_response_byte_size {
+            if ne == 0 || ne > 0x10000 {
+                return Err(CommandAPDUError::IllegalMaxResponseByteSize(ne));
+            }
         }
+        Ok(OwnedCommandAPDU {
+            cla: self.cla,
+            ins: self.ins,
+            p1: self.p1,
+            p2: self.p2,
+            max_response_byte_size: self.max_response_byte_size,
+            command_data: self.command_data,
+        })
+    }
+}
+
+impl OwnedCommandAPDU {
+    pub fn get_cla(&self) -> u8 {
+        self.cla
+    }
+
+    pub fn get_ins(&self) -> u8 {
+        self.ins
+    }
+
+    pub fn get_p1(&self) -> u8 {
+        self.p1
+    }
+
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn get_command_data(&self) -> Option<&[u8]> {
+        self.command_data.as_deref()
+    }
+
+    /// Returns Ne, the maximum number of the response bytes; `None` when Le is absent.
+    pub fn get_max_response_byte_size(&self) -> Option<u32> {
+        self.max_response_byte_size
+    }
+
+    pub fn is_extended(&self) -> bool {
+        is_extended(self.command_data.as_deref(), self.max_response_byte_size)
+    }
 
-        Ok(bytes)
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CommandAPDUError> {
+        encode(
+            [self.cla, self.ins, self.p1, self.p2],
+            self.command_data.as_deref(),
+            self.max_response_byte_size,
+        )
     }
 }
 
@@ -75,8 +352,11 @@ mod test {
         new_standard_class, ClassTypeForStandardLogicalChannels,
         SecureMessagingIndicationForStandardLogicalChannels,
     };
-    use crate::command_apdu::new_command_apdu;
-    use crate::instruction::SelectFile;
+    use crate::command_apdu::{
+        is_extended_length, new_command_apdu, new_command_apdu_builder, new_extended_command_apdu,
+        parse_command_apdu, CommandAPDUError, OwnedCommandAPDU,
+    };
+    use crate::instruction::{ReadBinary, SelectFile, UpdateBinary};
 
     #[test]
     fn should_construct_command_with_data() {
@@ -102,4 +382,145 @@ mod test {
             Vec::from([0x00, 0xa4, 0x00, 0x04, 0x02, 0x6f, 0x61])
         );
     }
+
+    #[test]
+    fn should_choose_short_or_extended_length() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let apdu = new_extended_command_apdu(&class, &ReadBinary {}, 0x00, 0x00, Some(0x100), None);
+        assert!(!apdu.is_extended());
+        assert_eq!(
+            apdu.to_bytes().unwrap(),
+            Vec::from([0x00, 0xb0, 0x00, 0x00, 0x00])
+        );
+
+        let apdu = new_extended_command_apdu(&class, &ReadBinary {}, 0x00, 0x00, Some(0), None);
+        assert_eq!(apdu.get_max_response_byte_size(), Some(65536));
+        let bytes = apdu.to_bytes().unwrap();
+        assert_eq!(bytes, Vec::from([0x00, 0xb0, 0x00, 0x00, 0x00, 0x00, 0x00]));
+        assert!(is_extended_length(&bytes));
+
+        let data = [0xaa; 300];
+        let apdu = new_extended_command_apdu(
+            &class,
+            &UpdateBinary {},
+            0x00,
+            0x00,
+            Some(0x0200),
+            Some(&data),
+        );
+        let bytes = apdu.to_bytes().unwrap();
+        assert_eq!(bytes[..8], [0x00, 0xd6, 0x00, 0x00, 0x00, 0x01, 0x2c, 0xaa]);
+        assert_eq!(bytes[bytes.len() - 2..], [0x02, 0x00]);
+        assert_eq!(bytes.len(), 4 + 3 + 300 + 2);
+
+        // no Lc for the empty command data
+        let apdu = new_command_apdu(&class, &UpdateBinary {}, 0x00, 0x00, None, Some(&[]));
+        assert_eq!(
+            apdu.to_bytes().unwrap(),
+            Vec::from([0x00, 0xd6, 0x00, 0x00])
+        );
+        let apdu =
+            new_extended_command_apdu(&class, &ReadBinary {}, 0x00, 0x00, Some(0), Some(&[]));
+        assert_eq!(
+            apdu.to_bytes().unwrap(),
+            Vec::from([0x00, 0xb0, 0x00, 0x00, 0x00, 0x00, 0x00])
+        );
+
+        let data = [0xaa; 0x10000];
+        let apdu = new_command_apdu(&class, &UpdateBinary {}, 0x00, 0x00, None, Some(&data));
+        assert_eq!(
+            apdu.to_bytes().unwrap_err(),
+            CommandAPDUError::IllegalCommandDataLength(0x10000)
+        );
+    }
+
+    #[test]
+    fn should_build_owned_command_apdu() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<OwnedCommandAPDU>();
+
+        let apdu = new_command_apdu_builder()
+            .cla(0x00)
+            .ins(0xa4)
+            .p1(0x00)
+            .p2(0x04)
+            .data(&[0x2f, 0x00])
+            .le(256)
+            .build()
+            .unwrap();
+        assert_eq!(
+            apdu.to_bytes().unwrap(),
+            Vec::from([0x00, 0xa4, 0x00, 0x04, 0x02, 0x2f, 0x00, 0x00])
+        );
+        let queued = Vec::from([apdu.clone()]);
+        assert_eq!(queued[0], apdu);
+
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let data = [0x2f, 0x00];
+        let borrowed =
+            new_command_apdu(&class, &SelectFile {}, 0x00, 0x04, Some(0x00), Some(&data));
+        assert_eq!(borrowed.to_owned_command_apdu().unwrap(), apdu);
+
+        let empty = new_command_apdu_builder()
+            .ins(0xf2)
+            .data(&[])
+            .build()
+            .unwrap();
+        assert_eq!(empty.get_command_data(), None);
+        assert_eq!(
+            empty.to_bytes().unwrap(),
+            Vec::from([0x00, 0xf2, 0x00, 0x00])
+        );
+
+        assert_eq!(
+            new_command_apdu_builder().le(0).build().unwrap_err(),
+            CommandAPDUError::IllegalMaxResponseByteSize(0)
+        );
+        assert_eq!(
+            new_command_apdu_builder().cla(0xff).build().unwrap_err(),
+            CommandAPDUError::InvalidClassByte
+        );
+        for ins in [0x61, 0x6c, 0x90, 0x9f] {
+            assert_eq!(
+                new_command_apdu_builder().ins(ins).build().unwrap_err(),
+                CommandAPDUError::InvalidInstructionByte(ins)
+            );
+        }
+    }
+
+    #[test]
+    fn should_parse_command_apdu() {
+        for bytes in [
+            Vec::from([0x00, 0xa4, 0x00, 0x04, 0x02, 0x2f, 0x00, 0x00]),
+            Vec::from([0x00, 0xb0, 0x00, 0x00, 0x00, 0x02, 0x00]),
+            Vec::from([0x80, 0xf2, 0x00, 0x0c]),
+            Vec::from([0x00, 0xc0, 0x00, 0x00, 0x10]),
+        ] {
+            assert_eq!(
+                parse_command_apdu(&bytes).unwrap().to_bytes().unwrap(),
+                bytes
+            );
+        }
+        let mut bytes = Vec::from([0x00, 0xd6, 0x00, 0x00, 0x00, 0x01, 0x00]);
+        bytes.extend_from_slice(&[0xaa; 256]);
+        bytes.extend_from_slice(&[0x00, 0x00]);
+        let apdu = parse_command_apdu(&bytes).unwrap();
+        assert_eq!(apdu.get_command_data().unwrap().len(), 256);
+        assert_eq!(apdu.get_max_response_byte_size(), Some(65536));
+        assert_eq!(
+            parse_command_apdu(&[0x00, 0xa4, 0x00, 0x04, 0x02, 0x2f]).unwrap_err(),
+            CommandAPDUError::MalformedCommandAPDU(6)
+        );
+    }
 }
@@ -0,0 +1,293 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{decode_length, encode_length, BerTlvError};
+
+pub const COMMAND_DETAILS_TAG: u16 = 0x01;
+pub const DEVICE_IDENTITY_TAG: u16 = 0x02;
+pub const RESULT_TAG: u16 = 0x03;
+pub const DURATION_TAG: u16 = 0x04;
+pub const ALPHA_IDENTIFIER_TAG: u16 = 0x05;
+pub const ADDRESS_TAG: u16 = 0x06;
+pub const SMS_TPDU_TAG: u16 = 0x0b;
+pub const CELL_BROADCAST_PAGE_TAG: u16 = 0x0c;
+pub const TEXT_STRING_TAG: u16 = 0x0d;
+pub const ITEM_TAG: u16 = 0x0f;
+pub const ITEM_IDENTIFIER_TAG: u16 = 0x10;
+pub const RESPONSE_LENGTH_TAG: u16 = 0x11;
+pub const FILE_LIST_TAG: u16 = 0x12;
+pub const LOCATION_INFORMATION_TAG: u16 = 0x13;
+pub const HELP_REQUEST_TAG: u16 = 0x15;
+pub const DEFAULT_TEXT_TAG: u16 = 0x17;
+pub const EVENT_LIST_TAG: u16 = 0x19;
+pub const LOCATION_STATUS_TAG: u16 = 0x1b;
+pub const TRANSACTION_IDENTIFIER_TAG: u16 = 0x1c;
+pub const ICON_IDENTIFIER_TAG: u16 = 0x1e;
+pub const ITEM_ICON_IDENTIFIER_LIST_TAG: u16 = 0x1f;
+pub const TIMER_IDENTIFIER_TAG: u16 = 0x24;
+pub const TIMER_VALUE_TAG: u16 = 0x25;
+pub const IMMEDIATE_RESPONSE_TAG: u16 = 0x2b;
+pub const AID_TAG: u16 = 0x2f;
+pub const BEARER_DESCRIPTION_TAG: u16 = 0x35;
+pub const CHANNEL_DATA_TAG: u16 = 0x36;
+pub const CHANNEL_DATA_LENGTH_TAG: u16 = 0x37;
+pub const CHANNEL_STATUS_TAG: u16 = 0x38;
+pub const BUFFER_SIZE_TAG: u16 = 0x39;
+pub const TRANSPORT_LEVEL_TAG: u16 = 0x3c;
+pub const OTHER_ADDRESS_TAG: u16 = 0x3e;
+pub const TEXT_ATTRIBUTE_TAG: u16 = 0x50;
+pub const ITEM_TEXT_ATTRIBUTE_LIST_TAG: u16 = 0x51;
+pub const FRAME_IDENTIFIER_TAG: u16 = 0x68;
+
+/// The tags that this crate understands; a data object of the other tags with the comprehension required
+/// flag must be answered by "command data not understood by terminal".
+const KNOWN_TAGS: [u16; 35] = [
+    COMMAND_DETAILS_TAG,
+    DEVICE_IDENTITY_TAG,
+    RESULT_TAG,
+    DURATION_TAG,
+    ALPHA_IDENTIFIER_TAG,
+    ADDRESS_TAG,
+    SMS_TPDU_TAG,
+    CELL_BROADCAST_PAGE_TAG,
+    TEXT_STRING_TAG,
+    ITEM_TAG,
+    ITEM_IDENTIFIER_TAG,
+    RESPONSE_LENGTH_TAG,
+    FILE_LIST_TAG,
+    LOCATION_INFORMATION_TAG,
+    HELP_REQUEST_TAG,
+    DEFAULT_TEXT_TAG,
+    EVENT_LIST_TAG,
+    LOCATION_STATUS_TAG,
+    TRANSACTION_IDENTIFIER_TAG,
+    TIMER_IDENTIFIER_TAG,
+    TIMER_VALUE_TAG,
+    IMMEDIATE_RESPONSE_TAG,
+    AID_TAG,
+    BEARER_DESCRIPTION_TAG,
+    CHANNEL_DATA_TAG,
+    CHANNEL_DATA_LENGTH_TAG,
+    CHANNEL_STATUS_TAG,
+    BUFFER_SIZE_TAG,
+    TRANSPORT_LEVEL_TAG,
+    OTHER_ADDRESS_TAG,
+    ICON_IDENTIFIER_TAG,
+    ITEM_ICON_IDENTIFIER_LIST_TAG,
+    TEXT_ATTRIBUTE_TAG,
+    ITEM_TEXT_ATTRIBUTE_LIST_TAG,
+    FRAME_IDENTIFIER_TAG,
+];
+
+/// ComprehensionTlv: COMPREHENSION-TLV data object; ref 7.1.1 / ETSI TS 101 220 V15.0.0
+///
+/// The tag is held without the comprehension required flag; the single byte format is used for the tags
+/// within ['01', '7E'] and the three byte format is used for the others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComprehensionTlv {
+    tag: u16,
+    comprehension_required: bool,
+    value: Vec<u8>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ComprehensionTlvError {
+    #[error("unexpected end of the COMPREHENSION-TLV data at offset {0}")]
+    UnexpectedEnd(usize),
+    #[error("invalid tag of the COMPREHENSION-TLV: '{0:02X}'")]
+    InvalidTag(u8),
+    #[error("invalid length of the COMPREHENSION-TLV: {0}")]
+    InvalidLength(BerTlvError),
+    #[error("data object '{0:02X}' is comprehension required but not understood")]
+    UnknownComprehensionRequiredTag(u16),
+}
+
+pub fn new_comprehension_tlv(
+    tag: u16,
+    comprehension_required: bool,
+    value: Vec<u8>,
+) -> ComprehensionTlv {
+    ComprehensionTlv {
+        tag,
+        comprehension_required,
+        value,
+    }
+}
+
+/// Parses a COMPREHENSION-TLV data object at the beginning of the bytes and returns it with the number of
+/// consumed bytes.
+pub fn parse_comprehension_tlv(
+    bytes: &[u8],
+) -> Result<(ComprehensionTlv, usize), ComprehensionTlvError> {
+    let first = *bytes
+        .first()
+        .ok_or(ComprehensionTlvError::UnexpectedEnd(0))?;
+    let (tag, comprehension_required, mut offset) = match first {
+        0x00 | 0x80 | 0xff => return Err(ComprehensionTlvError::InvalidTag(first)),
+        0x7f => {
+            if bytes.len() < 3 {
+                return Err(ComprehensionTlvError::UnexpectedEnd(bytes.len()));
+            }
+            let tag = u16::from_be_bytes([bytes[1], bytes[2]]);
+            (tag & 0x7fff, tag & 0x8000 != 0, 3)
+        }
+        _ => ((first & 0x7f) as u16, first & 0x80 != 0, 1),
+    };
+
+    let (len, len_size) = decode_length(&bytes[offset..], offset).map_err(|e| match e {
+        BerTlvError::UnexpectedEnd(at) => ComprehensionTlvError::UnexpectedEnd(at),
+        e => ComprehensionTlvError::InvalidLength(e),
+    })?;
+    offset += len_size;
+
+    let end = offset + len;
+    if bytes.len() < end {
+        return Err(ComprehensionTlvError::UnexpectedEnd(bytes.len()));
+    }
+
+    Ok((
+        ComprehensionTlv {
+            tag,
+            comprehension_required,
+            value: bytes[offset..end].to_vec(),
+        },
+        end,
+    ))
+}
+
+/// Parses the sequence of COMPREHENSION-TLV data objects.
+pub fn parse_comprehension_tlvs(
+    bytes: &[u8],
+) -> Result<Vec<ComprehensionTlv>, ComprehensionTlvError> {
+    let mut tlvs = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let (tlv, consumed) = match parse_comprehension_tlv(&bytes[offset..]) {
+            Ok(parsed) => parsed,
+            Err(ComprehensionTlvError::UnexpectedEnd(at)) => {
+                return Err(ComprehensionTlvError::UnexpectedEnd(offset + at))
+            }
+            Err(e) => return Err(e),
+        };
+        tlvs.push(tlv);
+        offset += consumed;
+    }
+    Ok(tlvs)
+}
+
+/// Returns the first data object that has the given tag.
+pub fn find_comprehension_tlv(tlvs: &[ComprehensionTlv], tag: u16) -> Option<&ComprehensionTlv> {
+    tlvs.iter().find(|tlv| tlv.tag == tag)
+}
+
+/// Returns whether the tag is one of the data objects that this crate understands.
+pub fn is_known_tag(tag: u16) -> bool {
+    KNOWN_TAGS.contains(&tag)
+}
+
+/// Checks that every data object with the comprehension required flag is understood; ref 6.10.5 / ETSI TS
+/// 102 223 V15.0.0
+///
+/// The data objects of the unknown tags without the flag are ignored as the terminal does.
+pub fn check_comprehension_required(
+    tlvs: &[ComprehensionTlv],
+) -> Result<(), ComprehensionTlvError> {
+    match tlvs
+        .iter()
+        .find(|tlv| tlv.comprehension_required && !is_known_tag(tlv.tag))
+    {
+        Some(tlv) => Err(ComprehensionTlvError::UnknownComprehensionRequiredTag(
+            tlv.tag,
+        )),
+        None => Ok(()),
+    }
+}
+
+impl ComprehensionTlv {
+    pub fn get_tag(&self) -> u16 {
+        self.tag
+    }
+
+    pub fn is_comprehension_required(&self) -> bool {
+        self.comprehension_required
+    }
+
+    pub fn get_value(&self) -> &[u8] {
+        &self.value
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = if (0x01..=0x7e).contains(&self.tag) {
+            let cr = if self.comprehension_required {
+                0x80
+            } else {
+                0x00
+            };
+            Vec::from([cr | self.tag as u8])
+        } else {
+            let cr = if self.comprehension_required {
+                0x8000
+            } else {
+                0x0000
+            };
+            let tag = (cr | self.tag).to_be_bytes();
+            Vec::from([0x7f, tag[0], tag[1]])
+        };
+        bytes.extend(encode_length(self.value.len()));
+        bytes.extend_from_slice(&self.value);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::comprehension_tlv::{
+        check_comprehension_required, new_comprehension_tlv, parse_comprehension_tlvs,
+        ComprehensionTlvError,
+    };
+
+    #[test]
+    fn should_parse_comprehension_tlvs() {
+        let bytes = [
+            0x81, 0x03, 0x01, 0x21, 0x80, 0x02, 0x02, 0x81, 0x02, 0x7f, 0x80, 0x81, 0x01, 0xaa,
+        ];
+        let tlvs = parse_comprehension_tlvs(&bytes).unwrap();
+        assert_eq!(tlvs.len(), 3);
+        assert_eq!(tlvs[0].get_tag(), 0x01);
+        assert!(tlvs[0].is_comprehension_required());
+        assert_eq!(tlvs[0].get_value(), &[0x01, 0x21, 0x80]);
+        assert_eq!(tlvs[1].get_tag(), 0x02);
+        assert!(!tlvs[1].is_comprehension_required());
+        assert_eq!(tlvs[2].get_tag(), 0x0081);
+        assert!(tlvs[2].is_comprehension_required());
+
+        let encoded: Vec<u8> = tlvs.iter().flat_map(|tlv| tlv.to_bytes()).collect();
+        assert_eq!(encoded, bytes);
+        assert_eq!(
+            new_comprehension_tlv(0x0d, false, vec![0x00; 0x80]).to_bytes()[..3],
+            [0x0d, 0x81, 0x80]
+        );
+    }
+
+    #[test]
+    fn should_fail_parse_malformed_comprehension_tlvs() {
+        assert_eq!(
+            parse_comprehension_tlvs(&[0x81, 0x03, 0x01]).unwrap_err(),
+            ComprehensionTlvError::UnexpectedEnd(3)
+        );
+        assert_eq!(
+            parse_comprehension_tlvs(&[0x01, 0x00, 0xff, 0x00]).unwrap_err(),
+            ComprehensionTlvError::InvalidTag(0xff)
+        );
+    }
+
+    #[test]
+    fn should_report_unknown_comprehension_required_tag() {
+        let tlvs = parse_comprehension_tlvs(&[0x81, 0x00, 0x7a, 0x00, 0xfa, 0x01, 0x00]).unwrap();
+        assert_eq!(check_comprehension_required(&tlvs[..2]), Ok(()));
+        assert_eq!(
+            check_comprehension_required(&tlvs),
+            Err(ComprehensionTlvError::UnknownComprehensionRequiredTag(0x7a))
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::ber_tlv::{find_ber_tlv, parse_ber_tlvs, BerTlvError};
+use crate::hex::hex_bytes;
+
+/// File identifier of EF.DIR: ref 13.1 / ETSI TS 102 221 V15.0.0
+pub const EF_DIR_FILE_ID: u16 = 0x2f00;
+
+/// ApplicationTemplate: a record of EF.DIR; ref 13.1 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationTemplate {
+    #[serde(with = "hex_bytes")]
+    aid: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EfDirError {
+    #[error("invalid BER-TLV in the EF.DIR record: {0}")]
+    InvalidBerTlv(#[from] BerTlvError),
+    #[error(
+        "application identifier '4F' is mandatory but it is missing in the application template"
+    )]
+    MissingAID,
+}
+
+/// Parses a record of EF.DIR. An empty record, i.e. filled with 'FF', gives `None`.
+pub fn parse_ef_dir_record(record: &[u8]) -> Result<Option<ApplicationTemplate>, EfDirError> {
+    let tlvs = parse_ber_tlvs(record)?;
+    let template = match find_ber_tlv(&tlvs, 0x61) {
+        Some(template) => template.get_children()?,
+        None => return Ok(None),
+    };
+
+    let aid = match find_ber_tlv(&template, 0x4f) {
+        Some(tlv) => tlv.get_value().to_vec(),
+        None => return Err(EfDirError::MissingAID),
+    };
+    let label = find_ber_tlv(&template, 0x50)
+        .map(|tlv| String::from_utf8_lossy(tlv.get_value()).into_owned());
+
+    Ok(Some(ApplicationTemplate { aid, label }))
+}
+
+impl ApplicationTemplate {
+    pub fn get_aid(&self) -> &[u8] {
+        &self.aid
+    }
+
+    pub fn get_label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ef_dir::{parse_ef_dir_record, EfDirError};
+
+    #[test]
+    fn should_parse_ef_dir_record() {
+        let app = parse_ef_dir_record(&[
+            0x61, 0x0f, 0x4f, 0x07, 0xa0, 0x00, 0x00, 0x00, 0x87, 0x10, 0x02, 0x50, 0x04, 0x55,
+            0x53, 0x49, 0x4d, 0xff, 0xff,
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(app.get_aid(), &[0xa0, 0x00, 0x00, 0x00, 0x87, 0x10, 0x02]);
+        assert_eq!(app.get_label(), Some("USIM"));
+
+        assert_eq!(parse_ef_dir_record(&[0xff; 16]).unwrap(), None);
+        assert_eq!(
+            parse_ef_dir_record(&[0x61, 0x02, 0x50, 0x00]).unwrap_err(),
+            EfDirError::MissingAID
+        );
+    }
+}
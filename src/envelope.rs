@@ -0,0 +1,341 @@
+use crate::ber_tlv::new_ber_tlv;
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::comprehension_tlv::{
+    new_comprehension_tlv, ComprehensionTlv, ADDRESS_TAG, CELL_BROADCAST_PAGE_TAG,
+    CHANNEL_DATA_LENGTH_TAG, DEVICE_IDENTITY_TAG, EVENT_LIST_TAG, HELP_REQUEST_TAG,
+    ITEM_IDENTIFIER_TAG, LOCATION_STATUS_TAG, SMS_TPDU_TAG, TIMER_IDENTIFIER_TAG, TIMER_VALUE_TAG,
+    TRANSACTION_IDENTIFIER_TAG,
+};
+use crate::instruction::Envelope as EnvelopeInstruction;
+use crate::proactive_command::{DEVICE_KEYPAD, DEVICE_NETWORK, DEVICE_TERMINAL, DEVICE_UICC};
+use crate::response_apdu::ResponseAPDU;
+use crate::simple_tlv::{Address, ChannelStatus, LocationInformation};
+
+/// BER-TLV tags of the envelopes; ref 9.1 / ETSI TS 101 220 V15.0.0
+pub const SMS_PP_DOWNLOAD_TAG: u32 = 0xd1;
+pub const CELL_BROADCAST_DOWNLOAD_TAG: u32 = 0xd2;
+pub const MENU_SELECTION_TAG: u32 = 0xd3;
+pub const EVENT_DOWNLOAD_TAG: u32 = 0xd6;
+pub const TIMER_EXPIRATION_TAG: u32 = 0xd7;
+
+/// Event: the event of EVENT DOWNLOAD and SET UP EVENT LIST; ref 8.25 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Event {
+    MtCall = 0x00,
+    CallConnected = 0x01,
+    CallDisconnected = 0x02,
+    LocationStatus = 0x03,
+    UserActivity = 0x04,
+    IdleScreenAvailable = 0x05,
+    CardReaderStatus = 0x06,
+    LanguageSelection = 0x07,
+    BrowserTermination = 0x08,
+    DataAvailable = 0x09,
+    ChannelStatus = 0x0a,
+    AccessTechnologyChange = 0x0b,
+    DisplayParametersChanged = 0x0c,
+    LocalConnection = 0x0d,
+    NetworkSearchModeChange = 0x0e,
+}
+
+/// Envelope: the data that the terminal transmits to the UICC by ENVELOPE; ref 7 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    tag: u32,
+    data_objects: Vec<ComprehensionTlv>,
+    bytes: Vec<u8>,
+}
+
+/// EnvelopeStatus: the interpretation of the status word that follows ENVELOPE; ref 10.2.1 / ETSI TS 102
+/// 221 V15.0.0 and 7.1.2 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnvelopeStatus {
+    /// '9000'
+    Success,
+    /// '91XX': a proactive command of the length is pending
+    ProactiveCommandPending(u8),
+    /// '9EXX': the response data of the length for the data download error is available by GET RESPONSE
+    DataDownloadErrorResponse(u8),
+    /// '9300': the toolkit is busy; the terminal may retry the envelope later
+    ToolkitBusy,
+    /// any other status word
+    Failure(u16),
+}
+
+fn device_identities(source: u8) -> ComprehensionTlv {
+    new_comprehension_tlv(DEVICE_IDENTITY_TAG, true, Vec::from([source, DEVICE_UICC]))
+}
+
+fn new_envelope(tag: u32, data_objects: Vec<ComprehensionTlv>) -> Envelope {
+    let value = data_objects.iter().flat_map(|tlv| tlv.to_bytes()).collect();
+    Envelope {
+        tag,
+        bytes: new_ber_tlv(tag, value).to_bytes(),
+        data_objects,
+    }
+}
+
+/// Creates MENU SELECTION with the identifier of the selected item; ref 7.2 / ETSI TS 102 223 V15.0.0
+pub fn new_menu_selection_envelope(item_identifier: u8, help_requested: bool) -> Envelope {
+    let mut data_objects = Vec::from([
+        device_identities(DEVICE_KEYPAD),
+        new_comprehension_tlv(ITEM_IDENTIFIER_TAG, true, Vec::from([item_identifier])),
+    ]);
+    if help_requested {
+        data_objects.push(new_comprehension_tlv(HELP_REQUEST_TAG, true, Vec::new()));
+    }
+    new_envelope(MENU_SELECTION_TAG, data_objects)
+}
+
+/// Creates EVENT DOWNLOAD of the event with the event-specific data objects; ref 7.5 / ETSI TS 102 223
+/// V15.0.0
+pub fn new_event_download_envelope(
+    event: Event,
+    source: u8,
+    event_data_objects: Vec<ComprehensionTlv>,
+) -> Envelope {
+    let mut data_objects = Vec::from([
+        new_comprehension_tlv(EVENT_LIST_TAG, true, Vec::from([event as u8])),
+        device_identities(source),
+    ]);
+    data_objects.extend(event_data_objects);
+    new_envelope(EVENT_DOWNLOAD_TAG, data_objects)
+}
+
+/// Creates EVENT DOWNLOAD of Location status with the location information when it is in normal service.
+pub fn new_location_status_envelope(
+    location_status: u8,
+    location_information: Option<&LocationInformation>,
+) -> Envelope {
+    let mut data_objects = Vec::from([new_comprehension_tlv(
+        LOCATION_STATUS_TAG,
+        true,
+        Vec::from([location_status]),
+    )]);
+    if let Some(location_information) = location_information {
+        data_objects.push(location_information.to_comprehension_tlv());
+    }
+    new_event_download_envelope(Event::LocationStatus, DEVICE_TERMINAL, data_objects)
+}
+
+/// Creates EVENT DOWNLOAD of Call connected; the source is the terminal when it accepts the MT call and
+/// the network when the far end answers the MO call; ref 7.5.2.2 / ETSI TS 102 223 V15.0.0
+pub fn new_call_connected_envelope(
+    transaction_identifier: u8,
+    mobile_terminated: bool,
+) -> Envelope {
+    let source = if mobile_terminated {
+        DEVICE_TERMINAL
+    } else {
+        DEVICE_NETWORK
+    };
+    new_event_download_envelope(
+        Event::CallConnected,
+        source,
+        Vec::from([new_comprehension_tlv(
+            TRANSACTION_IDENTIFIER_TAG,
+            true,
+            Vec::from([transaction_identifier]),
+        )]),
+    )
+}
+
+/// Creates EVENT DOWNLOAD of Data available with the channel status and the number of available bytes.
+pub fn new_data_available_envelope(
+    channel_status: &ChannelStatus,
+    channel_data_length: u8,
+) -> Envelope {
+    new_event_download_envelope(
+        Event::DataAvailable,
+        DEVICE_TERMINAL,
+        Vec::from([
+            channel_status.to_comprehension_tlv(),
+            new_comprehension_tlv(
+                CHANNEL_DATA_LENGTH_TAG,
+                true,
+                Vec::from([channel_data_length]),
+            ),
+        ]),
+    )
+}
+
+/// Creates EVENT DOWNLOAD of Channel status.
+pub fn new_channel_status_envelope(channel_status: &ChannelStatus) -> Envelope {
+    new_event_download_envelope(
+        Event::ChannelStatus,
+        DEVICE_TERMINAL,
+        Vec::from([channel_status.to_comprehension_tlv()]),
+    )
+}
+
+/// Creates SMS-PP DATA DOWNLOAD with the address of the service centre and the SMS-DELIVER TPDU;
+/// ref 7.1 / 3GPP TS 31.111
+///
+/// The address is not comprehension required in this envelope.
+pub fn new_sms_pp_download_envelope(address: Option<&Address>, tpdu: &[u8]) -> Envelope {
+    let mut data_objects = Vec::from([device_identities(DEVICE_NETWORK)]);
+    if let Some(address) = address {
+        let value = address.to_comprehension_tlv().get_value().to_vec();
+        data_objects.push(new_comprehension_tlv(ADDRESS_TAG, false, value));
+    }
+    data_objects.push(new_comprehension_tlv(SMS_TPDU_TAG, true, tpdu.to_vec()));
+    new_envelope(SMS_PP_DOWNLOAD_TAG, data_objects)
+}
+
+/// Creates CELL BROADCAST DATA DOWNLOAD with the page; ref 7.1 / 3GPP TS 31.111
+pub fn new_cell_broadcast_download_envelope(page: &[u8]) -> Envelope {
+    new_envelope(
+        CELL_BROADCAST_DOWNLOAD_TAG,
+        Vec::from([
+            device_identities(DEVICE_NETWORK),
+            new_comprehension_tlv(CELL_BROADCAST_PAGE_TAG, true, page.to_vec()),
+        ]),
+    )
+}
+
+/// Creates TIMER EXPIRATION with the timer and its value in the BCD-coded hours, minutes and seconds;
+/// ref 7.4 / ETSI TS 102 223 V15.0.0
+pub fn new_timer_expiration_envelope(timer_identifier: u8, timer_value: [u8; 3]) -> Envelope {
+    new_envelope(
+        TIMER_EXPIRATION_TAG,
+        Vec::from([
+            device_identities(DEVICE_TERMINAL),
+            new_comprehension_tlv(TIMER_IDENTIFIER_TAG, true, Vec::from([timer_identifier])),
+            new_comprehension_tlv(TIMER_VALUE_TAG, true, timer_value.to_vec()),
+        ]),
+    )
+}
+
+/// Interprets the status word of the response to ENVELOPE.
+pub fn parse_envelope_status(response: &ResponseAPDU) -> EnvelopeStatus {
+    match (response.get_sw1(), response.get_sw2()) {
+        (0x90, 0x00) => EnvelopeStatus::Success,
+        (0x91, length) => EnvelopeStatus::ProactiveCommandPending(length),
+        (0x9e, length) => EnvelopeStatus::DataDownloadErrorResponse(length),
+        (0x93, 0x00) => EnvelopeStatus::ToolkitBusy,
+        _ => EnvelopeStatus::Failure(response.get_status_word()),
+    }
+}
+
+impl Envelope {
+    pub fn get_tag(&self) -> u32 {
+        self.tag
+    }
+
+    pub fn get_data_objects(&self) -> &[ComprehensionTlv] {
+        &self.data_objects
+    }
+
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &EnvelopeInstruction {},
+            0x00,
+            0x00,
+            None,
+            Some(&self.bytes),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::envelope::{
+        new_call_connected_envelope, new_data_available_envelope, new_location_status_envelope,
+        new_menu_selection_envelope, new_sms_pp_download_envelope, new_timer_expiration_envelope,
+        parse_envelope_status, EnvelopeStatus,
+    };
+    use crate::response_apdu::new_response_apdu;
+    use crate::simple_tlv::{new_address, new_channel_status, new_location_information};
+
+    #[test]
+    fn should_construct_envelopes() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            new_menu_selection_envelope(0x02, false)
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([
+                0x80, 0xc2, 0x00, 0x00, 0x09, 0xd3, 0x07, 0x82, 0x02, 0x01, 0x81, 0x90, 0x01, 0x02
+            ])
+        );
+        assert_eq!(
+            new_menu_selection_envelope(0x02, true).get_bytes()[..2],
+            [0xd3, 0x09]
+        );
+
+        assert_eq!(
+            new_sms_pp_download_envelope(Some(&new_address(0x91, "0123").unwrap()), &[0x04, 0x00])
+                .get_bytes(),
+            &[
+                0xd1, 0x0d, 0x82, 0x02, 0x83, 0x81, 0x06, 0x03, 0x91, 0x10, 0x32, 0x8b, 0x02, 0x04,
+                0x00
+            ]
+        );
+        assert_eq!(
+            new_location_status_envelope(0x02, None).get_bytes(),
+            &[0xd6, 0x0a, 0x99, 0x01, 0x03, 0x82, 0x02, 0x82, 0x81, 0x9b, 0x01, 0x02]
+        );
+        let location_information =
+            new_location_information([0x32, 0xf4, 0x01], 0x1234, &[0x00, 0x56]);
+        assert_eq!(
+            new_location_status_envelope(0x00, Some(&location_information)).get_bytes()[12..],
+            [0x93, 0x07, 0x32, 0xf4, 0x01, 0x12, 0x34, 0x00, 0x56]
+        );
+        assert_eq!(
+            new_data_available_envelope(&new_channel_status(1, true, 0x00), 0xff).get_bytes()[9..],
+            [0xb8, 0x02, 0x81, 0x00, 0xb7, 0x01, 0xff]
+        );
+        assert_eq!(
+            new_timer_expiration_envelope(0x01, [0x00, 0x10, 0x00]).get_bytes(),
+            &[0xd7, 0x0c, 0x82, 0x02, 0x82, 0x81, 0xa4, 0x01, 0x01, 0xa5, 0x03, 0x00, 0x10, 0x00]
+        );
+    }
+
+    #[test]
+    fn should_construct_call_connected_envelope() {
+        // the terminal accepts the MT call
+        assert_eq!(
+            new_call_connected_envelope(0x05, true).get_bytes(),
+            &[0xd6, 0x0a, 0x99, 0x01, 0x01, 0x82, 0x02, 0x82, 0x81, 0x9c, 0x01, 0x05]
+        );
+        // the far end answers the MO call
+        assert_eq!(
+            new_call_connected_envelope(0x05, false).get_bytes()[5..9],
+            [0x82, 0x02, 0x83, 0x81]
+        );
+    }
+
+    #[test]
+    fn should_interpret_envelope_status() {
+        let status = |sw1, sw2| parse_envelope_status(&new_response_apdu(Vec::new(), sw1, sw2));
+        assert_eq!(status(0x90, 0x00), EnvelopeStatus::Success);
+        assert_eq!(
+            status(0x91, 0x20),
+            EnvelopeStatus::ProactiveCommandPending(0x20)
+        );
+        assert_eq!(
+            status(0x9e, 0x10),
+            EnvelopeStatus::DataDownloadErrorResponse(0x10)
+        );
+        assert_eq!(status(0x93, 0x00), EnvelopeStatus::ToolkitBusy);
+        assert_eq!(status(0x6f, 0x00), EnvelopeStatus::Failure(0x6f00));
+    }
+}
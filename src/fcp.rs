@@ -0,0 +1,622 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::ber_tlv::{find_ber_tlv, new_ber_tlv, parse_ber_tlv, BerTlvError};
+use crate::file::{new_file_id_from_bytes, FileError, FileId};
+use crate::hex::{hex_bytes, optional_hex_bytes, to_hex};
+
+/// The data coding byte of the file descriptor is '21' for the UICC: ref 11.1.1.4.3 / ETSI TS 102 221 V15.0.0
+const DEFAULT_DATA_CODING_BYTE: u8 = 0x21;
+
+/// FileType: ref 11.1.1.4.3 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FileType {
+    WorkingEF,
+    InternalEF,
+    DFOrADF,
+}
+
+/// EFStructure: ref 11.1.1.4.3 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EFStructure {
+    NoInformation,
+    Transparent,
+    LinearFixed,
+    Cyclic,
+    BerTlv,
+}
+
+/// FileDescriptor: ref 11.1.1.4.3 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileDescriptor {
+    shareable: bool,
+    file_type: FileType,
+    structure: EFStructure,
+    #[serde(default = "default_data_coding_byte")]
+    data_coding_byte: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record_length: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    number_of_records: Option<u8>,
+}
+
+/// SecurityAttributes: ref 11.1.1.4.7 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", content = "value")]
+pub enum SecurityAttributes {
+    /// Compact format: tag '8C'
+    Compact(#[serde(with = "hex_bytes")] Vec<u8>),
+    /// Expanded format: tag 'AB'
+    Expanded(#[serde(with = "hex_bytes")] Vec<u8>),
+    /// Referenced to expanded format: tag '8B'
+    Referenced(#[serde(with = "hex_bytes")] Vec<u8>),
+}
+
+/// LifeCycleStatus: the life cycle status integer (tag '8A'); ref 11.1.1.4.9 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LifeCycleStatus {
+    /// '00'
+    NoInformation,
+    /// '01'
+    Creation,
+    /// '03'
+    Initialisation,
+    /// '05' or '07'
+    OperationalActivated,
+    /// '04' or '06'
+    OperationalDeactivated,
+    /// '0C' to '0F'
+    Terminated,
+    /// '10' to 'FF'
+    Proprietary(u8),
+    /// The other values
+    ReservedForFutureUse(u8),
+}
+
+/// FileControlParameters: ref 11.1.1.3 / ETSI TS 102 221 V15.0.0
+///
+/// The FCP that is parsed from the UICC keeps the template as it is, so that `to_bytes` returns the same bytes
+/// including the data objects of the other tags, their order and their lengths. The FCP that is written by hand
+/// has no template and is encoded from the data objects.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedFileControlParameters")]
+pub struct FileControlParameters {
+    #[serde(flatten)]
+    data_objects: FcpDataObjects,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_hex_bytes"
+    )]
+    template: Option<Vec<u8>>,
+}
+
+/// The data objects of the FCP that this crate interprets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FcpDataObjects {
+    file_descriptor: FileDescriptor,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_id: Option<FileId>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_hex_bytes"
+    )]
+    df_name: Option<Vec<u8>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_hex_bytes"
+    )]
+    proprietary_information: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    life_cycle_status_integer: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    security_attributes: Option<SecurityAttributes>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_hex_bytes"
+    )]
+    pin_status_template: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_file_size: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    short_file_id: Option<u8>,
+}
+
+/// FileControlParameters before the data objects are checked against the template.
+#[derive(Deserialize)]
+struct UncheckedFileControlParameters {
+    #[serde(flatten)]
+    data_objects: FcpDataObjects,
+    #[serde(default, with = "optional_hex_bytes")]
+    template: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FcpError {
+    #[error("invalid BER-TLV in the FCP: {0}")]
+    InvalidBerTlv(#[from] BerTlvError),
+    #[error("unexpected tag of the FCP template; this must be '62' but '{0:X}'")]
+    UnexpectedTemplateTag(u32),
+    #[error("file descriptor '82' is mandatory but it is missing in the FCP")]
+    MissingFileDescriptor,
+    #[error("invalid length of the file descriptor; this must be 2 or 5 bytes but {0} bytes")]
+    InvalidFileDescriptorLength(usize),
+    #[error("invalid file identifier in the FCP: {0}")]
+    InvalidFileId(#[from] FileError),
+    #[error("the data objects of the FCP do not match the template '{0}'")]
+    InconsistentTemplate(String),
+}
+
+/// Parses the FCP template (tag '62') that is returned by SELECT or STATUS.
+pub fn parse_fcp(bytes: &[u8]) -> Result<FileControlParameters, FcpError> {
+    let (template, length) = parse_ber_tlv(bytes)?;
+    if template.get_tag() != 0x62 {
+        return Err(FcpError::UnexpectedTemplateTag(template.get_tag()));
+    }
+    let tlvs = template.get_children()?;
+
+    let file_descriptor = match find_ber_tlv(&tlvs, 0x82) {
+        Some(tlv) => parse_file_descriptor(tlv.get_value())?,
+        None => return Err(FcpError::MissingFileDescriptor),
+    };
+
+    let file_id = match find_ber_tlv(&tlvs, 0x83) {
+        Some(tlv) => Some(new_file_id_from_bytes(tlv.get_value())?),
+        None => None,
+    };
+
+    let security_attributes = tlvs.iter().find_map(|tlv| match tlv.get_tag() {
+        0x8c => Some(SecurityAttributes::Compact(tlv.get_value().to_vec())),
+        0xab => Some(SecurityAttributes::Expanded(tlv.get_value().to_vec())),
+        0x8b => Some(SecurityAttributes::Referenced(tlv.get_value().to_vec())),
+        _ => None,
+    });
+
+    let value_of = |tag: u32| find_ber_tlv(&tlvs, tag).map(|tlv| tlv.get_value().to_vec());
+
+    let data_objects = FcpDataObjects {
+        file_descriptor,
+        file_id,
+        df_name: value_of(0x84),
+        proprietary_information: value_of(0xa5),
+        life_cycle_status_integer: value_of(0x8a).and_then(|v| v.first().copied()),
+        security_attributes,
+        pin_status_template: value_of(0xc6),
+        file_size: value_of(0x80).map(|v| be_bytes_to_u32(&v)),
+        total_file_size: value_of(0x81).map(|v| be_bytes_to_u32(&v)),
+        short_file_id: value_of(0x88).and_then(|v| v.first().map(|sfi| sfi >> 3)),
+    };
+    Ok(FileControlParameters {
+        data_objects,
+        template: Some(bytes[..length].to_vec()),
+    })
+}
+
+fn default_data_coding_byte() -> u8 {
+    DEFAULT_DATA_CODING_BYTE
+}
+
+fn parse_file_descriptor(value: &[u8]) -> Result<FileDescriptor, FcpError> {
+    if value.len() != 2 && value.len() != 5 {
+        return Err(FcpError::InvalidFileDescriptorLength(value.len()));
+    }
+
+    let descriptor_byte = value[0];
+    let (file_type, structure) = if descriptor_byte & 0b111111 == 0b111001 {
+        (FileType::WorkingEF, EFStructure::BerTlv)
+    } else {
+        let file_type = match (descriptor_byte >> 3) & 0b111 {
+            0b000 => FileType::WorkingEF,
+            0b111 => FileType::DFOrADF,
+            _ => FileType::InternalEF,
+        };
+        let structure = match descriptor_byte & 0b111 {
+            0b001 => EFStructure::Transparent,
+            0b010 => EFStructure::LinearFixed,
+            0b110 => EFStructure::Cyclic,
+            _ => EFStructure::NoInformation,
+        };
+        (file_type, structure)
+    };
+
+    let (record_length, number_of_records) = if value.len() == 5 {
+        (
+            Some(u16::from_be_bytes([value[2], value[3]])),
+            Some(value[4]),
+        )
+    } else {
+        (None, None)
+    };
+
+    Ok(FileDescriptor {
+        shareable: descriptor_byte & 0b01000000 != 0,
+        file_type,
+        structure,
+        data_coding_byte: value[1],
+        record_length,
+        number_of_records,
+    })
+}
+
+fn be_bytes_to_u32(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u32)
+}
+
+fn u32_to_be_bytes(value: u32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take(2).take_while(|b| **b == 0).count();
+    bytes[skip..].to_vec()
+}
+
+impl FileDescriptor {
+    fn to_bytes(&self) -> Vec<u8> {
+        let type_bits = match (self.file_type, self.structure) {
+            (_, EFStructure::BerTlv) => 0b111,
+            (FileType::WorkingEF, _) => 0b000,
+            (FileType::InternalEF, _) => 0b001,
+            (FileType::DFOrADF, _) => 0b111,
+        };
+        let structure_bits = match self.structure {
+            EFStructure::NoInformation => 0b000,
+            EFStructure::Transparent => 0b001,
+            EFStructure::LinearFixed => 0b010,
+            EFStructure::Cyclic => 0b110,
+            EFStructure::BerTlv => 0b001,
+        };
+        let descriptor_byte = ((self.shareable as u8) << 6) | (type_bits << 3) | structure_bits;
+
+        let mut bytes = Vec::from([descriptor_byte, self.data_coding_byte]);
+        if let (Some(record_length), Some(number_of_records)) =
+            (self.record_length, self.number_of_records)
+        {
+            bytes.extend_from_slice(&record_length.to_be_bytes());
+            bytes.push(number_of_records);
+        }
+        bytes
+    }
+
+    pub fn is_shareable(&self) -> bool {
+        self.shareable
+    }
+
+    pub fn get_file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    pub fn get_structure(&self) -> EFStructure {
+        self.structure
+    }
+
+    pub fn get_data_coding_byte(&self) -> u8 {
+        self.data_coding_byte
+    }
+
+    pub fn get_record_length(&self) -> Option<u16> {
+        self.record_length
+    }
+
+    pub fn get_number_of_records(&self) -> Option<u8> {
+        self.number_of_records
+    }
+}
+
+impl LifeCycleStatus {
+    pub fn from_byte(b: u8) -> LifeCycleStatus {
+        use LifeCycleStatus::*;
+        match b {
+            0x00 => NoInformation,
+            0x01 => Creation,
+            0x03 => Initialisation,
+            0x05 | 0x07 => OperationalActivated,
+            0x04 | 0x06 => OperationalDeactivated,
+            0x0c..=0x0f => Terminated,
+            0x10..=0xff => Proprietary(b),
+            _ => ReservedForFutureUse(b),
+        }
+    }
+
+    /// Returns whether the file can be used by the commands other than the life cycle management.
+    pub fn is_operational(&self) -> bool {
+        matches!(
+            self,
+            LifeCycleStatus::OperationalActivated | LifeCycleStatus::OperationalDeactivated
+        )
+    }
+}
+
+impl FileControlParameters {
+    pub fn get_file_descriptor(&self) -> &FileDescriptor {
+        &self.data_objects.file_descriptor
+    }
+
+    pub fn get_file_id(&self) -> Option<FileId> {
+        self.data_objects.file_id
+    }
+
+    /// Returns the DF name; that is the AID for an ADF.
+    pub fn get_df_name(&self) -> Option<&[u8]> {
+        self.data_objects.df_name.as_deref()
+    }
+
+    pub fn get_proprietary_information(&self) -> Option<&[u8]> {
+        self.data_objects.proprietary_information.as_deref()
+    }
+
+    pub fn get_life_cycle_status_integer(&self) -> Option<u8> {
+        self.data_objects.life_cycle_status_integer
+    }
+
+    pub fn get_life_cycle_status(&self) -> Option<LifeCycleStatus> {
+        self.data_objects
+            .life_cycle_status_integer
+            .map(LifeCycleStatus::from_byte)
+    }
+
+    pub fn get_security_attributes(&self) -> Option<&SecurityAttributes> {
+        self.data_objects.security_attributes.as_ref()
+    }
+
+    pub fn get_pin_status_template(&self) -> Option<&[u8]> {
+        self.data_objects.pin_status_template.as_deref()
+    }
+
+    pub fn get_file_size(&self) -> Option<u32> {
+        self.data_objects.file_size
+    }
+
+    pub fn get_total_file_size(&self) -> Option<u32> {
+        self.data_objects.total_file_size
+    }
+
+    pub fn get_short_file_id(&self) -> Option<u8> {
+        self.data_objects.short_file_id
+    }
+
+    pub fn is_df(&self) -> bool {
+        self.data_objects.file_descriptor.file_type == FileType::DFOrADF
+    }
+
+    /// Returns the template as it is parsed from the UICC.
+    pub fn get_template(&self) -> Option<&[u8]> {
+        self.template.as_deref()
+    }
+
+    /// Returns the template as it is parsed from the UICC, or encodes the FCP template (tag '62') in the order
+    /// of the data objects in 11.1.1.3 / ETSI TS 102 221 V15.0.0
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match &self.template {
+            Some(template) => template.clone(),
+            None => self.data_objects.to_bytes(),
+        }
+    }
+}
+
+impl FcpDataObjects {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut tlvs = Vec::from([new_ber_tlv(0x82, self.file_descriptor.to_bytes())]);
+        if let Some(file_id) = self.file_id {
+            tlvs.push(new_ber_tlv(0x83, file_id.get_bytes().to_vec()));
+        }
+        if let Some(df_name) = &self.df_name {
+            tlvs.push(new_ber_tlv(0x84, df_name.clone()));
+        }
+        if let Some(proprietary_information) = &self.proprietary_information {
+            tlvs.push(new_ber_tlv(0xa5, proprietary_information.clone()));
+        }
+        if let Some(lcsi) = self.life_cycle_status_integer {
+            tlvs.push(new_ber_tlv(0x8a, Vec::from([lcsi])));
+        }
+        match &self.security_attributes {
+            Some(SecurityAttributes::Compact(value)) => tlvs.push(new_ber_tlv(0x8c, value.clone())),
+            Some(SecurityAttributes::Expanded(value)) => {
+                tlvs.push(new_ber_tlv(0xab, value.clone()))
+            }
+            Some(SecurityAttributes::Referenced(value)) => {
+                tlvs.push(new_ber_tlv(0x8b, value.clone()))
+            }
+            None => {}
+        }
+        if let Some(pin_status_template) = &self.pin_status_template {
+            tlvs.push(new_ber_tlv(0xc6, pin_status_template.clone()));
+        }
+        if let Some(file_size) = self.file_size {
+            tlvs.push(new_ber_tlv(0x80, u32_to_be_bytes(file_size)));
+        }
+        if let Some(total_file_size) = self.total_file_size {
+            tlvs.push(new_ber_tlv(0x81, u32_to_be_bytes(total_file_size)));
+        }
+        if let Some(sfi) = self.short_file_id {
+            tlvs.push(new_ber_tlv(0x88, Vec::from([sfi << 3])));
+        }
+
+        new_ber_tlv(0x62, tlvs.iter().flat_map(|tlv| tlv.to_bytes()).collect()).to_bytes()
+    }
+}
+
+impl TryFrom<UncheckedFileControlParameters> for FileControlParameters {
+    type Error = FcpError;
+
+    fn try_from(unchecked: UncheckedFileControlParameters) -> Result<Self, Self::Error> {
+        if let Some(template) = &unchecked.template {
+            if parse_fcp(template)?.data_objects != unchecked.data_objects {
+                return Err(FcpError::InconsistentTemplate(to_hex(template)));
+            }
+        }
+        Ok(FileControlParameters {
+            data_objects: unchecked.data_objects,
+            template: unchecked.template,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fcp::{
+        parse_fcp, EFStructure, FcpError, FileControlParameters, FileType, LifeCycleStatus,
+        SecurityAttributes,
+    };
+
+    #[test]
+    fn should_parse_fcp_of_linear_fixed_ef() {
+        let fcp = parse_fcp(&[
+            0x62, 0x1a, 0x82, 0x05, 0x42, 0x21, 0x00, 0x26, 0x02, 0x83, 0x02, 0x2f, 0x00, 0x8a,
+            0x01, 0x05, 0x8b, 0x03, 0x2f, 0x06, 0x01, 0x80, 0x02, 0x00, 0x4c, 0x88, 0x01, 0xf0,
+        ])
+        .unwrap();
+
+        let descriptor = fcp.get_file_descriptor();
+        assert!(descriptor.is_shareable());
+        assert_eq!(descriptor.get_file_type(), FileType::WorkingEF);
+        assert_eq!(descriptor.get_structure(), EFStructure::LinearFixed);
+        assert_eq!(descriptor.get_record_length(), Some(0x26));
+        assert_eq!(descriptor.get_number_of_records(), Some(2));
+        assert_eq!(fcp.get_file_id().unwrap().get_value(), 0x2f00);
+        assert_eq!(fcp.get_life_cycle_status_integer(), Some(0x05));
+        assert_eq!(
+            fcp.get_life_cycle_status(),
+            Some(LifeCycleStatus::OperationalActivated)
+        );
+        assert_eq!(
+            fcp.get_security_attributes(),
+            Some(&SecurityAttributes::Referenced(Vec::from([
+                0x2f, 0x06, 0x01
+            ])))
+        );
+        assert_eq!(fcp.get_file_size(), Some(0x4c));
+        assert_eq!(fcp.get_short_file_id(), Some(0x1e));
+        assert!(!fcp.is_df());
+    }
+
+    #[test]
+    fn should_parse_fcp_of_adf() {
+        let fcp = parse_fcp(&[
+            0x62, 0x15, 0x82, 0x02, 0x78, 0x21, 0x84, 0x07, 0xa0, 0x00, 0x00, 0x00, 0x87, 0x10,
+            0x02, 0x8a, 0x01, 0x05, 0xc6, 0x03, 0x90, 0x01, 0x00,
+        ])
+        .unwrap();
+        assert!(fcp.is_df());
+        assert_eq!(fcp.get_file_id(), None);
+        assert_eq!(
+            fcp.get_df_name(),
+            Some(&[0xa0, 0x00, 0x00, 0x00, 0x87, 0x10, 0x02][..])
+        );
+        assert_eq!(fcp.get_pin_status_template(), Some(&[0x90, 0x01, 0x00][..]));
+    }
+
+    /// The FCP of the fixtures in this crate and the ones that the encoding from the data objects would change.
+    const FCP_FIXTURES: [&[u8]; 10] = [
+        &[
+            0x62, 0x1a, 0x82, 0x05, 0x42, 0x21, 0x00, 0x26, 0x02, 0x83, 0x02, 0x2f, 0x00, 0x8a,
+            0x01, 0x05, 0x8b, 0x03, 0x2f, 0x06, 0x01, 0x80, 0x02, 0x00, 0x4c, 0x88, 0x01, 0xf0,
+        ],
+        &[
+            0x62, 0x15, 0x82, 0x02, 0x78, 0x21, 0x84, 0x07, 0xa0, 0x00, 0x00, 0x00, 0x87, 0x10,
+            0x02, 0x8a, 0x01, 0x05, 0xc6, 0x03, 0x90, 0x01, 0x00,
+        ],
+        &[
+            0x62, 0x0d, 0x82, 0x02, 0x79, 0x21, 0x83, 0x02, 0x6f, 0x30, 0x81, 0x03, 0x01, 0x00,
+            0x00,
+        ],
+        &[0x62, 0x08, 0x82, 0x02, 0x78, 0x21, 0x83, 0x02, 0x3f, 0x00],
+        &[
+            0x62, 0x0b, 0x82, 0x02, 0x41, 0x21, 0x83, 0x02, 0x2f, 0xe2, 0x80, 0x01, 0x0a,
+        ],
+        &[
+            0x62, 0x0b, 0x82, 0x05, 0x42, 0x21, 0x00, 0x14, 0x02, 0x83, 0x02, 0x2f, 0x00,
+        ],
+        // SFI not supported
+        &[
+            0x62, 0x0a, 0x82, 0x02, 0x41, 0x21, 0x83, 0x02, 0x6f, 0x07, 0x88, 0x00,
+        ],
+        // proprietary data coding byte and an unknown data object
+        &[
+            0x62, 0x0c, 0x82, 0x02, 0x41, 0x01, 0x83, 0x02, 0x6f, 0x07, 0x99, 0x02, 0x12, 0x34,
+        ],
+        // the compact and the referenced security attributes
+        &[
+            0x62, 0x11, 0x82, 0x02, 0x41, 0x21, 0x8c, 0x03, 0x03, 0x01, 0x02, 0x8b, 0x03, 0x6f,
+            0x06, 0x01, 0x80, 0x01, 0x0a,
+        ],
+        // the data objects out of the order of 11.1.1.3 and the long form of the length
+        &[
+            0x62, 0x81, 0x0b, 0x83, 0x02, 0x6f, 0x07, 0x82, 0x02, 0x41, 0x21, 0x80, 0x01, 0x0a,
+        ],
+    ];
+
+    #[test]
+    fn should_encode_fcp_losslessly() {
+        for bytes in FCP_FIXTURES {
+            let fcp = parse_fcp(bytes).unwrap();
+            assert_eq!(fcp.to_bytes(), bytes.to_vec());
+            assert_eq!(fcp.get_template(), Some(bytes));
+
+            let json = serde_json::to_string(&fcp).unwrap();
+            let fcp: FileControlParameters = serde_json::from_str(&json).unwrap();
+            assert_eq!(fcp.to_bytes(), bytes.to_vec());
+        }
+
+        let fcp = parse_fcp(FCP_FIXTURES[7]).unwrap();
+        assert_eq!(fcp.get_file_descriptor().get_data_coding_byte(), 0x01);
+        let fcp = parse_fcp(FCP_FIXTURES[2]).unwrap();
+        assert_eq!(
+            fcp.get_file_descriptor().get_structure(),
+            EFStructure::BerTlv
+        );
+        assert_eq!(
+            fcp.get_file_descriptor().get_file_type(),
+            FileType::WorkingEF
+        );
+    }
+
+    #[test]
+    fn should_encode_fcp_without_template() {
+        let mut json = serde_json::to_value(parse_fcp(FCP_FIXTURES[0]).unwrap()).unwrap();
+        json.as_object_mut().unwrap().remove("template");
+        let fcp: FileControlParameters = serde_json::from_value(json).unwrap();
+        assert_eq!(fcp.get_template(), None);
+        assert_eq!(fcp.to_bytes(), FCP_FIXTURES[0].to_vec());
+
+        // the file size is encoded in 2 bytes at least; ref 11.1.1.4.1 / ETSI TS 102 221 V15.0.0
+        let fcp: FileControlParameters = serde_json::from_str(
+            r#"{ "file_descriptor": { "shareable": true, "file_type": "WorkingEF", "structure": "Transparent" },
+                 "file_id": "6F07", "file_size": 9 }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            fcp.to_bytes(),
+            Vec::from([
+                0x62, 0x0c, 0x82, 0x02, 0x41, 0x21, 0x83, 0x02, 0x6f, 0x07, 0x80, 0x02, 0x00, 0x09
+            ])
+        );
+    }
+
+    #[test]
+    fn should_fail_deserialize_fcp_inconsistent_with_template() {
+        let mut json = serde_json::to_value(parse_fcp(FCP_FIXTURES[4]).unwrap()).unwrap();
+        json["file_size"] = serde_json::Value::from(11);
+        let error = serde_json::from_value::<FileControlParameters>(json).unwrap_err();
+        assert!(error.to_string().contains("do not match the template"));
+    }
+
+    #[test]
+    fn should_fail_parse_fcp_with_invalid_template() {
+        assert_eq!(
+            parse_fcp(&[0x6f, 0x02, 0x82, 0x00]).unwrap_err(),
+            FcpError::UnexpectedTemplateTag(0x6f)
+        );
+        assert_eq!(
+            parse_fcp(&[0x62, 0x03, 0x83, 0x01, 0x00]).unwrap_err(),
+            FcpError::MissingFileDescriptor
+        );
+        assert_eq!(
+            parse_fcp(&[0x62, 0x03, 0x82, 0x01, 0x38]).unwrap_err(),
+            FcpError::InvalidFileDescriptorLength(1)
+        );
+    }
+}
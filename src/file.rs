@@ -0,0 +1,306 @@
+use std::fmt;
+
+use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+/// File identifier of the MF: ref 8.4.1 / ETSI TS 102 221 V15.0.0
+pub const MASTER_FILE_ID: u16 = 0x3f00;
+/// File identifier that references the current ADF: ref 8.4.1 / ETSI TS 102 221 V15.0.0
+pub const CURRENT_ADF_ID: u16 = 0x7fff;
+/// File identifier reserved for the path selection by ISO/IEC 7816-4: ref 8.4.1 / ETSI TS 102 221 V15.0.0
+pub const RESERVED_PATH_FILE_ID: u16 = 0x3fff;
+/// File identifier reserved for future use: ref 8.4.1 / ETSI TS 102 221 V15.0.0
+pub const RESERVED_FUTURE_USE_FILE_ID: u16 = 0xffff;
+
+/// FileId: ref 8.4.1 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId {
+    id: u16,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FileError {
+    #[error("reserved file identifier '{0:04X}' cannot be used to address a file")]
+    ReservedFileId(u16),
+    #[error("invalid length of the file identifier; this must be 2 bytes but the given value is {0} bytes")]
+    InvalidFileIdLength(usize),
+    #[error("invalid file identifier notation: '{0}'")]
+    InvalidFileIdNotation(String),
+    #[error("path must contain at least one file identifier")]
+    EmptyPath,
+    #[error("MF '3F00' can only appear at the beginning of the path but it is at position {0}")]
+    MisplacedMasterFile(usize),
+    #[error("current ADF '7FFF' can only appear at the beginning of the path or right after the MF but it is at position {0}")]
+    MisplacedCurrentADF(usize),
+}
+
+pub fn new_file_id(id: u16) -> Result<FileId, FileError> {
+    if id == RESERVED_PATH_FILE_ID || id == RESERVED_FUTURE_USE_FILE_ID {
+        return Err(FileError::ReservedFileId(id));
+    }
+
+    Ok(FileId { id })
+}
+
+pub fn new_file_id_from_bytes(bytes: &[u8]) -> Result<FileId, FileError> {
+    if bytes.len() != 2 {
+        return Err(FileError::InvalidFileIdLength(bytes.len()));
+    }
+    new_file_id(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Parses the hexadecimal notation of the file identifier, e.g. "6F07"; this must be exactly 4 hexadecimal
+/// digits.
+pub fn parse_file_id(s: &str) -> Result<FileId, FileError> {
+    if s.len() != 4 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(FileError::InvalidFileIdNotation(s.to_string()));
+    }
+    match u16::from_str_radix(s, 16) {
+        Ok(id) => new_file_id(id),
+        Err(_) => Err(FileError::InvalidFileIdNotation(s.to_string())),
+    }
+}
+
+impl FileId {
+    pub fn get_value(&self) -> u16 {
+        self.id
+    }
+
+    pub fn get_bytes(&self) -> [u8; 2] {
+        self.id.to_be_bytes()
+    }
+
+    pub fn is_master_file(&self) -> bool {
+        self.id == MASTER_FILE_ID
+    }
+
+    pub fn is_current_adf(&self) -> bool {
+        self.id == CURRENT_ADF_ID
+    }
+}
+
+impl fmt::Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}", self.id)
+    }
+}
+
+impl Serialize for FileId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for FileId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_file_id(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Path: ref 8.4.2 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Path {
+    file_ids: Vec<FileId>,
+}
+
+pub fn new_path(file_ids: Vec<FileId>) -> Result<Path, FileError> {
+    if file_ids.is_empty() {
+        return Err(FileError::EmptyPath);
+    }
+
+    for (i, file_id) in file_ids.iter().enumerate() {
+        if file_id.is_master_file() && i != 0 {
+            return Err(FileError::MisplacedMasterFile(i));
+        }
+        if file_id.is_current_adf() && !(i == 0 || (i == 1 && file_ids[0].is_master_file())) {
+            return Err(FileError::MisplacedCurrentADF(i));
+        }
+    }
+
+    Ok(Path { file_ids })
+}
+
+/// Parses the textual notation of the path that is separated by "/", e.g. "3F00/7FFF/6F07".
+pub fn parse_path(s: &str) -> Result<Path, FileError> {
+    let mut file_ids = Vec::new();
+    for notation in s.split('/') {
+        file_ids.push(parse_file_id(notation)?);
+    }
+    new_path(file_ids)
+}
+
+impl Path {
+    pub fn get_file_ids(&self) -> &[FileId] {
+        &self.file_ids
+    }
+
+    pub fn is_from_master_file(&self) -> bool {
+        self.file_ids[0].is_master_file()
+    }
+
+    /// Returns whether the path addresses the MF itself, i.e. "3F00".
+    pub fn is_master_file(&self) -> bool {
+        self.file_ids.len() == 1 && self.is_from_master_file()
+    }
+
+    /// Returns the P1 of SELECT for this path; '08' for the selection from the MF and '09' for the selection from the current DF.
+    /// The path of the MF itself is selected by the file identifier with '00' since the path from the MF would be empty.
+    pub fn get_select_p1(&self) -> u8 {
+        if self.is_master_file() {
+            0x00
+        } else if self.is_from_master_file() {
+            0x08
+        } else {
+            0x09
+        }
+    }
+
+    /// Returns the command data of SELECT by path. The file identifier of the MF is not included in the data
+    /// for the selection from the MF, but it is the data of the selection by the file identifier when the path is
+    /// the MF itself: ref 8.4.2 / ETSI TS 102 221 V15.0.0
+    pub fn to_select_bytes(&self) -> Vec<u8> {
+        if self.is_master_file() {
+            return MASTER_FILE_ID.to_be_bytes().to_vec();
+        }
+        let file_ids = if self.is_from_master_file() {
+            &self.file_ids[1..]
+        } else {
+            &self.file_ids[..]
+        };
+
+        let mut bytes = Vec::with_capacity(file_ids.len() * 2);
+        for file_id in file_ids {
+            bytes.extend_from_slice(&file_id.get_bytes());
+        }
+        bytes
+    }
+
+    pub fn join(&self, file_id: FileId) -> Result<Path, FileError> {
+        let mut file_ids = self.file_ids.clone();
+        file_ids.push(file_id);
+        new_path(file_ids)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let notations: Vec<String> = self.file_ids.iter().map(|id| id.to_string()).collect();
+        write!(f, "{}", notations.join("/"))
+    }
+}
+
+impl Serialize for Path {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Path {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_path(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::file::{
+        new_file_id, new_file_id_from_bytes, new_path, parse_file_id, parse_path, FileError,
+        RESERVED_FUTURE_USE_FILE_ID, RESERVED_PATH_FILE_ID,
+    };
+
+    #[test]
+    fn should_new_file_id_successfully() {
+        let file_id = new_file_id_from_bytes(&[0x6f, 0x07]).unwrap();
+        assert_eq!(file_id.get_value(), 0x6f07);
+        assert_eq!(file_id.get_bytes(), [0x6f, 0x07]);
+        assert_eq!(file_id.to_string(), "6F07");
+        assert!(new_file_id(0x3f00).unwrap().is_master_file());
+        assert!(new_file_id(0x7fff).unwrap().is_current_adf());
+    }
+
+    #[test]
+    fn should_fail_new_file_id_with_reserved_id() {
+        assert_eq!(
+            new_file_id(RESERVED_PATH_FILE_ID).unwrap_err(),
+            FileError::ReservedFileId(0x3fff)
+        );
+        assert_eq!(
+            new_file_id(RESERVED_FUTURE_USE_FILE_ID).unwrap_err(),
+            FileError::ReservedFileId(0xffff)
+        );
+        assert_eq!(
+            new_file_id_from_bytes(&[0x6f]).unwrap_err(),
+            FileError::InvalidFileIdLength(1)
+        );
+    }
+
+    #[test]
+    fn should_parse_path_and_encode_for_select() {
+        let path = parse_path("3F00/7FFF/6F07").unwrap();
+        assert_eq!(path.to_string(), "3F00/7FFF/6F07");
+        assert_eq!(path.get_select_p1(), 0x08);
+        assert_eq!(path.to_select_bytes(), Vec::from([0x7f, 0xff, 0x6f, 0x07]));
+
+        let path = parse_path("7f20/6f07").unwrap();
+        assert_eq!(path.get_select_p1(), 0x09);
+        assert_eq!(path.to_select_bytes(), Vec::from([0x7f, 0x20, 0x6f, 0x07]));
+    }
+
+    #[test]
+    fn should_select_master_file_by_file_id() {
+        let path = parse_path("3F00").unwrap();
+        assert!(path.is_master_file());
+        assert_eq!(path.get_select_p1(), 0x00);
+        assert_eq!(path.to_select_bytes(), Vec::from([0x3f, 0x00]));
+
+        let path = parse_path("3F00/2FE2").unwrap();
+        assert!(!path.is_master_file());
+        assert_eq!(path.get_select_p1(), 0x08);
+        assert_eq!(path.to_select_bytes(), Vec::from([0x2f, 0xe2]));
+    }
+
+    #[test]
+    fn should_fail_parse_path_with_illegal_notation() {
+        assert_eq!(
+            parse_path("3F00/6F0").unwrap_err(),
+            FileError::InvalidFileIdNotation("6F0".into())
+        );
+        assert_eq!(
+            parse_path("3F00/XXXX").unwrap_err(),
+            FileError::InvalidFileIdNotation("XXXX".into())
+        );
+        assert_eq!(
+            parse_file_id("+FFF").unwrap_err(),
+            FileError::InvalidFileIdNotation("+FFF".into())
+        );
+        assert_eq!(
+            parse_file_id("-6F0").unwrap_err(),
+            FileError::InvalidFileIdNotation("-6F0".into())
+        );
+        assert_eq!(
+            parse_path("3F00/3FFF").unwrap_err(),
+            FileError::ReservedFileId(0x3fff)
+        );
+        assert_eq!(
+            parse_path("").unwrap_err(),
+            FileError::InvalidFileIdNotation("".into())
+        );
+        assert_eq!(new_path(Vec::new()).unwrap_err(), FileError::EmptyPath);
+    }
+
+    #[test]
+    fn should_fail_parse_path_with_misplaced_reserved_id() {
+        assert_eq!(
+            parse_path("7F10/3F00").unwrap_err(),
+            FileError::MisplacedMasterFile(1)
+        );
+        assert_eq!(
+            parse_path("3F00/7F10/7FFF").unwrap_err(),
+            FileError::MisplacedCurrentADF(2)
+        );
+        assert!(parse_path("7FFF/6F07").is_ok());
+    }
+}
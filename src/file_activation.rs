@@ -0,0 +1,237 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::fcp::LifeCycleStatus;
+use crate::file::{FileId, Path};
+use crate::instruction::{ActivateFile, DeactivateFile, Instruction};
+use crate::response_apdu::ResponseAPDU;
+
+/// FileReference: the file that ACTIVATE FILE or DEACTIVATE FILE operates on; ref 11.1.14 and 11.1.15 /
+/// ETSI TS 102 221 V15.0.0
+pub enum FileReference<'a> {
+    /// The current EF (P1 = '00' without the command data)
+    CurrentFile,
+    /// Select by file id (P1 = '00')
+    FileId(FileId),
+    /// Select by path from MF (P1 = '08') or from the current DF (P1 = '09'), according to the path
+    Path(&'a Path),
+    /// The EF that is referenced by the short file identifier in P2 b8-b4; ref 7.4.2 / ISO/IEC 7816-9
+    ShortFileId(u8),
+}
+
+/// FileActivation: whether the command activates or deactivates the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileActivation {
+    Activate,
+    Deactivate,
+}
+
+/// FileActivationCommand: ACTIVATE FILE or DEACTIVATE FILE; ref 11.1.14 and 11.1.15 / ETSI TS 102 221
+/// V15.0.0
+pub struct FileActivationCommand {
+    activation: FileActivation,
+    p1: u8,
+    p2: u8,
+    command_data: Vec<u8>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum FileActivationError {
+    #[error(
+        "invalid short file identifier; this must be within [1, 30] but the given value is {0}"
+    )]
+    InvalidShortFileId(u8),
+    #[error("unsuccessful status word of {0:?}: '{1:04X}'")]
+    UnsuccessfulStatusWord(FileActivation, u16),
+    #[error("{0:?} is not defined for the life cycle status {1:?}")]
+    UndefinedTransition(FileActivation, LifeCycleStatus),
+}
+
+pub fn new_activate_file_command(
+    reference: FileReference,
+) -> Result<FileActivationCommand, FileActivationError> {
+    new_file_activation_command(FileActivation::Activate, reference)
+}
+
+pub fn new_deactivate_file_command(
+    reference: FileReference,
+) -> Result<FileActivationCommand, FileActivationError> {
+    new_file_activation_command(FileActivation::Deactivate, reference)
+}
+
+fn new_file_activation_command(
+    activation: FileActivation,
+    reference: FileReference,
+) -> Result<FileActivationCommand, FileActivationError> {
+    let (p1, p2, command_data) = match reference {
+        FileReference::CurrentFile => (0x00, 0x00, Vec::new()),
+        FileReference::FileId(file_id) => (0x00, 0x00, file_id.get_bytes().to_vec()),
+        FileReference::Path(path) => (path.get_select_p1(), 0x00, path.to_select_bytes()),
+        FileReference::ShortFileId(sfi) if sfi == 0 || sfi > 30 => {
+            return Err(FileActivationError::InvalidShortFileId(sfi))
+        }
+        FileReference::ShortFileId(sfi) => (0x00, sfi << 3, Vec::new()),
+    };
+
+    Ok(FileActivationCommand {
+        activation,
+        p1,
+        p2,
+        command_data,
+    })
+}
+
+impl FileActivation {
+    /// Previews the life cycle status of the file after the successful command.
+    ///
+    /// A terminated file cannot be activated again, and the transition from the status that gives no
+    /// information or a proprietary one cannot be predicted.
+    pub fn preview(&self, status: LifeCycleStatus) -> Result<LifeCycleStatus, FileActivationError> {
+        use LifeCycleStatus::*;
+        match (self, status) {
+            (
+                FileActivation::Activate,
+                Creation | Initialisation | OperationalActivated | OperationalDeactivated,
+            ) => Ok(OperationalActivated),
+            (FileActivation::Deactivate, OperationalActivated | OperationalDeactivated) => {
+                Ok(OperationalDeactivated)
+            }
+            _ => Err(FileActivationError::UndefinedTransition(*self, status)),
+        }
+    }
+}
+
+impl FileActivationCommand {
+    pub fn get_activation(&self) -> FileActivation {
+        self.activation
+    }
+
+    pub fn get_p1(&self) -> u8 {
+        self.p1
+    }
+
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn get_command_data(&self) -> &[u8] {
+        &self.command_data
+    }
+
+    /// Previews the life cycle status of the file after the command; see [`FileActivation::preview`].
+    pub fn preview(&self, status: LifeCycleStatus) -> Result<LifeCycleStatus, FileActivationError> {
+        self.activation.preview(status)
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        let command_data = if self.command_data.is_empty() {
+            None
+        } else {
+            Some(&self.command_data[..])
+        };
+        let instruction: &dyn Instruction = match self.activation {
+            FileActivation::Activate => &ActivateFile {},
+            FileActivation::Deactivate => &DeactivateFile {},
+        };
+        new_command_apdu(class, instruction, self.p1, self.p2, None, command_data)
+    }
+
+    pub fn parse_response(&self, response: &ResponseAPDU) -> Result<(), FileActivationError> {
+        if !response.is_normal_ending() {
+            return Err(FileActivationError::UnsuccessfulStatusWord(
+                self.activation,
+                response.get_status_word(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::fcp::LifeCycleStatus;
+    use crate::file::{new_file_id, parse_path};
+    use crate::file_activation::{
+        new_activate_file_command, new_deactivate_file_command, FileActivation,
+        FileActivationError, FileReference,
+    };
+    use crate::response_apdu::new_response_apdu;
+
+    #[test]
+    fn should_construct_file_activation_commands() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let command =
+            new_deactivate_file_command(FileReference::FileId(new_file_id(0x6f07).unwrap()))
+                .unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0x04, 0x00, 0x00, 0x02, 0x6f, 0x07])
+        );
+
+        let path = parse_path("3F00/7FFF/6F07").unwrap();
+        let command = new_activate_file_command(FileReference::Path(&path)).unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0x44, 0x08, 0x00, 0x04, 0x7f, 0xff, 0x6f, 0x07])
+        );
+
+        let command = new_activate_file_command(FileReference::CurrentFile).unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0x44, 0x00, 0x00])
+        );
+
+        let command = new_deactivate_file_command(FileReference::ShortFileId(0x07)).unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0x04, 0x00, 0x38])
+        );
+        assert_eq!(
+            new_activate_file_command(FileReference::ShortFileId(31)).err(),
+            Some(FileActivationError::InvalidShortFileId(31))
+        );
+    }
+
+    #[test]
+    fn should_preview_life_cycle_status_transition() {
+        let activate = new_activate_file_command(FileReference::CurrentFile).unwrap();
+        let deactivate = new_deactivate_file_command(FileReference::CurrentFile).unwrap();
+        let deactivated = LifeCycleStatus::from_byte(0x04);
+        assert_eq!(
+            activate.preview(deactivated),
+            Ok(LifeCycleStatus::OperationalActivated)
+        );
+        assert_eq!(
+            deactivate.preview(LifeCycleStatus::from_byte(0x05)),
+            Ok(LifeCycleStatus::OperationalDeactivated)
+        );
+        assert_eq!(
+            activate.preview(LifeCycleStatus::from_byte(0x0c)),
+            Err(FileActivationError::UndefinedTransition(
+                FileActivation::Activate,
+                LifeCycleStatus::Terminated
+            ))
+        );
+
+        assert!(new_response_apdu(Vec::new(), 0x62, 0x83).is_selected_file_invalidated());
+        assert_eq!(
+            deactivate.parse_response(&new_response_apdu(Vec::new(), 0x69, 0x82)),
+            Err(FileActivationError::UnsuccessfulStatusWord(
+                FileActivation::Deactivate,
+                0x6982
+            ))
+        );
+    }
+}
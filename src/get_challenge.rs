@@ -0,0 +1,233 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::{ExternalAuthenticate, GetChallenge};
+use crate::response_apdu::ResponseAPDU;
+use crate::session::{Session, SessionError};
+
+/// GetChallengeCommand: ref 11.1.18 / ETSI TS 102 221 V15.0.0 and 11.5.3 / ISO/IEC 7816-4
+pub struct GetChallengeCommand {
+    le: u8,
+}
+
+/// Challenge: the random bytes that GET CHALLENGE returns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Challenge {
+    random: Vec<u8>,
+}
+
+/// ExternalAuthenticateCommand: ref 11.5.4 / ISO/IEC 7816-4
+///
+/// P1 references the algorithm ('00' when the algorithm is known implicitly) and P2 references the key.
+pub struct ExternalAuthenticateCommand {
+    algorithm_reference: u8,
+    key_reference: u8,
+    cryptogram: Vec<u8>,
+}
+
+/// CryptogramCalculator: the cryptographic callback that calculates the cryptogram of EXTERNAL AUTHENTICATE
+/// from the challenge, e.g. by a key in a HSM.
+pub trait CryptogramCalculator {
+    fn calculate(&mut self, challenge: &Challenge) -> Result<Vec<u8>, String>;
+}
+
+impl<F: FnMut(&Challenge) -> Result<Vec<u8>, String>> CryptogramCalculator for F {
+    fn calculate(&mut self, challenge: &Challenge) -> Result<Vec<u8>, String> {
+        self(challenge)
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum GetChallengeError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("unsuccessful status word of GET CHALLENGE: '{0:04X}'")]
+    UnsuccessfulStatusWord(u16),
+    #[error("unexpected length of the challenge; this must be {0} bytes but {1} bytes")]
+    UnexpectedChallengeLength(usize, usize),
+    #[error("invalid length of the cryptogram; this must be within [1, 255] bytes but {0} bytes")]
+    InvalidCryptogramLength(usize),
+    #[error("failed to calculate the cryptogram: {0}")]
+    CryptographicFailure(String),
+    #[error("external authentication failed; {0} retries remaining")]
+    AuthenticationFailed(u8),
+    #[error("unsuccessful status word of EXTERNAL AUTHENTICATE: '{0:04X}'")]
+    UnsuccessfulAuthentication(u16),
+}
+
+/// Requests the challenge of `le` bytes; '00' requests as many bytes as the UICC returns.
+pub fn new_get_challenge_command(le: u8) -> GetChallengeCommand {
+    GetChallengeCommand { le }
+}
+
+pub fn new_external_authenticate_command(
+    algorithm_reference: u8,
+    key_reference: u8,
+    cryptogram: &[u8],
+) -> Result<ExternalAuthenticateCommand, GetChallengeError> {
+    if cryptogram.is_empty() || cryptogram.len() > 0xff {
+        return Err(GetChallengeError::InvalidCryptogramLength(cryptogram.len()));
+    }
+    Ok(ExternalAuthenticateCommand {
+        algorithm_reference,
+        key_reference,
+        cryptogram: cryptogram.to_vec(),
+    })
+}
+
+/// Authenticates the terminal to the UICC: requests the challenge of `le` bytes, lets the calculator
+/// calculate the cryptogram and sends it by EXTERNAL AUTHENTICATE.
+pub fn authenticate_externally(
+    session: &mut Session,
+    class: &Class,
+    le: u8,
+    algorithm_reference: u8,
+    key_reference: u8,
+    calculator: &mut dyn CryptogramCalculator,
+) -> Result<(), GetChallengeError> {
+    let command = new_get_challenge_command(le);
+    let challenge = command.parse_response(&session.transmit(&command.to_command_apdu(class))?)?;
+    let cryptogram = calculator
+        .calculate(&challenge)
+        .map_err(GetChallengeError::CryptographicFailure)?;
+    let command =
+        new_external_authenticate_command(algorithm_reference, key_reference, &cryptogram)?;
+    command.parse_response(&session.transmit(&command.to_command_apdu(class))?)
+}
+
+impl GetChallengeCommand {
+    pub fn get_le(&self) -> u8 {
+        self.le
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(class, &GetChallenge {}, 0x00, 0x00, Some(self.le), None)
+    }
+
+    pub fn parse_response(&self, response: &ResponseAPDU) -> Result<Challenge, GetChallengeError> {
+        if !response.is_normal_ending() {
+            return Err(GetChallengeError::UnsuccessfulStatusWord(
+                response.get_status_word(),
+            ));
+        }
+        let random = response.get_data();
+        if self.le != 0 && random.len() != self.le as usize {
+            return Err(GetChallengeError::UnexpectedChallengeLength(
+                self.le as usize,
+                random.len(),
+            ));
+        }
+        Ok(Challenge {
+            random: random.to_vec(),
+        })
+    }
+}
+
+impl Challenge {
+    pub fn get_random(&self) -> &[u8] {
+        &self.random
+    }
+}
+
+impl ExternalAuthenticateCommand {
+    pub fn get_cryptogram(&self) -> &[u8] {
+        &self.cryptogram
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &ExternalAuthenticate {},
+            self.algorithm_reference,
+            self.key_reference,
+            None,
+            Some(&self.cryptogram),
+        )
+    }
+
+    /// Parses the response; '63CX' means that the cryptogram is wrong and X retries remain.
+    pub fn parse_response(&self, response: &ResponseAPDU) -> Result<(), GetChallengeError> {
+        match response.get_status_word() {
+            _ if response.is_normal_ending() => Ok(()),
+            sw if sw & 0xfff0 == 0x63c0 => {
+                Err(GetChallengeError::AuthenticationFailed((sw & 0x0f) as u8))
+            }
+            sw => Err(GetChallengeError::UnsuccessfulAuthentication(sw)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::get_challenge::{
+        authenticate_externally, new_external_authenticate_command, new_get_challenge_command,
+        Challenge, GetChallengeError,
+    };
+    use crate::response_apdu::new_response_apdu;
+    use crate::session::{new_session, SessionConfig};
+    use crate::testing::new_scripted_card;
+
+    #[test]
+    fn should_get_challenge() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let command = new_get_challenge_command(0x08);
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0x84, 0x00, 0x00, 0x08])
+        );
+        let challenge = command
+            .parse_response(&new_response_apdu(Vec::from([0x5a; 8]), 0x90, 0x00))
+            .unwrap();
+        assert_eq!(challenge.get_random(), &[0x5a; 8]);
+        assert_eq!(
+            command
+                .parse_response(&new_response_apdu(Vec::from([0x5a; 4]), 0x90, 0x00))
+                .unwrap_err(),
+            GetChallengeError::UnexpectedChallengeLength(8, 4)
+        );
+        assert_eq!(
+            new_external_authenticate_command(0x00, 0x81, &[]).err(),
+            Some(GetChallengeError::InvalidCryptogramLength(0))
+        );
+    }
+
+    #[test]
+    fn should_authenticate_externally() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let mut card = new_scripted_card(&[
+            &[0x01, 0x02, 0x03, 0x04, 0x90, 0x00],
+            &[0x90, 0x00],
+            &[0x05, 0x06, 0x07, 0x08, 0x90, 0x00],
+            &[0x63, 0xc2],
+        ]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        let mut calculator = |challenge: &Challenge| -> Result<Vec<u8>, String> {
+            Ok(challenge.get_random().iter().map(|b| b ^ 0xff).collect())
+        };
+        authenticate_externally(&mut session, &class, 0x04, 0x00, 0x81, &mut calculator).unwrap();
+        assert_eq!(
+            authenticate_externally(&mut session, &class, 0x04, 0x00, 0x81, &mut calculator),
+            Err(GetChallengeError::AuthenticationFailed(2))
+        );
+        assert_eq!(
+            card.commands[1],
+            Vec::from([0x00, 0x82, 0x00, 0x81, 0x04, 0xfe, 0xfd, 0xfc, 0xfb])
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// serde helper that represents the bytes as an upper-case hexadecimal string.
+pub(crate) mod hex_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        from_hex(&s).ok_or_else(|| serde::de::Error::custom(format!("invalid hex string: '{}'", s)))
+    }
+}
+
+/// serde helper that represents the optional bytes as an upper-case hexadecimal string.
+pub(crate) mod optional_hex_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_str(&to_hex(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => from_hex(&s)
+                .map(Some)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid hex string: '{}'", s))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// serde helper that represents the fixed-size bytes as an upper-case hexadecimal string.
+pub(crate) mod hex_array {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = from_hex(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid hex string: '{}'", s)))?;
+        bytes.try_into().map_err(|bytes: Vec<u8>| {
+            serde::de::Error::custom(format!(
+                "invalid length of '{}'; this must be {} bytes but {} bytes",
+                s,
+                N,
+                bytes.len()
+            ))
+        })
+    }
+}
+
+/// serde helper that represents the list of bytes as a list of upper-case hexadecimal strings.
+pub(crate) mod hex_bytes_list {
+    use super::*;
+    use serde::ser::SerializeSeq;
+
+    pub fn serialize<S: Serializer>(list: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(list.len()))?;
+        for bytes in list {
+            seq.serialize_element(&to_hex(bytes))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| {
+                from_hex(s)
+                    .ok_or_else(|| serde::de::Error::custom(format!("invalid hex string: '{}'", s)))
+            })
+            .collect()
+    }
+}
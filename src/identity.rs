@@ -0,0 +1,382 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::GetIdentity;
+use crate::response_apdu::ResponseAPDU;
+use crate::simple_tlv::{decode_mcc, decode_mnc};
+
+/// IdentityContext: the context of the identity that GET IDENTITY requests, which is coded in P2; ref
+/// 11.1.23 / ETSI TS 102 221 V15.0.0 and 7.5 / 3GPP TS 31.102
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdentityContext {
+    /// The SUCI that the USIM calculates
+    Suci,
+    /// The contexts that are defined by the other specifications
+    Other(u8),
+}
+
+/// MobileIdentityType: the type of the identity in the 5GS mobile identity; ref 9.11.3.4 / 3GPP TS 24.501
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum MobileIdentityType {
+    NoIdentity = 0b000,
+    Suci = 0b001,
+    FiveGGuti = 0b010,
+    Imei = 0b011,
+    FiveGSTmsi = 0b100,
+    Imeisv = 0b101,
+    MacAddress = 0b110,
+    Eui64 = 0b111,
+}
+
+/// SupiFormat: the format of the SUPI that the SUCI conceals; ref 9.11.3.4 / 3GPP TS 24.501
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SupiFormat {
+    Imsi,
+    NetworkSpecificIdentifier,
+    Other(u8),
+}
+
+/// GetIdentityCommand: ref 11.1.23 / ETSI TS 102 221 V15.0.0
+pub struct GetIdentityCommand {
+    p2: u8,
+    data: Option<Vec<u8>>,
+}
+
+/// Identity: the response data of GET IDENTITY, i.e. the contents of the 5GS mobile identity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    bytes: Vec<u8>,
+}
+
+/// Suci: the SUCI of the IMSI format; ref 9.11.3.4 / 3GPP TS 24.501
+///
+/// The scheme output is the MSIN for the null scheme, and the ephemeral public key, the ciphertext and the
+/// MAC tag for the other schemes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suci {
+    mcc: String,
+    mnc: String,
+    routing_indicator: String,
+    protection_scheme_identifier: u8,
+    home_network_public_key_identifier: u8,
+    scheme_output: Vec<u8>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum GetIdentityError {
+    #[error("unsuccessful status word of GET IDENTITY: '{0:04X}'")]
+    UnsuccessfulStatusWord(u16),
+    #[error("empty identity")]
+    EmptyIdentity,
+    #[error("identity is not SUCI of the IMSI format")]
+    NotImsiSuci,
+    #[error("too short SUCI: {0} bytes")]
+    TooShortSuci(usize),
+}
+
+pub fn new_get_identity_command(
+    context: IdentityContext,
+    data: Option<&[u8]>,
+) -> GetIdentityCommand {
+    GetIdentityCommand {
+        p2: context.get_byte(),
+        data: data.map(|d| d.to_vec()),
+    }
+}
+
+pub fn parse_identity(bytes: &[u8]) -> Result<Identity, GetIdentityError> {
+    if bytes.is_empty() {
+        return Err(GetIdentityError::EmptyIdentity);
+    }
+    Ok(Identity {
+        bytes: bytes.to_vec(),
+    })
+}
+
+impl IdentityContext {
+    pub fn get_byte(&self) -> u8 {
+        match self {
+            IdentityContext::Suci => 0x01,
+            IdentityContext::Other(b) => *b,
+        }
+    }
+}
+
+impl MobileIdentityType {
+    pub fn from_byte(b: u8) -> MobileIdentityType {
+        use MobileIdentityType::*;
+        match b & 0b111 {
+            0b000 => NoIdentity,
+            0b001 => Suci,
+            0b010 => FiveGGuti,
+            0b011 => Imei,
+            0b100 => FiveGSTmsi,
+            0b101 => Imeisv,
+            0b110 => MacAddress,
+            _ => Eui64,
+        }
+    }
+}
+
+impl GetIdentityCommand {
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &GetIdentity {},
+            0x00,
+            self.p2,
+            Some(0x00),
+            self.data.as_deref(),
+        )
+    }
+
+    pub fn parse_response(&self, response: &ResponseAPDU) -> Result<Identity, GetIdentityError> {
+        if !response.is_normal_ending() {
+            return Err(GetIdentityError::UnsuccessfulStatusWord(
+                response.get_status_word(),
+            ));
+        }
+        parse_identity(response.get_data())
+    }
+}
+
+impl Identity {
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn get_type(&self) -> MobileIdentityType {
+        MobileIdentityType::from_byte(self.bytes[0])
+    }
+
+    /// Returns the SUPI format for SUCI; `None` for the other identities.
+    pub fn get_supi_format(&self) -> Option<SupiFormat> {
+        if self.get_type() != MobileIdentityType::Suci {
+            return None;
+        }
+        Some(match (self.bytes[0] >> 4) & 0b111 {
+            0b000 => SupiFormat::Imsi,
+            0b001 => SupiFormat::NetworkSpecificIdentifier,
+            b => SupiFormat::Other(b),
+        })
+    }
+
+    /// Decodes SUCI of the IMSI format.
+    pub fn get_suci(&self) -> Result<Suci, GetIdentityError> {
+        if self.get_supi_format() != Some(SupiFormat::Imsi) {
+            return Err(GetIdentityError::NotImsiSuci);
+        }
+        if self.bytes.len() < 8 {
+            return Err(GetIdentityError::TooShortSuci(self.bytes.len()));
+        }
+        let plmn = [self.bytes[1], self.bytes[2], self.bytes[3]];
+        let routing_indicator = [
+            self.bytes[4] & 0x0f,
+            self.bytes[4] >> 4,
+            self.bytes[5] & 0x0f,
+            self.bytes[5] >> 4,
+        ]
+        .iter()
+        .filter(|d| **d != 0xf)
+        .map(|d| (b'0' + d) as char)
+        .collect();
+        Ok(Suci {
+            mcc: decode_mcc(&plmn),
+            mnc: decode_mnc(&plmn),
+            routing_indicator,
+            protection_scheme_identifier: self.bytes[6] & 0x0f,
+            home_network_public_key_identifier: self.bytes[7],
+            scheme_output: self.bytes[8..].to_vec(),
+        })
+    }
+}
+
+impl Suci {
+    pub fn get_mcc(&self) -> &str {
+        &self.mcc
+    }
+
+    pub fn get_mnc(&self) -> &str {
+        &self.mnc
+    }
+
+    pub fn get_routing_indicator(&self) -> &str {
+        &self.routing_indicator
+    }
+
+    /// Returns the protection scheme: 0 for the null scheme, 1 for ECIES profile A and 2 for profile B.
+    pub fn get_protection_scheme_identifier(&self) -> u8 {
+        self.protection_scheme_identifier
+    }
+
+    pub fn get_home_network_public_key_identifier(&self) -> u8 {
+        self.home_network_public_key_identifier
+    }
+
+    pub fn get_scheme_output(&self) -> &[u8] {
+        &self.scheme_output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::identity::{
+        new_get_identity_command, parse_identity, GetIdentityError, IdentityContext,
+        MobileIdentityType, SupiFormat,
+    };
+    use crate::response_apdu::new_response_apdu;
+
+    #[test]
+    fn should_get_suci() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let command = new_get_identity_command(IdentityContext::Suci, None);
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x80, 0x78, 0x00, 0x01, 0x00])
+        );
+
+        // the null scheme of MCC 001, MNC 01 and MSIN 0123456789
+        let identity = command
+            .parse_response(&new_response_apdu(
+                Vec::from([
+                    0x01, 0x00, 0xf1, 0x10, 0xf0, 0xff, 0x00, 0x00, 0x10, 0x32, 0x54, 0x76, 0x98,
+                ]),
+                0x90,
+                0x00,
+            ))
+            .unwrap();
+        assert_eq!(identity.get_type(), MobileIdentityType::Suci);
+        assert_eq!(identity.get_supi_format(), Some(SupiFormat::Imsi));
+        let suci = identity.get_suci().unwrap();
+        assert_eq!(suci.get_mcc(), "001");
+        assert_eq!(suci.get_mnc(), "01");
+        assert_eq!(suci.get_routing_indicator(), "0");
+        assert_eq!(suci.get_protection_scheme_identifier(), 0);
+        assert_eq!(suci.get_scheme_output(), &[0x10, 0x32, 0x54, 0x76, 0x98]);
+
+        assert_eq!(
+            command
+                .parse_response(&new_response_apdu(Vec::new(), 0x6a, 0x86))
+                .unwrap_err(),
+            GetIdentityError::UnsuccessfulStatusWord(0x6a86)
+        );
+        let guti = command
+            .parse_response(&new_response_apdu(Vec::from([0xf2, 0x00]), 0x90, 0x00))
+            .unwrap();
+        assert_eq!(guti.get_type(), MobileIdentityType::FiveGGuti);
+        assert_eq!(guti.get_suci().unwrap_err(), GetIdentityError::NotImsiSuci);
+    }
+
+    #[test]
+    fn should_encode_identity_context_in_p2() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            1,
+        )
+        .unwrap();
+        assert_eq!(IdentityContext::Suci.get_byte(), 0x01);
+        assert_eq!(IdentityContext::Other(0x02).get_byte(), 0x02);
+
+        let command = new_get_identity_command(IdentityContext::Other(0x02), Some(&[0xaa, 0xbb]));
+        assert_eq!(command.get_p2(), 0x02);
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x81, 0x78, 0x00, 0x02, 0x02, 0xaa, 0xbb, 0x00])
+        );
+    }
+
+    #[test]
+    fn should_decode_identity_types() {
+        use MobileIdentityType::*;
+        for (b, typ) in [
+            (0x00, NoIdentity),
+            (0x01, Suci),
+            (0x02, FiveGGuti),
+            (0x03, Imei),
+            (0x04, FiveGSTmsi),
+            (0x05, Imeisv),
+            (0x06, MacAddress),
+            (0x07, Eui64),
+        ] {
+            assert_eq!(MobileIdentityType::from_byte(b), typ);
+            // the odd/even indication and the other bits are ignored
+            assert_eq!(MobileIdentityType::from_byte(b | 0xf8), typ);
+        }
+
+        let imei = parse_identity(&[0x4b, 0x09, 0x51, 0x24, 0x30, 0x32, 0x57, 0x81, 0x00]).unwrap();
+        assert_eq!(imei.get_type(), Imei);
+        assert_eq!(imei.get_supi_format(), None);
+        assert_eq!(imei.get_suci().unwrap_err(), GetIdentityError::NotImsiSuci);
+        let tmsi = parse_identity(&[0xf4, 0x00, 0x41, 0x12, 0x34, 0x56, 0x78]).unwrap();
+        assert_eq!(tmsi.get_type(), FiveGSTmsi);
+        assert_eq!(tmsi.get_supi_format(), None);
+
+        let nsi = parse_identity(&[0x11, 0x40, 0x75, 0x73, 0x65, 0x72]).unwrap();
+        assert_eq!(nsi.get_type(), Suci);
+        assert_eq!(
+            nsi.get_supi_format(),
+            Some(SupiFormat::NetworkSpecificIdentifier)
+        );
+        assert_eq!(nsi.get_suci().unwrap_err(), GetIdentityError::NotImsiSuci);
+        assert_eq!(
+            parse_identity(&[0x71]).unwrap().get_supi_format(),
+            Some(SupiFormat::Other(0x07))
+        );
+    }
+
+    #[test]
+    fn should_fail_decode_identity() {
+        assert_eq!(
+            parse_identity(&[]).unwrap_err(),
+            GetIdentityError::EmptyIdentity
+        );
+        let command = new_get_identity_command(IdentityContext::Suci, None);
+        assert_eq!(
+            command
+                .parse_response(&new_response_apdu(Vec::new(), 0x90, 0x00))
+                .unwrap_err(),
+            GetIdentityError::EmptyIdentity
+        );
+        assert_eq!(
+            command
+                .parse_response(&new_response_apdu(Vec::new(), 0x69, 0x85))
+                .unwrap_err(),
+            GetIdentityError::UnsuccessfulStatusWord(0x6985)
+        );
+
+        // the SUCI that ends before the home network public key identifier
+        let identity = parse_identity(&[0x01, 0x00, 0xf1, 0x10, 0xf0, 0xff, 0x01]).unwrap();
+        assert_eq!(
+            identity.get_suci().unwrap_err(),
+            GetIdentityError::TooShortSuci(7)
+        );
+
+        // the SUCI of ECIES profile A with the routing indicator 1234 and the 3 digit MNC 123
+        let suci = parse_identity(&[0x01, 0x02, 0x36, 0x21, 0x21, 0x43, 0x01, 0x05, 0xaa, 0xbb])
+            .unwrap()
+            .get_suci()
+            .unwrap();
+        assert_eq!(suci.get_mcc(), "206");
+        assert_eq!(suci.get_mnc(), "123");
+        assert_eq!(suci.get_routing_indicator(), "1234");
+        assert_eq!(suci.get_protection_scheme_identifier(), 1);
+        assert_eq!(suci.get_home_network_public_key_identifier(), 0x05);
+    }
+}
@@ -229,6 +229,18 @@ impl Instruction for Authenticate {
     }
 }
 
+pub struct AuthenticateOdd {}
+
+impl Instruction for AuthenticateOdd {
+    fn get_byte(&self, class: &Class) -> Result<u8, InstructionError> {
+        let code = 0x89;
+        match validate(code, class, &[0x00, 0x40, 0x60], false) {
+            None => Ok(code),
+            Some(e) => Err(e),
+        }
+    }
+}
+
 pub struct GetChallenge {}
 
 impl Instruction for GetChallenge {
@@ -241,6 +253,18 @@ impl Instruction for GetChallenge {
     }
 }
 
+pub struct ExternalAuthenticate {}
+
+impl Instruction for ExternalAuthenticate {
+    fn get_byte(&self, class: &Class) -> Result<u8, InstructionError> {
+        let code = 0x82;
+        match validate(code, class, &[0x00, 0x40, 0x60], false) {
+            None => Ok(code),
+            Some(e) => Err(e),
+        }
+    }
+}
+
 pub struct TerminalCapability {}
 
 impl Instruction for TerminalCapability {
@@ -373,6 +397,50 @@ impl Instruction for GetResponse {
     }
 }
 
+/// The names of the instructions above by their codes; ref 10.1.2 / ETSI TS 102 221 V15.0.0
+const INSTRUCTION_NAMES: [(u8, &str); 32] = [
+    (0xa4, "SELECT FILE"),
+    (0xf2, "STATUS"),
+    (0xb0, "READ BINARY"),
+    (0xd6, "UPDATE BINARY"),
+    (0xb2, "READ RECORD"),
+    (0xdc, "UPDATE RECORD"),
+    (0xa2, "SEARCH RECORD"),
+    (0x32, "INCREASE"),
+    (0xcb, "RETRIEVE DATA"),
+    (0xdb, "SET DATA"),
+    (0x20, "VERIFY PIN"),
+    (0x24, "CHANGE PIN"),
+    (0x26, "DISABLE PIN"),
+    (0x28, "ENABLE PIN"),
+    (0x2c, "UNBLOCK PIN"),
+    (0x04, "DEACTIVATE FILE"),
+    (0x44, "ACTIVATE FILE"),
+    (0x88, "AUTHENTICATE"),
+    (0x89, "AUTHENTICATE"),
+    (0x84, "GET CHALLENGE"),
+    (0x82, "EXTERNAL AUTHENTICATE"),
+    (0xaa, "TERMINAL CAPABILITY"),
+    (0x10, "TERMINAL PROFILE"),
+    (0xc2, "ENVELOPE"),
+    (0x12, "FETCH"),
+    (0x14, "TERMINAL RESPONSE"),
+    (0x70, "MANAGE CHANNEL"),
+    (0x73, "MANAGE SECURE CHANNEL"),
+    (0x75, "TRANSACT DATA"),
+    (0x76, "SUSPEND UICC"),
+    (0x78, "GET IDENTITY"),
+    (0xc0, "GET RESPONSE"),
+];
+
+/// Returns the name of the instruction code, e.g. "SELECT FILE" for 'A4'.
+pub fn get_instruction_name(code: u8) -> Option<&'static str> {
+    INSTRUCTION_NAMES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, name)| *name)
+}
+
 fn validate(
     instruction: u8,
     class: &Class,
@@ -408,7 +476,9 @@ mod test {
         ClassTypeForStandardLogicalChannels, SecureMessagingIndicationForExtendedLogicalChannels,
         SecureMessagingIndicationForStandardLogicalChannels,
     };
-    use crate::instruction::{Fetch, Instruction, InstructionError, SelectFile};
+    use crate::instruction::{
+        get_instruction_name, Fetch, Instruction, InstructionError, SelectFile,
+    };
 
     #[test]
     fn should_get_byte_successfully() {
@@ -517,4 +587,11 @@ mod test {
             InstructionError::InvalidClassByte(0x12, "'0x80'".into(), 0b10000001)
         );
     }
+
+    #[test]
+    fn should_get_instruction_name() {
+        assert_eq!(get_instruction_name(0xa4), Some("SELECT FILE"));
+        assert_eq!(get_instruction_name(0x89), Some("AUTHENTICATE"));
+        assert_eq!(get_instruction_name(0xff), None);
+    }
 }
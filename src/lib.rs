@@ -1,3 +1,40 @@
+pub mod authenticate;
+pub mod ber_tlv;
+pub mod chaining;
 pub mod class;
 pub mod command_apdu;
+pub mod comprehension_tlv;
+pub mod ef_dir;
+pub mod envelope;
+pub mod fcp;
+pub mod file;
+pub mod file_activation;
+pub mod get_challenge;
+mod hex;
+pub mod identity;
 pub mod instruction;
+pub mod milenage;
+pub mod proactive_command;
+pub mod proactive_session;
+pub mod profile;
+pub mod read_binary;
+pub mod read_record;
+pub mod response_apdu;
+pub mod retrieve_data;
+pub mod secure_channel;
+pub mod secure_messaging;
+pub mod select_file;
+pub mod session;
+pub mod set_data;
+pub mod simple_tlv;
+pub mod status;
+pub mod suspend;
+pub mod terminal_capability;
+pub mod terminal_profile;
+pub mod terminal_response;
+#[cfg(test)]
+mod testing;
+pub mod trace;
+pub mod transport;
+pub mod virtual_uicc;
+pub mod walker;
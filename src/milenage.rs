@@ -0,0 +1,268 @@
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use aes::Aes128;
+
+/// Milenage: the authentication and key generation functions f1, f1*, f2, f3, f4, f5 and f5*;
+/// ref 3GPP TS 35.206
+///
+/// This is a reference implementation to simulate the network and the card; it is not hardened against
+/// side-channel attacks.
+pub struct Milenage {
+    cipher: Aes128,
+    opc: [u8; 16],
+}
+
+/// MilenageOutput: the outputs of f2, f3, f4 and f5 for a RAND.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MilenageOutput {
+    res: [u8; 8],
+    ck: [u8; 16],
+    ik: [u8; 16],
+    ak: [u8; 6],
+}
+
+/// Creates Milenage with the subscriber key K and the operator variant OP; OPc is derived from them.
+pub fn new_milenage_with_op(k: &[u8; 16], op: &[u8; 16]) -> Milenage {
+    new_milenage_with_opc(k, &compute_opc(k, op))
+}
+
+/// Creates Milenage with the subscriber key K and the derived operator variant OPc.
+pub fn new_milenage_with_opc(k: &[u8; 16], opc: &[u8; 16]) -> Milenage {
+    Milenage {
+        cipher: Aes128::new(GenericArray::from_slice(k)),
+        opc: *opc,
+    }
+}
+
+/// Computes OPc = OP xor E[OP]K
+pub fn compute_opc(k: &[u8; 16], op: &[u8; 16]) -> [u8; 16] {
+    let cipher = Aes128::new(GenericArray::from_slice(k));
+    xor(&encrypt(&cipher, op), op)
+}
+
+/// Computes SRES from RES by the conversion function c2; ref 6.8.1.2 / 3GPP TS 33.102
+pub fn compute_sres(res: &[u8]) -> [u8; 4] {
+    let mut sres = [0u8; 4];
+    for (i, b) in res.iter().enumerate() {
+        sres[i % 4] ^= b;
+    }
+    sres
+}
+
+/// Computes Kc from CK and IK by the conversion function c3; ref 6.8.1.2 / 3GPP TS 33.102
+pub fn compute_kc(ck: &[u8; 16], ik: &[u8; 16]) -> [u8; 8] {
+    let mut kc = [0u8; 8];
+    for i in 0..8 {
+        kc[i] = ck[i] ^ ck[i + 8] ^ ik[i] ^ ik[i + 8];
+    }
+    kc
+}
+
+fn encrypt(cipher: &Aes128, input: &[u8; 16]) -> [u8; 16] {
+    let mut block = GenericArray::clone_from_slice(input);
+    cipher.encrypt_block(&mut block);
+    block.into()
+}
+
+fn xor(a: &[u8; 16], b: &[u8; 16]) -> [u8; 16] {
+    let mut out = [0u8; 16];
+    for i in 0..16 {
+        out[i] = a[i] ^ b[i];
+    }
+    out
+}
+
+/// Rotates the 128-bit value cyclically to the left by the bytes.
+fn rotate(input: &[u8; 16], bytes: usize) -> [u8; 16] {
+    let mut out = *input;
+    out.rotate_left(bytes);
+    out
+}
+
+impl Milenage {
+    pub fn get_opc(&self) -> &[u8; 16] {
+        &self.opc
+    }
+
+    /// Computes OUT1 of which the former half is MAC-A (f1) and the latter is MAC-S (f1*).
+    fn out1(&self, rand: &[u8; 16], sqn: &[u8; 6], amf: &[u8; 2]) -> [u8; 16] {
+        let temp = encrypt(&self.cipher, &xor(rand, &self.opc));
+        let mut in1 = [0u8; 16];
+        for half in in1.chunks_mut(8) {
+            half[..6].copy_from_slice(sqn);
+            half[6..].copy_from_slice(amf);
+        }
+        // r1 = 64 bits and c1 = 0
+        let input = xor(&temp, &rotate(&xor(&in1, &self.opc), 8));
+        xor(&encrypt(&self.cipher, &input), &self.opc)
+    }
+
+    /// Computes OUTn for n >= 2 with rn in bytes and the last byte of cn.
+    fn out(&self, temp: &[u8; 16], rotation: usize, constant: u8) -> [u8; 16] {
+        let mut input = rotate(&xor(temp, &self.opc), rotation);
+        input[15] ^= constant;
+        xor(&encrypt(&self.cipher, &input), &self.opc)
+    }
+
+    /// f1: the network authentication function that computes MAC-A.
+    pub fn f1(&self, rand: &[u8; 16], sqn: &[u8; 6], amf: &[u8; 2]) -> [u8; 8] {
+        self.out1(rand, sqn, amf)[..8].try_into().unwrap()
+    }
+
+    /// f1*: the re-synchronisation message authentication function that computes MAC-S.
+    pub fn f1_star(&self, rand: &[u8; 16], sqn: &[u8; 6], amf: &[u8; 2]) -> [u8; 8] {
+        self.out1(rand, sqn, amf)[8..].try_into().unwrap()
+    }
+
+    /// f2, f3, f4 and f5: computes RES, CK, IK and AK.
+    pub fn f2345(&self, rand: &[u8; 16]) -> MilenageOutput {
+        let temp = encrypt(&self.cipher, &xor(rand, &self.opc));
+        let out2 = self.out(&temp, 0, 0x01);
+        MilenageOutput {
+            res: out2[8..].try_into().unwrap(),
+            ck: self.out(&temp, 4, 0x02),
+            ik: self.out(&temp, 8, 0x04),
+            ak: out2[..6].try_into().unwrap(),
+        }
+    }
+
+    /// f5*: the anonymity key derivation function for the re-synchronisation.
+    pub fn f5_star(&self, rand: &[u8; 16]) -> [u8; 6] {
+        let temp = encrypt(&self.cipher, &xor(rand, &self.opc));
+        self.out(&temp, 12, 0x08)[..6].try_into().unwrap()
+    }
+
+    /// Generates AUTN = SQN xor AK || AMF || MAC-A, as the network does.
+    pub fn generate_autn(&self, rand: &[u8; 16], sqn: &[u8; 6], amf: &[u8; 2]) -> [u8; 16] {
+        let ak = self.f2345(rand).ak;
+        let mut autn = [0u8; 16];
+        for i in 0..6 {
+            autn[i] = sqn[i] ^ ak[i];
+        }
+        autn[6..8].copy_from_slice(amf);
+        autn[8..].copy_from_slice(&self.f1(rand, sqn, amf));
+        autn
+    }
+
+    /// Generates AUTS = SQNms xor AK* || MAC-S, as the card does on the synchronisation failure.
+    pub fn generate_auts(&self, rand: &[u8; 16], sqn_ms: &[u8; 6]) -> [u8; 14] {
+        let ak_star = self.f5_star(rand);
+        let mut auts = [0u8; 14];
+        for i in 0..6 {
+            auts[i] = sqn_ms[i] ^ ak_star[i];
+        }
+        // the dummy AMF that is used for the re-synchronisation; ref 6.3.3 / 3GPP TS 33.102
+        auts[6..].copy_from_slice(&self.f1_star(rand, sqn_ms, &[0x00, 0x00]));
+        auts
+    }
+}
+
+impl MilenageOutput {
+    pub fn get_res(&self) -> &[u8; 8] {
+        &self.res
+    }
+
+    pub fn get_ck(&self) -> &[u8; 16] {
+        &self.ck
+    }
+
+    pub fn get_ik(&self) -> &[u8; 16] {
+        &self.ik
+    }
+
+    pub fn get_ak(&self) -> &[u8; 6] {
+        &self.ak
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hex::from_hex;
+    use crate::milenage::{compute_kc, compute_opc, compute_sres, new_milenage_with_op};
+
+    fn hex<const N: usize>(s: &str) -> [u8; N] {
+        from_hex(s).unwrap().try_into().unwrap()
+    }
+
+    struct TestSet {
+        k: &'static str,
+        rand: &'static str,
+        sqn: &'static str,
+        amf: &'static str,
+        op: &'static str,
+        opc: &'static str,
+        f1: &'static str,
+        f1_star: &'static str,
+        f2: &'static str,
+        f3: &'static str,
+        f4: &'static str,
+        f5: &'static str,
+        f5_star: &'static str,
+    }
+
+    // ref 4 / 3GPP TS 35.208
+    const TEST_SETS: [TestSet; 2] = [
+        TestSet {
+            k: "465B5CE8B199B49FAA5F0A2EE238A6BC",
+            rand: "23553CBE9637A89D218AE64DAE47BF35",
+            sqn: "FF9BB4D0B607",
+            amf: "B9B9",
+            op: "CDC202D5123E20F62B6D676AC72CB318",
+            opc: "CD63CB71954A9F4E48A5994E37A02BAF",
+            f1: "4A9FFAC354DFAFB3",
+            f1_star: "01CFAF9EC4E871E9",
+            f2: "A54211D5E3BA50BF",
+            f3: "B40BA9A3C58B2A05BBF0D987B21BF8CB",
+            f4: "F769BCD751044604127672711C6D3441",
+            f5: "AA689C648370",
+            f5_star: "451E8BECA43B",
+        },
+        TestSet {
+            k: "0396EB317B6D1C36F19C1C84CD6FFD16",
+            rand: "C00D603103DCEE52C4478119494202E8",
+            sqn: "FD8EEF40DF7D",
+            amf: "AF17",
+            op: "FF53BADE17DF5D4E793073CE9D7579FA",
+            opc: "53C15671C60A4B731C55B4A441C0BDE2",
+            f1: "5DF5B31807E258B0",
+            f1_star: "A8C016E51EF4A343",
+            f2: "D3A628ED988620F0",
+            f3: "58C433FF7A7082ACD424220F2B67C556",
+            f4: "21A8C1F929702ADB3E738488B9F5C5DA",
+            f5: "C47783995F72",
+            f5_star: "30F1197061C1",
+        },
+    ];
+
+    #[test]
+    fn should_compute_milenage_test_sets() {
+        for set in TEST_SETS {
+            let k = hex::<16>(set.k);
+            let rand = hex::<16>(set.rand);
+            let sqn = hex::<6>(set.sqn);
+            let amf = hex::<2>(set.amf);
+            let op = hex::<16>(set.op);
+            assert_eq!(compute_opc(&k, &op), hex::<16>(set.opc));
+
+            let milenage = new_milenage_with_op(&k, &op);
+            assert_eq!(milenage.f1(&rand, &sqn, &amf), hex::<8>(set.f1));
+            assert_eq!(milenage.f1_star(&rand, &sqn, &amf), hex::<8>(set.f1_star));
+            let output = milenage.f2345(&rand);
+            assert_eq!(output.get_res(), &hex::<8>(set.f2));
+            assert_eq!(output.get_ck(), &hex::<16>(set.f3));
+            assert_eq!(output.get_ik(), &hex::<16>(set.f4));
+            assert_eq!(output.get_ak(), &hex::<6>(set.f5));
+            assert_eq!(milenage.f5_star(&rand), hex::<6>(set.f5_star));
+        }
+    }
+
+    #[test]
+    fn should_convert_to_gsm_parameters() {
+        // ref 5.1 / 3GPP TS 35.208 test set 1
+        let set = &TEST_SETS[0];
+        assert_eq!(compute_sres(&hex::<8>(set.f2)), hex::<4>("46F8416A"));
+        assert_eq!(
+            compute_kc(&hex::<16>(set.f3), &hex::<16>(set.f4)),
+            hex::<8>("EAE4BE823AF9A08B")
+        );
+    }
+}
@@ -0,0 +1,687 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{parse_ber_tlv, BerTlvError};
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::comprehension_tlv::{
+    check_comprehension_required, find_comprehension_tlv, parse_comprehension_tlvs,
+    ComprehensionTlv, ComprehensionTlvError, ADDRESS_TAG, AID_TAG, ALPHA_IDENTIFIER_TAG,
+    BEARER_DESCRIPTION_TAG, BUFFER_SIZE_TAG, CHANNEL_DATA_LENGTH_TAG, CHANNEL_DATA_TAG,
+    COMMAND_DETAILS_TAG, DEFAULT_TEXT_TAG, DEVICE_IDENTITY_TAG, DURATION_TAG, EVENT_LIST_TAG,
+    FILE_LIST_TAG, IMMEDIATE_RESPONSE_TAG, ITEM_IDENTIFIER_TAG, ITEM_TAG, OTHER_ADDRESS_TAG,
+    RESPONSE_LENGTH_TAG, SMS_TPDU_TAG, TEXT_STRING_TAG, TIMER_IDENTIFIER_TAG, TIMER_VALUE_TAG,
+    TRANSPORT_LEVEL_TAG,
+};
+use crate::instruction::Fetch;
+use crate::simple_tlv::{
+    parse_address, parse_alpha_identifier, parse_bearer_description, parse_channel_data,
+    parse_duration, parse_item, parse_text_string, value_at_least, Address, AlphaIdentifier,
+    BearerDescription, ChannelData, Duration, Item, SimpleTlvError, TextString,
+};
+
+/// Proactive UICC command tag of the BER-TLV; ref 9.1 / ETSI TS 101 220 V15.0.0
+pub const PROACTIVE_COMMAND_TAG: u32 = 0xd0;
+
+pub const DEVICE_KEYPAD: u8 = 0x01;
+pub const DEVICE_DISPLAY: u8 = 0x02;
+pub const DEVICE_EARPIECE: u8 = 0x03;
+pub const DEVICE_UICC: u8 = 0x81;
+pub const DEVICE_TERMINAL: u8 = 0x82;
+pub const DEVICE_NETWORK: u8 = 0x83;
+
+/// TypeOfCommand: ref 9.4 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum TypeOfCommand {
+    Refresh = 0x01,
+    MoreTime = 0x02,
+    PollInterval = 0x03,
+    PollingOff = 0x04,
+    SetUpEventList = 0x05,
+    SetUpCall = 0x10,
+    SendSs = 0x11,
+    SendUssd = 0x12,
+    SendShortMessage = 0x13,
+    SendDtmf = 0x14,
+    LaunchBrowser = 0x15,
+    PlayTone = 0x20,
+    DisplayText = 0x21,
+    GetInkey = 0x22,
+    GetInput = 0x23,
+    SelectItem = 0x24,
+    SetUpMenu = 0x25,
+    ProvideLocalInformation = 0x26,
+    TimerManagement = 0x27,
+    SetUpIdleModeText = 0x28,
+    PerformCardApdu = 0x30,
+    PowerOnCard = 0x31,
+    PowerOffCard = 0x32,
+    GetReaderStatus = 0x33,
+    RunAtCommand = 0x34,
+    LanguageNotification = 0x35,
+    OpenChannel = 0x40,
+    CloseChannel = 0x41,
+    ReceiveData = 0x42,
+    SendData = 0x43,
+    GetChannelStatus = 0x44,
+}
+
+const TYPES_OF_COMMAND: [TypeOfCommand; 31] = {
+    use TypeOfCommand::*;
+    [
+        Refresh,
+        MoreTime,
+        PollInterval,
+        PollingOff,
+        SetUpEventList,
+        SetUpCall,
+        SendSs,
+        SendUssd,
+        SendShortMessage,
+        SendDtmf,
+        LaunchBrowser,
+        PlayTone,
+        DisplayText,
+        GetInkey,
+        GetInput,
+        SelectItem,
+        SetUpMenu,
+        ProvideLocalInformation,
+        TimerManagement,
+        SetUpIdleModeText,
+        PerformCardApdu,
+        PowerOnCard,
+        PowerOffCard,
+        GetReaderStatus,
+        RunAtCommand,
+        LanguageNotification,
+        OpenChannel,
+        CloseChannel,
+        ReceiveData,
+        SendData,
+        GetChannelStatus,
+    ]
+};
+
+impl TypeOfCommand {
+    pub fn from_byte(b: u8) -> Option<TypeOfCommand> {
+        TYPES_OF_COMMAND.iter().copied().find(|t| *t as u8 == b)
+    }
+}
+
+/// CommandDetails: ref 8.6 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandDetails {
+    number: u8,
+    type_of_command: u8,
+    qualifier: u8,
+}
+
+/// DeviceIdentities: ref 8.7 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceIdentities {
+    source: u8,
+    destination: u8,
+}
+
+/// ProactiveCommandBody: the command-specific data objects of the proactive command; ref 6.6 / ETSI TS
+/// 102 223 V15.0.0
+///
+/// The commands that are not decoded are represented by `Other`, and their data objects are available from
+/// `ProactiveCommand`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProactiveCommandBody {
+    DisplayText {
+        text: TextString,
+        immediate_response: bool,
+        duration: Option<Duration>,
+    },
+    GetInkey {
+        text: TextString,
+        duration: Option<Duration>,
+    },
+    GetInput {
+        text: TextString,
+        minimum_response_length: u8,
+        maximum_response_length: u8,
+        default_text: Option<TextString>,
+    },
+    SelectItem {
+        alpha_identifier: Option<AlphaIdentifier>,
+        items: Vec<Item>,
+        default_item_identifier: Option<u8>,
+    },
+    /// An empty list of the items means the removal of the menu.
+    SetUpMenu {
+        alpha_identifier: Option<AlphaIdentifier>,
+        items: Vec<Item>,
+    },
+    SendShortMessage {
+        alpha_identifier: Option<AlphaIdentifier>,
+        address: Option<Address>,
+        tpdu: Vec<u8>,
+    },
+    SetUpEventList {
+        events: Vec<u8>,
+    },
+    /// The type of the information is indicated by the command qualifier.
+    ProvideLocalInformation,
+    /// The type of the operation is indicated by the command qualifier.
+    TimerManagement {
+        timer_identifier: u8,
+        timer_value: Option<[u8; 3]>,
+    },
+    PollInterval {
+        duration: Duration,
+    },
+    Refresh {
+        file_list: Option<Vec<u8>>,
+        aid: Option<Vec<u8>>,
+    },
+    /// Both the local address and the data destination address are Other Address data objects; the data
+    /// destination address is the one after the UICC/terminal interface transport level: ref 6.6.27 / ETSI TS
+    /// 102 223 V15.0.0
+    OpenChannel {
+        alpha_identifier: Option<AlphaIdentifier>,
+        address: Option<Address>,
+        bearer_description: BearerDescription,
+        buffer_size: u16,
+        local_address: Option<Vec<u8>>,
+        transport_level: Option<Vec<u8>>,
+        data_destination_address: Option<Vec<u8>>,
+    },
+    CloseChannel {
+        alpha_identifier: Option<AlphaIdentifier>,
+    },
+    ReceiveData {
+        alpha_identifier: Option<AlphaIdentifier>,
+        channel_data_length: u8,
+    },
+    SendData {
+        alpha_identifier: Option<AlphaIdentifier>,
+        channel_data: ChannelData,
+    },
+    Other,
+}
+
+/// ProactiveCommand: the proactive command that is fetched from the UICC; ref 6.6 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProactiveCommand {
+    command_details: CommandDetails,
+    device_identities: DeviceIdentities,
+    body: ProactiveCommandBody,
+    data_objects: Vec<ComprehensionTlv>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ProactiveCommandError {
+    #[error("invalid BER-TLV of the proactive command: {0}")]
+    InvalidBerTlv(#[from] BerTlvError),
+    #[error("unexpected tag of the proactive command; this must be 'D0' but '{0:X}'")]
+    UnexpectedTemplateTag(u32),
+    #[error("invalid COMPREHENSION-TLV in the proactive command: {0}")]
+    InvalidComprehensionTlv(#[from] ComprehensionTlvError),
+    #[error("mandatory data object '{0:02X}' is missing in the proactive command")]
+    MissingDataObject(u16),
+    #[error("invalid data object in the proactive command: {0}")]
+    InvalidDataObject(#[from] SimpleTlvError),
+}
+
+/// FetchCommand: ref 11.2.3 / ETSI TS 102 221 V15.0.0
+pub struct FetchCommand {
+    le: u8,
+}
+
+/// Creates FETCH with the length that is indicated by '91XX'.
+pub fn new_fetch_command(length: u8) -> FetchCommand {
+    FetchCommand { le: length }
+}
+
+impl FetchCommand {
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(class, &Fetch {}, 0x00, 0x00, Some(self.le), None)
+    }
+}
+
+fn required(
+    tlvs: &[ComprehensionTlv],
+    tag: u16,
+) -> Result<&ComprehensionTlv, ProactiveCommandError> {
+    find_comprehension_tlv(tlvs, tag).ok_or(ProactiveCommandError::MissingDataObject(tag))
+}
+
+fn optional_value(tlvs: &[ComprehensionTlv], tag: u16) -> Option<Vec<u8>> {
+    find_comprehension_tlv(tlvs, tag).map(|tlv| tlv.get_value().to_vec())
+}
+
+fn optional_duration(tlvs: &[ComprehensionTlv]) -> Result<Option<Duration>, ProactiveCommandError> {
+    find_comprehension_tlv(tlvs, DURATION_TAG)
+        .map(parse_duration)
+        .transpose()
+        .map_err(ProactiveCommandError::from)
+}
+
+fn optional_address(tlvs: &[ComprehensionTlv]) -> Result<Option<Address>, ProactiveCommandError> {
+    find_comprehension_tlv(tlvs, ADDRESS_TAG)
+        .map(parse_address)
+        .transpose()
+        .map_err(ProactiveCommandError::from)
+}
+
+/// Returns the local address and the data destination address of OPEN CHANNEL by their positions.
+fn other_addresses(tlvs: &[ComprehensionTlv]) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+    let transport_level = tlvs
+        .iter()
+        .position(|tlv| tlv.get_tag() == TRANSPORT_LEVEL_TAG);
+    let mut local_address = None;
+    let mut data_destination_address = None;
+    for (i, tlv) in tlvs.iter().enumerate() {
+        if tlv.get_tag() != OTHER_ADDRESS_TAG {
+            continue;
+        }
+        let address = match transport_level {
+            Some(position) if i > position => &mut data_destination_address,
+            _ => &mut local_address,
+        };
+        address.get_or_insert_with(|| tlv.get_value().to_vec());
+    }
+    (local_address, data_destination_address)
+}
+
+fn parse_items(tlvs: &[ComprehensionTlv]) -> Vec<Item> {
+    tlvs.iter()
+        .filter(|tlv| tlv.get_tag() == ITEM_TAG)
+        .filter_map(parse_item)
+        .collect()
+}
+
+/// Parses the response data of FETCH, i.e. the proactive UICC command BER-TLV.
+pub fn parse_proactive_command(bytes: &[u8]) -> Result<ProactiveCommand, ProactiveCommandError> {
+    let (template, _) = parse_ber_tlv(bytes)?;
+    if template.get_tag() != PROACTIVE_COMMAND_TAG {
+        return Err(ProactiveCommandError::UnexpectedTemplateTag(
+            template.get_tag(),
+        ));
+    }
+    let tlvs = parse_comprehension_tlvs(template.get_value())?;
+
+    let details = value_at_least(required(&tlvs, COMMAND_DETAILS_TAG)?, 3)?;
+    let command_details = CommandDetails {
+        number: details[0],
+        type_of_command: details[1],
+        qualifier: details[2],
+    };
+    let identities = value_at_least(required(&tlvs, DEVICE_IDENTITY_TAG)?, 2)?;
+    let device_identities = DeviceIdentities {
+        source: identities[0],
+        destination: identities[1],
+    };
+
+    let alpha_identifier =
+        find_comprehension_tlv(&tlvs, ALPHA_IDENTIFIER_TAG).map(parse_alpha_identifier);
+    let body = match TypeOfCommand::from_byte(command_details.type_of_command) {
+        Some(TypeOfCommand::DisplayText) => ProactiveCommandBody::DisplayText {
+            text: parse_text_string(required(&tlvs, TEXT_STRING_TAG)?),
+            immediate_response: find_comprehension_tlv(&tlvs, IMMEDIATE_RESPONSE_TAG).is_some(),
+            duration: optional_duration(&tlvs)?,
+        },
+        Some(TypeOfCommand::GetInkey) => ProactiveCommandBody::GetInkey {
+            text: parse_text_string(required(&tlvs, TEXT_STRING_TAG)?),
+            duration: optional_duration(&tlvs)?,
+        },
+        Some(TypeOfCommand::GetInput) => {
+            let response_length = value_at_least(required(&tlvs, RESPONSE_LENGTH_TAG)?, 2)?;
+            ProactiveCommandBody::GetInput {
+                text: parse_text_string(required(&tlvs, TEXT_STRING_TAG)?),
+                minimum_response_length: response_length[0],
+                maximum_response_length: response_length[1],
+                default_text: find_comprehension_tlv(&tlvs, DEFAULT_TEXT_TAG)
+                    .map(parse_text_string),
+            }
+        }
+        Some(TypeOfCommand::SelectItem) => ProactiveCommandBody::SelectItem {
+            alpha_identifier,
+            items: parse_items(&tlvs),
+            default_item_identifier: find_comprehension_tlv(&tlvs, ITEM_IDENTIFIER_TAG)
+                .map(|tlv| value_at_least(tlv, 1).map(|v| v[0]))
+                .transpose()?,
+        },
+        Some(TypeOfCommand::SetUpMenu) => ProactiveCommandBody::SetUpMenu {
+            alpha_identifier,
+            items: parse_items(&tlvs),
+        },
+        Some(TypeOfCommand::SendShortMessage) => ProactiveCommandBody::SendShortMessage {
+            alpha_identifier,
+            address: optional_address(&tlvs)?,
+            tpdu: required(&tlvs, SMS_TPDU_TAG)?.get_value().to_vec(),
+        },
+        Some(TypeOfCommand::SetUpEventList) => ProactiveCommandBody::SetUpEventList {
+            events: required(&tlvs, EVENT_LIST_TAG)?.get_value().to_vec(),
+        },
+        Some(TypeOfCommand::ProvideLocalInformation) => {
+            ProactiveCommandBody::ProvideLocalInformation
+        }
+        Some(TypeOfCommand::TimerManagement) => ProactiveCommandBody::TimerManagement {
+            timer_identifier: value_at_least(required(&tlvs, TIMER_IDENTIFIER_TAG)?, 1)?[0],
+            timer_value: find_comprehension_tlv(&tlvs, TIMER_VALUE_TAG)
+                .map(|tlv| value_at_least(tlv, 3).map(|v| [v[0], v[1], v[2]]))
+                .transpose()?,
+        },
+        Some(TypeOfCommand::PollInterval) => ProactiveCommandBody::PollInterval {
+            duration: parse_duration(required(&tlvs, DURATION_TAG)?)?,
+        },
+        Some(TypeOfCommand::Refresh) => ProactiveCommandBody::Refresh {
+            file_list: optional_value(&tlvs, FILE_LIST_TAG),
+            aid: optional_value(&tlvs, AID_TAG),
+        },
+        Some(TypeOfCommand::OpenChannel) => {
+            let buffer_size = value_at_least(required(&tlvs, BUFFER_SIZE_TAG)?, 2)?;
+            let (local_address, data_destination_address) = other_addresses(&tlvs);
+            ProactiveCommandBody::OpenChannel {
+                alpha_identifier,
+                address: optional_address(&tlvs)?,
+                bearer_description: parse_bearer_description(required(
+                    &tlvs,
+                    BEARER_DESCRIPTION_TAG,
+                )?)?,
+                buffer_size: u16::from_be_bytes([buffer_size[0], buffer_size[1]]),
+                local_address,
+                transport_level: optional_value(&tlvs, TRANSPORT_LEVEL_TAG),
+                data_destination_address,
+            }
+        }
+        Some(TypeOfCommand::CloseChannel) => {
+            ProactiveCommandBody::CloseChannel { alpha_identifier }
+        }
+        Some(TypeOfCommand::ReceiveData) => ProactiveCommandBody::ReceiveData {
+            alpha_identifier,
+            channel_data_length: value_at_least(required(&tlvs, CHANNEL_DATA_LENGTH_TAG)?, 1)?[0],
+        },
+        Some(TypeOfCommand::SendData) => ProactiveCommandBody::SendData {
+            alpha_identifier,
+            channel_data: parse_channel_data(required(&tlvs, CHANNEL_DATA_TAG)?),
+        },
+        _ => ProactiveCommandBody::Other,
+    };
+
+    Ok(ProactiveCommand {
+        command_details,
+        device_identities,
+        body,
+        data_objects: tlvs,
+    })
+}
+
+/// Returns the command details data object of the proactive command that cannot be decoded, so that the
+/// terminal response to it can echo them; `None` when the command details themselves cannot be found.
+pub fn find_command_details(bytes: &[u8]) -> Option<ComprehensionTlv> {
+    let (template, _) = parse_ber_tlv(bytes).ok()?;
+    if template.get_tag() != PROACTIVE_COMMAND_TAG {
+        return None;
+    }
+    let tlvs = parse_comprehension_tlvs(template.get_value()).ok()?;
+    find_comprehension_tlv(&tlvs, COMMAND_DETAILS_TAG)
+        .filter(|tlv| tlv.get_value().len() >= 3)
+        .cloned()
+}
+
+impl CommandDetails {
+    pub fn get_number(&self) -> u8 {
+        self.number
+    }
+
+    /// Returns the type of the command; `None` for the types that are not known.
+    pub fn get_type_of_command(&self) -> Option<TypeOfCommand> {
+        TypeOfCommand::from_byte(self.type_of_command)
+    }
+
+    pub fn get_type_of_command_byte(&self) -> u8 {
+        self.type_of_command
+    }
+
+    pub fn get_qualifier(&self) -> u8 {
+        self.qualifier
+    }
+
+    pub fn to_bytes(&self) -> [u8; 3] {
+        [self.number, self.type_of_command, self.qualifier]
+    }
+}
+
+impl DeviceIdentities {
+    pub fn get_source(&self) -> u8 {
+        self.source
+    }
+
+    pub fn get_destination(&self) -> u8 {
+        self.destination
+    }
+}
+
+impl ProactiveCommand {
+    pub fn get_command_details(&self) -> &CommandDetails {
+        &self.command_details
+    }
+
+    pub fn get_device_identities(&self) -> &DeviceIdentities {
+        &self.device_identities
+    }
+
+    pub fn get_body(&self) -> &ProactiveCommandBody {
+        &self.body
+    }
+
+    /// Returns all the COMPREHENSION-TLV data objects of the command, including the command details.
+    pub fn get_data_objects(&self) -> &[ComprehensionTlv] {
+        &self.data_objects
+    }
+
+    /// Checks that the terminal understands every data object with the comprehension required flag; the
+    /// error should be answered by "command data not understood by terminal".
+    pub fn check_comprehension_required(&self) -> Result<(), ComprehensionTlvError> {
+        check_comprehension_required(&self.data_objects)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::comprehension_tlv::ComprehensionTlvError;
+    use crate::proactive_command::{
+        find_command_details, new_fetch_command, parse_proactive_command, ProactiveCommandBody,
+        ProactiveCommandError, TypeOfCommand, DEVICE_DISPLAY, DEVICE_TERMINAL, DEVICE_UICC,
+    };
+    use crate::simple_tlv::{BearerType, TimeUnit};
+
+    #[test]
+    fn should_decode_display_text() {
+        let bytes = [
+            0xd0, 0x13, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02, 0x8d, 0x06, 0x04,
+            0x48, 0x65, 0x6c, 0x6c, 0x6f, 0xab, 0x00,
+        ];
+        let command = parse_proactive_command(&bytes).unwrap();
+        let details = command.get_command_details();
+        assert_eq!(details.get_number(), 0x01);
+        assert_eq!(
+            details.get_type_of_command(),
+            Some(TypeOfCommand::DisplayText)
+        );
+        assert_eq!(details.get_qualifier(), 0x80);
+        assert_eq!(command.get_device_identities().get_source(), DEVICE_UICC);
+        assert_eq!(
+            command.get_device_identities().get_destination(),
+            DEVICE_DISPLAY
+        );
+        match command.get_body() {
+            ProactiveCommandBody::DisplayText {
+                text,
+                immediate_response,
+                duration,
+            } => {
+                assert_eq!(text.get_data_coding_scheme(), Some(0x04));
+                assert_eq!(text.get_text(), b"Hello");
+                assert!(immediate_response);
+                assert_eq!(*duration, None);
+            }
+            body => panic!("unexpected body: {:?}", body),
+        }
+    }
+
+    #[test]
+    fn should_decode_set_up_menu_and_poll_interval() {
+        let bytes = [
+            0xd0, 0x19, 0x81, 0x03, 0x01, 0x25, 0x00, 0x82, 0x02, 0x81, 0x82, 0x85, 0x04, 0x4d,
+            0x65, 0x6e, 0x75, 0x8f, 0x03, 0x01, 0x41, 0x42, 0x8f, 0x03, 0x02, 0x43, 0x44,
+        ];
+        let command = parse_proactive_command(&bytes).unwrap();
+        assert_eq!(
+            command.get_device_identities().get_destination(),
+            DEVICE_TERMINAL
+        );
+        match command.get_body() {
+            ProactiveCommandBody::SetUpMenu {
+                alpha_identifier,
+                items,
+            } => {
+                let alpha_identifier = alpha_identifier.as_ref().unwrap();
+                assert_eq!(alpha_identifier.get_text(), b"Menu");
+                assert_eq!(alpha_identifier.decode().unwrap(), "Menu");
+                assert_eq!(items.len(), 2);
+                assert_eq!(items[1].get_identifier(), 0x02);
+                assert_eq!(items[1].get_text(), b"CD");
+            }
+            body => panic!("unexpected body: {:?}", body),
+        }
+
+        let bytes = [
+            0xd0, 0x0d, 0x81, 0x03, 0x01, 0x03, 0x00, 0x82, 0x02, 0x81, 0x82, 0x84, 0x02, 0x01,
+            0x14,
+        ];
+        match parse_proactive_command(&bytes).unwrap().get_body() {
+            ProactiveCommandBody::PollInterval { duration } => {
+                assert_eq!(duration.get_unit(), TimeUnit::Seconds);
+                assert_eq!(duration.get_interval(), 20);
+            }
+            body => panic!("unexpected body: {:?}", body),
+        }
+    }
+
+    #[test]
+    fn should_decode_open_channel() {
+        let bytes = [
+            0xd0, 0x1c, 0x81, 0x03, 0x01, 0x40, 0x01, 0x82, 0x02, 0x81, 0x82, 0x35, 0x01, 0x03,
+            0x39, 0x02, 0x05, 0x78, 0x3c, 0x03, 0x01, 0x1f, 0x90, 0x3e, 0x05, 0x21, 0x7f, 0x00,
+            0x00, 0x01,
+        ];
+        match parse_proactive_command(&bytes).unwrap().get_body() {
+            ProactiveCommandBody::OpenChannel {
+                bearer_description,
+                buffer_size,
+                local_address,
+                transport_level,
+                data_destination_address,
+                ..
+            } => {
+                assert_eq!(
+                    bearer_description.get_bearer_type(),
+                    Some(BearerType::Default)
+                );
+                assert!(bearer_description.get_parameters().is_empty());
+                assert_eq!(*buffer_size, 1400);
+                assert_eq!(*local_address, None);
+                assert_eq!(transport_level.as_deref(), Some(&[0x01, 0x1f, 0x90][..]));
+                assert_eq!(
+                    data_destination_address.as_deref(),
+                    Some(&[0x21, 0x7f, 0x00, 0x00, 0x01][..])
+                );
+            }
+            body => panic!("unexpected body: {:?}", body),
+        }
+
+        // the local address comes before the transport level and the data destination address after it
+        let bytes = [
+            0xd0, 0x23, 0x81, 0x03, 0x01, 0x40, 0x01, 0x82, 0x02, 0x81, 0x82, 0x35, 0x01, 0x03,
+            0x39, 0x02, 0x05, 0x78, 0x3e, 0x05, 0x21, 0x0a, 0x00, 0x00, 0x02, 0x3c, 0x03, 0x01,
+            0x1f, 0x90, 0x3e, 0x05, 0x21, 0x7f, 0x00, 0x00, 0x01,
+        ];
+        match parse_proactive_command(&bytes).unwrap().get_body() {
+            ProactiveCommandBody::OpenChannel {
+                local_address,
+                data_destination_address,
+                ..
+            } => {
+                assert_eq!(
+                    local_address.as_deref(),
+                    Some(&[0x21, 0x0a, 0x00, 0x00, 0x02][..])
+                );
+                assert_eq!(
+                    data_destination_address.as_deref(),
+                    Some(&[0x21, 0x7f, 0x00, 0x00, 0x01][..])
+                );
+            }
+            body => panic!("unexpected body: {:?}", body),
+        }
+    }
+
+    #[test]
+    fn should_fail_decode_without_mandatory_data_object() {
+        let bytes = [
+            0xd0, 0x09, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02,
+        ];
+        assert_eq!(
+            parse_proactive_command(&bytes).unwrap_err(),
+            ProactiveCommandError::MissingDataObject(0x0d)
+        );
+        assert_eq!(
+            parse_proactive_command(&[0x62, 0x00]).unwrap_err(),
+            ProactiveCommandError::UnexpectedTemplateTag(0x62)
+        );
+    }
+
+    #[test]
+    fn should_find_command_details_of_undecodable_command() {
+        let bytes = [
+            0xd0, 0x09, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02,
+        ];
+        assert_eq!(
+            find_command_details(&bytes).unwrap().get_value(),
+            &[0x01, 0x21, 0x80]
+        );
+        assert!(find_command_details(&[0xd0, 0x03, 0x81, 0x01, 0x01]).is_none());
+        assert!(find_command_details(&[0x62, 0x00]).is_none());
+    }
+
+    #[test]
+    fn should_report_unknown_comprehension_required_data_object() {
+        let bytes = [
+            0xd0, 0x10, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02, 0x8d, 0x02, 0x04,
+            0x41, 0xfa, 0x01, 0x00,
+        ];
+        let command = parse_proactive_command(&bytes).unwrap();
+        assert_eq!(
+            command.check_comprehension_required(),
+            Err(ComprehensionTlvError::UnknownComprehensionRequiredTag(0x7a))
+        );
+    }
+
+    #[test]
+    fn should_construct_fetch_command() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            new_fetch_command(0x1a)
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x80, 0x12, 0x00, 0x00, 0x1a])
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::{new_basic_class, ClassTypeForStandardLogicalChannels};
+use crate::command_apdu::CommandAPDU;
+use crate::proactive_command::{
+    find_command_details, new_fetch_command, parse_proactive_command, ProactiveCommand,
+    ProactiveCommandBody, ProactiveCommandError, TypeOfCommand,
+};
+use crate::response_apdu::ResponseAPDU;
+use crate::session::{new_session, Session, SessionConfig, SessionError};
+use crate::status::{new_status_command, StatusIndication, StatusResponse};
+use crate::terminal_response::{
+    new_command_result, new_terminal_response_builder, new_undecoded_command_terminal_response,
+    GeneralResult, TerminalResponse, TerminalResponseBuilder, TerminalResponseError,
+};
+use crate::transport::Transport;
+
+/// ProactiveCommandHandler performs the proactive commands on behalf of the terminal, e.g. the display and
+/// the keypad of a handset emulator.
+pub trait ProactiveCommandHandler {
+    /// Performs the command and returns the terminal response to it. The command details and the device
+    /// identities are added by the builder.
+    fn handle<'c>(&mut self, command: &'c ProactiveCommand) -> TerminalResponseBuilder<'c>;
+}
+
+/// ProactiveSessionConfig: the limits of the proactive UICC session.
+pub struct ProactiveSessionConfig {
+    pub session: SessionConfig,
+    /// The maximum number of the proactive commands that are fetched in a run
+    pub max_proactive_commands: usize,
+    /// The time limit of a run; `None` means no limit
+    pub timeout: Option<Duration>,
+    /// The interval of STATUS until POLL INTERVAL changes it, and after POLLING OFF
+    pub default_poll_interval: Duration,
+}
+
+impl Default for ProactiveSessionConfig {
+    fn default() -> Self {
+        ProactiveSessionConfig {
+            session: SessionConfig::default(),
+            max_proactive_commands: 32,
+            timeout: None,
+            default_poll_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// ProactiveSession drives the proactive UICC session; it fetches the pending proactive commands, passes
+/// them to the handler and sends the terminal responses until the UICC answers '9000'; ref 14.2 / ETSI
+/// TS 102 221 V15.0.0 and 6.3 / ETSI TS 102 223 V15.0.0
+pub struct ProactiveSession<'a> {
+    session: Session<'a>,
+    max_proactive_commands: usize,
+    timeout: Option<Duration>,
+    default_poll_interval: Duration,
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ProactiveSessionError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("failed to decode the proactive command: {0}")]
+    ProactiveCommand(#[from] ProactiveCommandError),
+    #[error("failed to build the terminal response: {0}")]
+    TerminalResponse(#[from] TerminalResponseError),
+    #[error("unexpected status word in the proactive session: '{0:04X}'")]
+    UnexpectedStatusWord(u16),
+    #[error("too many proactive commands; the limit is {0}")]
+    TooManyProactiveCommands(usize),
+    #[error("proactive session timed out")]
+    Timeout,
+}
+
+pub fn new_proactive_session(
+    transport: &mut dyn Transport,
+    config: ProactiveSessionConfig,
+) -> ProactiveSession<'_> {
+    ProactiveSession {
+        session: new_session(transport, config.session),
+        max_proactive_commands: config.max_proactive_commands,
+        timeout: config.timeout,
+        default_poll_interval: config.default_poll_interval,
+        poll_interval: config.default_poll_interval,
+        last_poll: None,
+    }
+}
+
+impl<'a> ProactiveSession<'a> {
+    pub fn get_poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Transmits the command and runs the proactive session when the UICC answers '91XX'. The response to
+    /// the command is returned as it is.
+    pub fn transmit(
+        &mut self,
+        command: &CommandAPDU,
+        handler: &mut dyn ProactiveCommandHandler,
+    ) -> Result<ResponseAPDU, ProactiveSessionError> {
+        let response = self.session.transmit(command)?;
+        if let Some(length) = response.get_pending_proactive_command_length() {
+            self.run(length, handler)?;
+        }
+        Ok(response)
+    }
+
+    /// Sends STATUS when the poll interval has passed since the last one or none has been sent yet, and
+    /// runs the proactive session when a proactive command is pending. Returns whether STATUS has been sent.
+    pub fn poll(
+        &mut self,
+        handler: &mut dyn ProactiveCommandHandler,
+    ) -> Result<bool, ProactiveSessionError> {
+        if self
+            .last_poll
+            .is_some_and(|last| last.elapsed() < self.poll_interval)
+        {
+            return Ok(false);
+        }
+        let class = new_basic_class(ClassTypeForStandardLogicalChannels::TS102_221);
+        let status = new_status_command(StatusIndication::NoIndication, StatusResponse::NoData);
+        self.last_poll = Some(Instant::now());
+        let response = self.session.transmit(&status.to_command_apdu(&class))?;
+        if let Some(length) = response.get_pending_proactive_command_length() {
+            self.run(length, handler)?;
+        } else if !response.is_normal_ending() {
+            return Err(ProactiveSessionError::UnexpectedStatusWord(
+                response.get_status_word(),
+            ));
+        }
+        Ok(true)
+    }
+
+    /// Fetches and performs the proactive commands, starting from the one of the given length, until the
+    /// UICC answers '9000' to the terminal response. Returns the number of the fetched commands.
+    ///
+    /// The command that cannot be decoded is answered by "error, required values are missing" when a
+    /// mandatory data object is missing, and by "command data not understood by terminal" otherwise; the
+    /// session fails only when the command details cannot be found; ref 6.8 / ETSI TS 102 223 V15.0.0
+    pub fn run(
+        &mut self,
+        length: u8,
+        handler: &mut dyn ProactiveCommandHandler,
+    ) -> Result<usize, ProactiveSessionError> {
+        let class = new_basic_class(ClassTypeForStandardLogicalChannels::TS102_221);
+        let started = Instant::now();
+        let mut pending = Some(length);
+        let mut count = 0;
+
+        while let Some(length) = pending {
+            if count == self.max_proactive_commands {
+                return Err(ProactiveSessionError::TooManyProactiveCommands(
+                    self.max_proactive_commands,
+                ));
+            }
+            if self.timeout.is_some_and(|t| started.elapsed() > t) {
+                return Err(ProactiveSessionError::Timeout);
+            }
+            count += 1;
+
+            let fetched = self
+                .session
+                .transmit(&new_fetch_command(length).to_command_apdu(&class))?;
+            if !fetched.is_normal_ending() {
+                return Err(ProactiveSessionError::UnexpectedStatusWord(
+                    fetched.get_status_word(),
+                ));
+            }
+            let response = match parse_proactive_command(fetched.get_data()) {
+                Ok(command) => self.perform(&command, handler)?,
+                Err(err) => {
+                    let general_result = match err {
+                        ProactiveCommandError::MissingDataObject(_) => {
+                            GeneralResult::RequiredValuesMissing
+                        }
+                        _ => GeneralResult::CommandDataNotUnderstood,
+                    };
+                    let command_details = find_command_details(fetched.get_data()).ok_or(err)?;
+                    new_undecoded_command_terminal_response(
+                        &command_details,
+                        new_command_result(general_result, Vec::new()),
+                    )?
+                }
+            };
+            let answered = self.session.transmit(&response.to_command_apdu(&class))?;
+            pending = answered.get_pending_proactive_command_length();
+            if pending.is_none() && !answered.is_normal_ending() {
+                return Err(ProactiveSessionError::UnexpectedStatusWord(
+                    answered.get_status_word(),
+                ));
+            }
+        }
+        Ok(count)
+    }
+
+    fn perform(
+        &mut self,
+        command: &ProactiveCommand,
+        handler: &mut dyn ProactiveCommandHandler,
+    ) -> Result<TerminalResponse, TerminalResponseError> {
+        match command.check_comprehension_required() {
+            Ok(()) => {
+                self.update_poll_interval(command);
+                handler.handle(command).build()
+            }
+            Err(_) => new_terminal_response_builder(
+                command,
+                new_command_result(GeneralResult::CommandDataNotUnderstood, Vec::new()),
+            )
+            .build(),
+        }
+    }
+
+    fn update_poll_interval(&mut self, command: &ProactiveCommand) {
+        match command.get_body() {
+            ProactiveCommandBody::PollInterval { duration } => {
+                self.poll_interval = duration.to_std_duration();
+            }
+            _ if command.get_command_details().get_type_of_command()
+                == Some(TypeOfCommand::PollingOff) =>
+            {
+                self.poll_interval = self.default_poll_interval;
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::proactive_command::{ProactiveCommand, ProactiveCommandBody, ProactiveCommandError};
+    use crate::proactive_session::{
+        new_proactive_session, ProactiveCommandHandler, ProactiveSessionConfig,
+        ProactiveSessionError,
+    };
+    use crate::terminal_response::{
+        new_command_result, new_terminal_response_builder, GeneralResult, TerminalResponseBuilder,
+    };
+    use crate::testing::new_scripted_card;
+
+    /// Handset performs every command successfully and echoes the poll interval.
+    struct Handset {
+        handled: usize,
+    }
+
+    impl ProactiveCommandHandler for Handset {
+        fn handle<'c>(&mut self, command: &'c ProactiveCommand) -> TerminalResponseBuilder<'c> {
+            self.handled += 1;
+            let builder = new_terminal_response_builder(
+                command,
+                new_command_result(GeneralResult::PerformedSuccessfully, Vec::new()),
+            );
+            match command.get_body() {
+                ProactiveCommandBody::PollInterval { duration } => builder.duration(duration),
+                _ => builder,
+            }
+        }
+    }
+
+    const DISPLAY_TEXT: [u8; 18] = [
+        0xd0, 0x10, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02, 0x8d, 0x05, 0x04, 0x48,
+        0x65, 0x6c, 0x6f,
+    ];
+    const POLL_INTERVAL: [u8; 15] = [
+        0xd0, 0x0d, 0x81, 0x03, 0x02, 0x03, 0x00, 0x82, 0x02, 0x81, 0x82, 0x84, 0x02, 0x01, 0x14,
+    ];
+
+    fn with_status_word(data: &[u8], sw1: u8, sw2: u8) -> Vec<u8> {
+        let mut bytes = data.to_vec();
+        bytes.extend([sw1, sw2]);
+        bytes
+    }
+
+    #[test]
+    fn should_run_proactive_session() {
+        let display_text = with_status_word(&DISPLAY_TEXT, 0x90, 0x00);
+        let poll_interval = with_status_word(&POLL_INTERVAL, 0x90, 0x00);
+        let mut card = new_scripted_card(&[
+            &[0x91, 0x12],
+            &display_text,
+            &[0x91, 0x0f],
+            &poll_interval,
+            &[0x90, 0x00],
+        ]);
+        let mut handset = Handset { handled: 0 };
+        let mut session = new_proactive_session(&mut card, ProactiveSessionConfig::default());
+        assert!(session.poll(&mut handset).unwrap());
+        assert_eq!(session.get_poll_interval(), Duration::from_secs(20));
+        assert!(!session.poll(&mut handset).unwrap());
+        assert_eq!(handset.handled, 2);
+
+        assert_eq!(card.commands[0], Vec::from([0x80, 0xf2, 0x00, 0x0c]));
+        assert_eq!(card.commands[1], Vec::from([0x80, 0x12, 0x00, 0x00, 0x12]));
+        assert_eq!(card.commands[2][..5], [0x80, 0x14, 0x00, 0x00, 0x0c]);
+        assert_eq!(
+            card.commands[4][card.commands[4].len() - 4..],
+            [0x84, 0x02, 0x01, 0x14]
+        );
+    }
+
+    #[test]
+    fn should_answer_command_data_not_understood() {
+        let unknown = with_status_word(
+            &[
+                0xd0, 0x10, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02, 0x8d, 0x02, 0x04,
+                0x41, 0xfa, 0x01, 0x00,
+            ],
+            0x90,
+            0x00,
+        );
+        let mut card = new_scripted_card(&[&unknown, &[0x90, 0x00]]);
+        let mut handset = Handset { handled: 0 };
+        let count = new_proactive_session(&mut card, ProactiveSessionConfig::default())
+            .run(0x12, &mut handset)
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(handset.handled, 0);
+        assert_eq!(card.commands[1][14..], [0x83, 0x01, 0x32]);
+    }
+
+    #[test]
+    fn should_answer_undecodable_commands() {
+        // DISPLAY TEXT without the text string
+        let missing_text = with_status_word(
+            &[
+                0xd0, 0x09, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02,
+            ],
+            0x90,
+            0x00,
+        );
+        // POLL INTERVAL with the reserved time unit
+        let invalid_duration = with_status_word(
+            &[
+                0xd0, 0x0d, 0x81, 0x03, 0x02, 0x03, 0x00, 0x82, 0x02, 0x81, 0x82, 0x84, 0x02, 0x05,
+                0x14,
+            ],
+            0x90,
+            0x00,
+        );
+        let mut card = new_scripted_card(&[
+            &missing_text,
+            &[0x91, 0x0f],
+            &invalid_duration,
+            &[0x90, 0x00],
+        ]);
+        let mut handset = Handset { handled: 0 };
+        let count = new_proactive_session(&mut card, ProactiveSessionConfig::default())
+            .run(0x0b, &mut handset)
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(handset.handled, 0);
+        assert_eq!(
+            card.commands[1][5..],
+            [0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x82, 0x81, 0x83, 0x01, 0x36]
+        );
+        assert_eq!(card.commands[3][5..10], [0x81, 0x03, 0x02, 0x03, 0x00]);
+        assert_eq!(card.commands[3][14..], [0x83, 0x01, 0x32]);
+
+        // the command details cannot be echoed
+        let missing_details = with_status_word(&[0xd0, 0x04, 0x82, 0x02, 0x81, 0x02], 0x90, 0x00);
+        let mut card = new_scripted_card(&[&missing_details]);
+        assert_eq!(
+            new_proactive_session(&mut card, ProactiveSessionConfig::default())
+                .run(0x06, &mut handset)
+                .unwrap_err(),
+            ProactiveSessionError::ProactiveCommand(ProactiveCommandError::MissingDataObject(0x01))
+        );
+    }
+
+    #[test]
+    fn should_limit_proactive_commands() {
+        let display_text = with_status_word(&DISPLAY_TEXT, 0x90, 0x00);
+        let mut card = new_scripted_card(&[&display_text, &[0x91, 0x12], &display_text]);
+        let result = new_proactive_session(
+            &mut card,
+            ProactiveSessionConfig {
+                max_proactive_commands: 1,
+                ..Default::default()
+            },
+        )
+        .run(0x12, &mut Handset { handled: 0 });
+        assert_eq!(
+            result.unwrap_err(),
+            ProactiveSessionError::TooManyProactiveCommands(1)
+        );
+    }
+}
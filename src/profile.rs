@@ -0,0 +1,378 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::hex::{hex_array, hex_bytes, optional_hex_bytes};
+use crate::walker::{FileNode, FileSystemTree};
+
+/// Version of the card profile format. This is incremented when the format changes incompatibly.
+pub const CARD_PROFILE_VERSION: u32 = 1;
+
+const PIN_MAX_ATTEMPTS: u8 = 3;
+const UNBLOCK_PIN_MAX_ATTEMPTS: u8 = 10;
+
+/// PinProfile: a PIN and its unblock PIN, identified by the key reference; ref 9.5.1 / ETSI TS 102 221 V15.0.0
+///
+/// The values are held as they are sent in VERIFY PIN and UNBLOCK PIN, i.e. padded with 'FF' to 8 bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinProfile {
+    key_reference: u8,
+    #[serde(with = "hex_bytes")]
+    value: Vec<u8>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_hex_bytes"
+    )]
+    unblock_value: Option<Vec<u8>>,
+    remaining_attempts: u8,
+    remaining_unblock_attempts: u8,
+    enabled: bool,
+}
+
+/// AuthenticationProfile: the subscriber key K, OPc and the highest sequence number accepted so far, to
+/// answer AUTHENTICATE by Milenage; ref 3GPP TS 35.206
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthenticationProfile {
+    #[serde(with = "hex_array")]
+    k: [u8; 16],
+    #[serde(with = "hex_array")]
+    opc: [u8; 16],
+    #[serde(with = "hex_array")]
+    sqn: [u8; 6],
+}
+
+/// CardProfile: a complete UICC that is dumped from a real card or written by hand, to be loaded into `VirtualUICC`.
+///
+/// The access rules are held in the security attributes of the FCP and in the records of EF.ARR.
+/// The FCP that is dumped from a real card also holds the template as it is read in "template", so that the
+/// virtual UICC answers the same bytes; the data objects must match the template.
+/// The JSON representation looks like the following; the bytes are written in upper-case hexadecimal strings.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "file_system": {
+///     "master_file": {
+///       "path": "3F00",
+///       "fcp": { "file_descriptor": { "shareable": true, "file_type": "DFOrADF", "structure": "NoInformation" } },
+///       "children": [
+///         {
+///           "path": "3F00/2FE2",
+///           "fcp": {
+///             "file_descriptor": { "shareable": true, "file_type": "WorkingEF", "structure": "Transparent" },
+///             "file_id": "2FE2",
+///             "file_size": 10
+///           },
+///           "content": { "kind": "Transparent", "data": "98940000000000000001" }
+///         }
+///       ]
+///     },
+///     "applications": [
+///       {
+///         "template": { "aid": "A0000000871002", "label": "USIM" },
+///         "root": {
+///           "path": "3F00/7FFF",
+///           "fcp": {
+///             "file_descriptor": { "shareable": true, "file_type": "DFOrADF", "structure": "NoInformation" },
+///             "df_name": "A0000000871002"
+///           }
+///         }
+///       }
+///     ]
+///   },
+///   "pins": [
+///     { "key_reference": 1, "value": "31323334FFFFFFFF", "remaining_attempts": 3,
+///       "remaining_unblock_attempts": 10, "enabled": true }
+///   ],
+///   "authentication": { "k": "465B5CE8B199B49FAA5F0A2EE238A6BC", "opc": "CD63CB71954A9F4E48A5994E37A02BAF",
+///     "sqn": "000000000000" }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardProfile {
+    version: u32,
+    file_system: FileSystemTree,
+    #[serde(default)]
+    pins: Vec<PinProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    authentication: Option<AuthenticationProfile>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CardProfileError {
+    #[error("invalid card profile: {0}")]
+    InvalidProfile(String),
+    #[error("unsupported version of the card profile; this must be {0} but {1}")]
+    UnsupportedVersion(u32, u64),
+    #[error("invalid path of the file in the card profile: {0}")]
+    InvalidPath(String),
+}
+
+/// Creates the PIN that is enabled and has the initial number of attempts; 3 for the PIN and 10 for the unblock PIN.
+pub fn new_pin_profile(
+    key_reference: u8,
+    value: Vec<u8>,
+    unblock_value: Option<Vec<u8>>,
+) -> PinProfile {
+    PinProfile {
+        key_reference,
+        value,
+        unblock_value,
+        remaining_attempts: PIN_MAX_ATTEMPTS,
+        remaining_unblock_attempts: UNBLOCK_PIN_MAX_ATTEMPTS,
+        enabled: true,
+    }
+}
+
+pub fn new_authentication_profile(
+    k: &[u8; 16],
+    opc: &[u8; 16],
+    sqn: &[u8; 6],
+) -> AuthenticationProfile {
+    AuthenticationProfile {
+        k: *k,
+        opc: *opc,
+        sqn: *sqn,
+    }
+}
+
+pub fn new_card_profile(file_system: FileSystemTree, pins: Vec<PinProfile>) -> CardProfile {
+    CardProfile {
+        version: CARD_PROFILE_VERSION,
+        file_system,
+        pins,
+        authentication: None,
+    }
+}
+
+pub fn parse_card_profile_json(json: &str) -> Result<CardProfile, CardProfileError> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| CardProfileError::InvalidProfile(e.to_string()))?;
+    let version = value
+        .get("version")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| CardProfileError::InvalidProfile("missing version".to_string()))?;
+    if version != CARD_PROFILE_VERSION as u64 {
+        return Err(CardProfileError::UnsupportedVersion(
+            CARD_PROFILE_VERSION,
+            version,
+        ));
+    }
+
+    let profile: CardProfile = serde_json::from_value(value)
+        .map_err(|e| CardProfileError::InvalidProfile(e.to_string()))?;
+    let file_system = profile.get_file_system();
+    let master_file = file_system.get_master_file();
+    if !master_file.get_path().is_master_file() {
+        return Err(CardProfileError::InvalidPath(
+            master_file.get_path().to_string(),
+        ));
+    }
+    check_child_paths(master_file)?;
+    for application in file_system.get_applications() {
+        let root = application.get_root();
+        let ids = root.get_path().get_file_ids();
+        if !(ids.len() == 2 && ids[0].is_master_file() && ids[1].is_current_adf()) {
+            return Err(CardProfileError::InvalidPath(root.get_path().to_string()));
+        }
+        check_child_paths(root)?;
+    }
+    Ok(profile)
+}
+
+/// Checks that the path of every descendant is the path of its parent followed by its file identifier.
+fn check_child_paths(node: &FileNode) -> Result<(), CardProfileError> {
+    for child in node.get_children() {
+        let ids = child.get_path().get_file_ids();
+        if ids.len() < 2 || ids[..ids.len() - 1] != *node.get_path().get_file_ids() {
+            return Err(CardProfileError::InvalidPath(child.get_path().to_string()));
+        }
+        check_child_paths(child)?;
+    }
+    Ok(())
+}
+
+impl PinProfile {
+    pub fn get_key_reference(&self) -> u8 {
+        self.key_reference
+    }
+
+    pub fn get_value(&self) -> &[u8] {
+        &self.value
+    }
+
+    pub fn get_unblock_value(&self) -> Option<&[u8]> {
+        self.unblock_value.as_deref()
+    }
+
+    pub fn get_remaining_attempts(&self) -> u8 {
+        self.remaining_attempts
+    }
+
+    pub fn get_remaining_unblock_attempts(&self) -> u8 {
+        self.remaining_unblock_attempts
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn reset_attempts(&mut self) {
+        self.remaining_attempts = PIN_MAX_ATTEMPTS;
+    }
+
+    pub(crate) fn consume_attempt(&mut self) {
+        self.remaining_attempts = self.remaining_attempts.saturating_sub(1);
+    }
+}
+
+impl AuthenticationProfile {
+    pub fn get_k(&self) -> &[u8; 16] {
+        &self.k
+    }
+
+    pub fn get_opc(&self) -> &[u8; 16] {
+        &self.opc
+    }
+
+    pub fn get_sqn(&self) -> &[u8; 6] {
+        &self.sqn
+    }
+}
+
+impl CardProfile {
+    pub fn get_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn get_file_system(&self) -> &FileSystemTree {
+        &self.file_system
+    }
+
+    pub fn get_pins(&self) -> &[PinProfile] {
+        &self.pins
+    }
+
+    pub fn get_authentication(&self) -> Option<&AuthenticationProfile> {
+        self.authentication.as_ref()
+    }
+
+    pub fn set_authentication(&mut self, authentication: Option<AuthenticationProfile>) {
+        self.authentication = authentication;
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::profile::{parse_card_profile_json, CardProfile, CardProfileError};
+
+    const PROFILE_JSON: &str = r#"{
+  "version": 1,
+  "file_system": {
+    "master_file": {
+      "path": "3F00",
+      "fcp": {
+        "file_descriptor": { "shareable": true, "file_type": "DFOrADF", "structure": "NoInformation" },
+        "file_id": "3F00",
+        "life_cycle_status_integer": 5
+      },
+      "children": [
+        {
+          "path": "3F00/2FE2",
+          "fcp": {
+            "file_descriptor": { "shareable": true, "file_type": "WorkingEF", "structure": "Transparent" },
+            "file_id": "2FE2",
+            "security_attributes": { "format": "Referenced", "value": "2F0601" },
+            "file_size": 10
+          },
+          "content": { "kind": "Transparent", "data": "98940000000000000001" }
+        }
+      ]
+    }
+  },
+  "pins": [
+    { "key_reference": 1, "value": "31323334FFFFFFFF", "unblock_value": "3132333435363738",
+      "remaining_attempts": 3, "remaining_unblock_attempts": 10, "enabled": true }
+  ],
+  "authentication": { "k": "465B5CE8B199B49FAA5F0A2EE238A6BC", "opc": "CD63CB71954A9F4E48A5994E37A02BAF",
+    "sqn": "000000000000" }
+}"#;
+
+    #[test]
+    fn should_round_trip_card_profile_json() {
+        let profile = parse_card_profile_json(PROFILE_JSON).unwrap();
+        assert_eq!(profile.get_version(), 1);
+        assert_eq!(profile.get_pins()[0].get_key_reference(), 0x01);
+        assert_eq!(
+            profile.get_file_system().get_master_file().get_children()[0]
+                .get_fcp()
+                .get_file_size(),
+            Some(10)
+        );
+
+        let json = profile.to_json().unwrap();
+        assert_eq!(parse_card_profile_json(&json).unwrap(), profile);
+    }
+
+    #[test]
+    fn should_fail_parse_card_profile_with_unsupported_version() {
+        assert_eq!(
+            parse_card_profile_json(&PROFILE_JSON.replace("\"version\": 1", "\"version\": 2"))
+                .unwrap_err(),
+            CardProfileError::UnsupportedVersion(1, 2)
+        );
+        assert!(matches!(
+            parse_card_profile_json("{\"version\": 1}").unwrap_err(),
+            CardProfileError::InvalidProfile(_)
+        ));
+        assert!(matches!(
+            parse_card_profile_json(
+                &PROFILE_JSON.replace("\"sqn\": \"000000000000\"", "\"sqn\": \"00\"")
+            )
+            .unwrap_err(),
+            CardProfileError::InvalidProfile(_)
+        ));
+    }
+
+    #[test]
+    fn should_fail_parse_card_profile_with_invalid_path() {
+        assert_eq!(
+            parse_card_profile_json(&PROFILE_JSON.replace("\"3F00/2FE2\"", "\"2FE2\""))
+                .unwrap_err(),
+            CardProfileError::InvalidPath("2FE2".to_string())
+        );
+        assert_eq!(
+            parse_card_profile_json(&PROFILE_JSON.replace("\"3F00/2FE2\"", "\"7F10/2FE2\""))
+                .unwrap_err(),
+            CardProfileError::InvalidPath("7F10/2FE2".to_string())
+        );
+        assert_eq!(
+            parse_card_profile_json(
+                &PROFILE_JSON.replace("\"path\": \"3F00\"", "\"path\": \"2F00\"")
+            )
+            .unwrap_err(),
+            CardProfileError::InvalidPath("2F00".to_string())
+        );
+    }
+
+    #[test]
+    fn should_fail_deserialize_authentication_with_invalid_length() {
+        let json = PROFILE_JSON.replace(
+            "465B5CE8B199B49FAA5F0A2EE238A6BC",
+            "465B5CE8B199B49FAA5F0A2EE238A6",
+        );
+        let error = serde_json::from_str::<CardProfile>(&json).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("this must be 16 bytes but 15 bytes"));
+        assert!(matches!(
+            parse_card_profile_json(&json).unwrap_err(),
+            CardProfileError::InvalidProfile(_)
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::ReadBinary;
+
+/// ReadBinaryCommand: ref 11.1.3 / ETSI TS 102 221 V15.0.0
+pub struct ReadBinaryCommand {
+    p1: u8,
+    p2: u8,
+    le: u8,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ReadBinaryError {
+    #[error("offset is out of range; this must be within [0, {0}] but the given value is {1}")]
+    OffsetOutOfRange(u16, u16),
+    #[error(
+        "invalid short file identifier; this must be within [1, 30] but the given value is {0}"
+    )]
+    InvalidShortFileId(u8),
+}
+
+/// Reads the current EF from the offset (P1 b8 = 0).
+pub fn new_read_binary_command(offset: u16, le: u8) -> Result<ReadBinaryCommand, ReadBinaryError> {
+    if offset > 0x7fff {
+        return Err(ReadBinaryError::OffsetOutOfRange(0x7fff, offset));
+    }
+
+    Ok(ReadBinaryCommand {
+        p1: (offset >> 8) as u8,
+        p2: offset as u8,
+        le,
+    })
+}
+
+/// Reads the EF that is referenced by the short file identifier from the offset (P1 b8 = 1).
+pub fn new_read_binary_command_with_short_file_id(
+    short_file_id: u8,
+    offset: u8,
+    le: u8,
+) -> Result<ReadBinaryCommand, ReadBinaryError> {
+    if short_file_id == 0 || short_file_id > 30 {
+        return Err(ReadBinaryError::InvalidShortFileId(short_file_id));
+    }
+
+    Ok(ReadBinaryCommand {
+        p1: 0b10000000 | short_file_id,
+        p2: offset,
+        le,
+    })
+}
+
+impl ReadBinaryCommand {
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(class, &ReadBinary {}, self.p1, self.p2, Some(self.le), None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::read_binary::{
+        new_read_binary_command, new_read_binary_command_with_short_file_id, ReadBinaryError,
+    };
+
+    #[test]
+    fn should_construct_read_binary_command() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let command = new_read_binary_command(0x0123, 0x10).unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0xb0, 0x01, 0x23, 0x10])
+        );
+
+        let command = new_read_binary_command_with_short_file_id(0x07, 0x02, 0x00).unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0xb0, 0x87, 0x02, 0x00])
+        );
+    }
+
+    #[test]
+    fn should_fail_construct_read_binary_command_with_invalid_parameters() {
+        assert_eq!(
+            new_read_binary_command(0x8000, 0x00).err().unwrap(),
+            ReadBinaryError::OffsetOutOfRange(0x7fff, 0x8000)
+        );
+        assert_eq!(
+            new_read_binary_command_with_short_file_id(31, 0x00, 0x00)
+                .err()
+                .unwrap(),
+            ReadBinaryError::InvalidShortFileId(31)
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::ReadRecord;
+
+/// RecordMode: ref 11.1.5.2 / ETSI TS 102 221 V15.0.0
+#[repr(u8)]
+pub enum RecordMode {
+    Next = 0b010,
+    Previous = 0b011,
+    AbsoluteOrCurrent = 0b100,
+}
+
+/// ReadRecordCommand: ref 11.1.5 / ETSI TS 102 221 V15.0.0
+pub struct ReadRecordCommand {
+    p1: u8,
+    p2: u8,
+    le: u8,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ReadRecordError {
+    #[error(
+        "invalid short file identifier; this must be within [1, 30] but the given value is {0}"
+    )]
+    InvalidShortFileId(u8),
+}
+
+/// Reads the record of the current EF, or of the EF that is referenced by the short file identifier if that is given.
+/// The record number '00' denotes the current record.
+pub fn new_read_record_command(
+    record_number: u8,
+    mode: RecordMode,
+    short_file_id: Option<u8>,
+    le: u8,
+) -> Result<ReadRecordCommand, ReadRecordError> {
+    let sfi_bits = match short_file_id {
+        Some(sfi) if sfi == 0 || sfi > 30 => return Err(ReadRecordError::InvalidShortFileId(sfi)),
+        Some(sfi) => sfi << 3,
+        None => 0,
+    };
+
+    Ok(ReadRecordCommand {
+        p1: record_number,
+        p2: sfi_bits | mode as u8,
+        le,
+    })
+}
+
+impl ReadRecordCommand {
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(class, &ReadRecord {}, self.p1, self.p2, Some(self.le), None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::read_record::{new_read_record_command, ReadRecordError, RecordMode};
+
+    #[test]
+    fn should_construct_read_record_command() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let command =
+            new_read_record_command(2, RecordMode::AbsoluteOrCurrent, None, 0x26).unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0xb2, 0x02, 0x04, 0x26])
+        );
+
+        let command = new_read_record_command(0, RecordMode::Next, Some(0x1e), 0x26).unwrap();
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0xb2, 0x00, 0xf2, 0x26])
+        );
+
+        assert_eq!(
+            new_read_record_command(1, RecordMode::Previous, Some(0), 0x00)
+                .err()
+                .unwrap(),
+            ReadRecordError::InvalidShortFileId(0)
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::Result;
+use thiserror::Error;
+
+/// ResponseAPDU: ref 10.2 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseAPDU {
+    data: Vec<u8>,
+    sw1: u8,
+    sw2: u8,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ResponseAPDUError {
+    #[error("illegal length of the response APDU; this must contain at least SW1 and SW2, but {0} bytes")]
+    IllegalResponseLength(usize),
+}
+
+pub fn new_response_apdu(data: Vec<u8>, sw1: u8, sw2: u8) -> ResponseAPDU {
+    ResponseAPDU { data, sw1, sw2 }
+}
+
+pub fn parse_response_apdu(bytes: &[u8]) -> Result<ResponseAPDU, ResponseAPDUError> {
+    let len = bytes.len();
+    if len < 2 {
+        return Err(ResponseAPDUError::IllegalResponseLength(len));
+    }
+
+    Ok(ResponseAPDU {
+        data: bytes[..len - 2].to_vec(),
+        sw1: bytes[len - 2],
+        sw2: bytes[len - 1],
+    })
+}
+
+/// Describes the status word; ref 10.2.1 / ETSI TS 102 221 V15.0.0 and 5.6 / ISO/IEC 7816-4
+pub fn describe_status_word(sw: u16) -> String {
+    let [sw1, sw2] = sw.to_be_bytes();
+    let description = match (sw1, sw2) {
+        (0x90, 0x00) => "normal ending of the command",
+        (0x91, _) => return format!("normal ending; proactive command of {} bytes pending", sw2),
+        (0x92, _) => "normal ending with the information concerning the data transfer session",
+        (0x93, 0x00) => "toolkit busy; the command cannot be executed at present",
+        (0x61, _) => return format!("{} response bytes still available", sw2),
+        (0x62, 0x00) => "no information given; state of non-volatile memory unchanged",
+        (0x62, 0x81) => "part of returned data may be corrupted",
+        (0x62, 0x82) => "end of file or record reached before reading Le bytes",
+        (0x62, 0x83) => "selected file invalidated",
+        (0x62, 0x85) => "selected file in termination state",
+        (0x62, 0xf1) => "more data available",
+        (0x62, 0xf2) => "more data available and proactive command pending",
+        (0x62, 0xf3) => "response data available",
+        (0x63, 0xf1) => "more data expected",
+        (0x63, 0xf2) => "more data expected and proactive command pending",
+        (0x63, _) if sw2 & 0xf0 == 0xc0 => {
+            return format!("verification failed; {} retries remaining", sw2 & 0x0f)
+        }
+        (0x64, 0x00) => "no information given; state of non-volatile memory unchanged",
+        (0x65, 0x00) => "no information given; state of non-volatile memory changed",
+        (0x65, 0x81) => "memory problem",
+        (0x67, 0x00) => "wrong length",
+        (0x67, _) => "incorrect parameter P3",
+        (0x68, 0x00) => "functions in class not supported",
+        (0x68, 0x81) => "logical channel not supported",
+        (0x68, 0x82) => "secure messaging not supported",
+        (0x68, 0x83) => "last command of the chain expected",
+        (0x68, 0x84) => "command chaining not supported",
+        (0x69, 0x00) => "command not allowed",
+        (0x69, 0x81) => "command incompatible with file structure",
+        (0x69, 0x82) => "security status not satisfied",
+        (0x69, 0x83) => "authentication or PIN method blocked",
+        (0x69, 0x84) => "referenced data invalidated",
+        (0x69, 0x85) => "conditions of use not satisfied",
+        (0x69, 0x86) => "command not allowed; no EF selected",
+        (0x69, 0x87) => "expected SM data objects missing",
+        (0x69, 0x88) => "incorrect SM data objects",
+        (0x6a, 0x80) => "incorrect parameters in the data field",
+        (0x6a, 0x81) => "function not supported",
+        (0x6a, 0x82) => "file not found",
+        (0x6a, 0x83) => "record not found",
+        (0x6a, 0x84) => "not enough memory space",
+        (0x6a, 0x86) => "incorrect parameters P1 to P2",
+        (0x6a, 0x87) => "Lc inconsistent with P1 to P2",
+        (0x6a, 0x88) => "referenced data not found",
+        (0x6b, 0x00) => "wrong parameters P1 to P2",
+        (0x6c, _) => return format!("wrong length; the exact length is {}", sw2),
+        (0x6d, 0x00) => "instruction code not supported or invalid",
+        (0x6e, 0x00) => "class not supported",
+        (0x6f, _) => "technical problem; no precise diagnosis",
+        (0x98, 0x50) => "INCREASE cannot be performed; maximum value reached",
+        (0x98, 0x62) => "authentication error; application specific",
+        (0x98, 0x63) => "security session or association expired",
+        (0x98, 0x64) => "minimum UICC suspension time is too long",
+        _ => "unknown status word",
+    };
+    description.to_string()
+}
+
+impl ResponseAPDU {
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn get_sw1(&self) -> u8 {
+        self.sw1
+    }
+
+    pub fn get_sw2(&self) -> u8 {
+        self.sw2
+    }
+
+    pub fn get_status_word(&self) -> u16 {
+        u16::from_be_bytes([self.sw1, self.sw2])
+    }
+
+    /// Returns whether the command has been executed successfully; that is '9000', '91XX' or '92XX'.
+    pub fn is_normal_ending(&self) -> bool {
+        matches!(self.sw1, 0x90..=0x92)
+    }
+
+    /// Returns the number of the response bytes still available by '61XX': ref 10.2.1.2 / ETSI TS 102 221 V15.0.0
+    pub fn get_available_response_bytes(&self) -> Option<u8> {
+        if self.sw1 == 0x61 {
+            return Some(self.sw2);
+        }
+        None
+    }
+
+    /// Returns the exact length that should be given as Le by '6CXX': ref 10.2.1.2 / ETSI TS 102 221 V15.0.0
+    pub fn get_correct_le(&self) -> Option<u8> {
+        if self.sw1 == 0x6c {
+            return Some(self.sw2);
+        }
+        None
+    }
+
+    /// Returns the length of the response data of the pending proactive command by '91XX': ref 10.2.1.1 /
+    /// ETSI TS 102 221 V15.0.0
+    pub fn get_pending_proactive_command_length(&self) -> Option<u8> {
+        if self.sw1 == 0x91 {
+            return Some(self.sw2);
+        }
+        None
+    }
+
+    /// Returns whether the selected file is invalidated, i.e. deactivated, by '6283': ref 10.2.1.2 / ETSI TS
+    /// 102 221 V15.0.0
+    pub fn is_selected_file_invalidated(&self) -> bool {
+        self.get_status_word() == 0x6283
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.data.clone();
+        bytes.push(self.sw1);
+        bytes.push(self.sw2);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::response_apdu::{describe_status_word, parse_response_apdu, ResponseAPDUError};
+
+    #[test]
+    fn should_parse_response_apdu_successfully() {
+        let response = parse_response_apdu(&[0x01, 0x02, 0x90, 0x00]).unwrap();
+        assert_eq!(response.get_data(), &[0x01, 0x02]);
+        assert_eq!(response.get_status_word(), 0x9000);
+        assert!(response.is_normal_ending());
+        assert_eq!(response.to_bytes(), Vec::from([0x01, 0x02, 0x90, 0x00]));
+
+        let response = parse_response_apdu(&[0x61, 0x1c]).unwrap();
+        assert!(response.get_data().is_empty());
+        assert!(!response.is_normal_ending());
+        assert_eq!(response.get_available_response_bytes(), Some(0x1c));
+        assert_eq!(response.get_correct_le(), None);
+    }
+
+    #[test]
+    fn should_fail_parse_response_apdu_without_status_word() {
+        assert_eq!(
+            parse_response_apdu(&[0x90]).unwrap_err(),
+            ResponseAPDUError::IllegalResponseLength(1)
+        );
+    }
+
+    #[test]
+    fn should_describe_status_word() {
+        assert_eq!(describe_status_word(0x9000), "normal ending of the command");
+        assert_eq!(
+            describe_status_word(0x9112),
+            "normal ending; proactive command of 18 bytes pending"
+        );
+        assert_eq!(
+            describe_status_word(0x63c2),
+            "verification failed; 2 retries remaining"
+        );
+        assert_eq!(describe_status_word(0x6a82), "file not found");
+        assert_eq!(describe_status_word(0x1234), "unknown status word");
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{encode_tag, parse_ber_tlv, BerTlv, BerTlvError};
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::RetrieveData;
+use crate::response_apdu::ResponseAPDU;
+use crate::session::{Session, SessionError};
+
+/// The block of RETRIEVE DATA and SET DATA that is coded in P1; ref 11.3.1 and 11.3.2 / ETSI TS 102 221
+/// V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum DataBlock {
+    /// The first block, with the tag of the data object in the command data
+    First = 0x00,
+    /// The next block of the same data object
+    Next = 0x01,
+    /// The previous block again, e.g. after the transmission error
+    Retransmit = 0x81,
+}
+
+/// RetrieveDataCommand: ref 11.3.1 / ETSI TS 102 221 V15.0.0
+pub struct RetrieveDataCommand {
+    block: DataBlock,
+    data: Option<Vec<u8>>,
+}
+
+/// RetrieveDataResult: a block of the data object and whether more blocks follow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetrieveDataResult {
+    data: Vec<u8>,
+    more_data_available: bool,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum RetrieveDataError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("unsuccessful status word of RETRIEVE DATA: '{0:04X}'")]
+    UnsuccessfulStatusWord(u16),
+    #[error("invalid data object: {0}")]
+    InvalidBerTlv(#[from] BerTlvError),
+    #[error("unexpected tag of the retrieved data object; this must be '{0:X}' but '{1:X}'")]
+    UnexpectedTag(u32, u32),
+    #[error("too many blocks; the limit is {0}")]
+    TooManyBlocks(usize),
+}
+
+/// Retrieves the first block of the data object of the tag.
+pub fn new_retrieve_first_block_command(tag: u32) -> RetrieveDataCommand {
+    RetrieveDataCommand {
+        block: DataBlock::First,
+        data: Some(encode_tag(tag)),
+    }
+}
+
+pub fn new_retrieve_next_block_command() -> RetrieveDataCommand {
+    RetrieveDataCommand {
+        block: DataBlock::Next,
+        data: None,
+    }
+}
+
+pub fn new_retrieve_previous_block_command() -> RetrieveDataCommand {
+    RetrieveDataCommand {
+        block: DataBlock::Retransmit,
+        data: None,
+    }
+}
+
+/// Retrieves the whole data object of the tag from the current BER-TLV structure EF, reassembling the
+/// blocks while the UICC answers '62F1' or '62F2'. `max_blocks` bounds the number of the commands.
+pub fn retrieve_data_object(
+    session: &mut Session,
+    class: &Class,
+    tag: u32,
+    max_blocks: usize,
+) -> Result<BerTlv, RetrieveDataError> {
+    let mut bytes = Vec::new();
+    let mut command = new_retrieve_first_block_command(tag);
+    for _ in 0..max_blocks {
+        let result = command.parse_response(&session.transmit(&command.to_command_apdu(class))?)?;
+        bytes.extend_from_slice(result.get_data());
+        if !result.is_more_data_available() {
+            let (tlv, _) = parse_ber_tlv(&bytes)?;
+            if tlv.get_tag() != tag {
+                return Err(RetrieveDataError::UnexpectedTag(tag, tlv.get_tag()));
+            }
+            return Ok(tlv);
+        }
+        command = new_retrieve_next_block_command();
+    }
+    Err(RetrieveDataError::TooManyBlocks(max_blocks))
+}
+
+impl RetrieveDataCommand {
+    pub fn get_block(&self) -> DataBlock {
+        self.block
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &RetrieveData {},
+            self.block as u8,
+            0x00,
+            Some(0x00),
+            self.data.as_deref(),
+        )
+    }
+
+    /// Parses the response; '62F1' and '62F2' mean that more data are available, and the latter also
+    /// means that a proactive command is pending.
+    pub fn parse_response(
+        &self,
+        response: &ResponseAPDU,
+    ) -> Result<RetrieveDataResult, RetrieveDataError> {
+        let more_data_available = match response.get_status_word() {
+            0x62f1 | 0x62f2 => true,
+            _ if response.is_normal_ending() => false,
+            sw => return Err(RetrieveDataError::UnsuccessfulStatusWord(sw)),
+        };
+        Ok(RetrieveDataResult {
+            data: response.get_data().to_vec(),
+            more_data_available,
+        })
+    }
+}
+
+impl RetrieveDataResult {
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn is_more_data_available(&self) -> bool {
+        self.more_data_available
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::retrieve_data::{
+        new_retrieve_first_block_command, new_retrieve_next_block_command,
+        new_retrieve_previous_block_command, retrieve_data_object, RetrieveDataError,
+    };
+    use crate::session::{new_session, SessionConfig};
+    use crate::testing::new_scripted_card;
+
+    #[test]
+    fn should_construct_retrieve_data_commands() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            new_retrieve_first_block_command(0xdf21)
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x81, 0xcb, 0x00, 0x00, 0x02, 0xdf, 0x21, 0x00])
+        );
+        assert_eq!(
+            new_retrieve_next_block_command()
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x81, 0xcb, 0x01, 0x00, 0x00])
+        );
+        assert_eq!(
+            new_retrieve_previous_block_command()
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x81, 0xcb, 0x81, 0x00, 0x00])
+        );
+    }
+
+    #[test]
+    fn should_reassemble_data_object() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let mut card = new_scripted_card(&[
+            &[0xdf, 0x21, 0x04, 0x01, 0x62, 0xf1],
+            &[0x02, 0x03, 0x62, 0xf1],
+            &[0x04, 0x90, 0x00],
+            &[0x6a, 0x88],
+        ]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        let tlv = retrieve_data_object(&mut session, &class, 0xdf21, 8).unwrap();
+        assert_eq!(tlv.get_tag(), 0xdf21);
+        assert_eq!(tlv.get_value(), &[0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(
+            retrieve_data_object(&mut session, &class, 0xdf22, 8).unwrap_err(),
+            RetrieveDataError::UnsuccessfulStatusWord(0x6a88)
+        );
+        assert_eq!(card.commands[2], Vec::from([0x80, 0xcb, 0x01, 0x00, 0x00]));
+    }
+}
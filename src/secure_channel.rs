@@ -0,0 +1,570 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::{ManageSecureChannel, TransactData};
+use crate::response_apdu::ResponseAPDU;
+use crate::retrieve_data::DataBlock;
+use crate::session::{Session, SessionError};
+
+const MAX_BLOCK_SIZE: usize = 0xff;
+
+/// SecureChannelOperation: the operation of MANAGE SECURE CHANNEL that is coded in P1; ref 11.1.20 / ETSI TS
+/// 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum SecureChannelOperation {
+    RetrieveUiccEndpoints = 0x00,
+    EstablishMasterSa = 0x01,
+    EstablishConnectionSa = 0x02,
+    StartSecureChannel = 0x03,
+    TerminateSecureChannel = 0x04,
+}
+
+/// ManageSecureChannelCommand: ref 11.1.20 / ETSI TS 102 221 V15.0.0 and ETSI TS 102 484
+pub struct ManageSecureChannelCommand {
+    operation: SecureChannelOperation,
+    p2: u8,
+    data: Option<Vec<u8>>,
+    le: Option<u8>,
+}
+
+/// TransactDataCommand: ref 11.1.21 / ETSI TS 102 221 V15.0.0
+///
+/// P1 b1 tells whether the command sends the data to the UICC or retrieves the data from it, and b2 and b3
+/// mark the first and the last block of the transaction; P2 is the transaction ID.
+pub struct TransactDataCommand {
+    p1: u8,
+    transaction_id: u8,
+    data: Option<Vec<u8>>,
+}
+
+/// SecureChannelBlock: a block of the response data and whether more blocks follow by '62F1' or '62F2'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecureChannelBlock {
+    data: Vec<u8>,
+    more_data_available: bool,
+}
+
+/// SecureChannelCrypto: the cryptographic primitives of the secure channel, e.g. the key agreement of the
+/// security associations and the protection of the transaction data; ref ETSI TS 102 484
+pub trait SecureChannelCrypto {
+    /// Returns the command data of ESTABLISH SA - Master SA for the UICC endpoints.
+    fn establish_master_sa(&mut self, uicc_endpoints: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Returns the command data of ESTABLISH SA - Connection SA for the response of the master SA.
+    fn establish_connection_sa(&mut self, master_sa_response: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Returns the command data of START SECURE CHANNEL for the response of the connection SA.
+    fn start_secure_channel(&mut self, connection_sa_response: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Protects the data that is sent by TRANSACT DATA.
+    fn protect(&mut self, data: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Verifies and recovers the data that is retrieved by TRANSACT DATA.
+    fn unprotect(&mut self, data: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SecureChannelError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("unsuccessful status word of {0:?}: '{1:04X}'")]
+    UnsuccessfulStatusWord(SecureChannelOperation, u16),
+    #[error("unsuccessful status word of TRANSACT DATA: '{0:04X}'")]
+    UnsuccessfulTransaction(u16),
+    #[error("illegal length of the block; this must be within [1, 255] bytes but {0} bytes")]
+    IllegalBlockLength(usize),
+    #[error("cryptographic failure of the secure channel: {0}")]
+    CryptographicFailure(String),
+    #[error("too many blocks; the limit is {0}")]
+    TooManyBlocks(usize),
+}
+
+/// Retrieves a block of the UICC endpoints; P2 codes the block as RETRIEVE DATA does.
+pub fn new_retrieve_uicc_endpoints_command(block: DataBlock) -> ManageSecureChannelCommand {
+    ManageSecureChannelCommand {
+        operation: SecureChannelOperation::RetrieveUiccEndpoints,
+        p2: block as u8,
+        data: None,
+        le: Some(0x00),
+    }
+}
+
+/// Sends a block of ESTABLISH SA; P2 b8 marks the first block of the command data.
+pub fn new_establish_sa_command(
+    operation: SecureChannelOperation,
+    first_block: bool,
+    data: &[u8],
+) -> Result<ManageSecureChannelCommand, SecureChannelError> {
+    if data.is_empty() || data.len() > MAX_BLOCK_SIZE {
+        return Err(SecureChannelError::IllegalBlockLength(data.len()));
+    }
+    Ok(ManageSecureChannelCommand {
+        operation,
+        p2: if first_block { 0x80 } else { 0x00 },
+        data: Some(data.to_vec()),
+        le: Some(0x00),
+    })
+}
+
+/// Retrieves the next block of the response data of ESTABLISH SA; P2 codes the block as RETRIEVE DATA does.
+pub fn new_establish_sa_next_block_command(
+    operation: SecureChannelOperation,
+) -> ManageSecureChannelCommand {
+    ManageSecureChannelCommand {
+        operation,
+        p2: DataBlock::Next as u8,
+        data: None,
+        le: Some(0x00),
+    }
+}
+
+pub fn new_start_secure_channel_command(
+    data: &[u8],
+) -> Result<ManageSecureChannelCommand, SecureChannelError> {
+    if data.is_empty() || data.len() > MAX_BLOCK_SIZE {
+        return Err(SecureChannelError::IllegalBlockLength(data.len()));
+    }
+    Ok(ManageSecureChannelCommand {
+        operation: SecureChannelOperation::StartSecureChannel,
+        p2: 0x00,
+        data: Some(data.to_vec()),
+        le: None,
+    })
+}
+
+/// Terminates the secure channel that the data identifies, or all the secure channels without the data.
+pub fn new_terminate_secure_channel_command(data: Option<&[u8]>) -> ManageSecureChannelCommand {
+    ManageSecureChannelCommand {
+        operation: SecureChannelOperation::TerminateSecureChannel,
+        p2: 0x00,
+        data: data.map(|d| d.to_vec()),
+        le: None,
+    }
+}
+
+/// Sends a block of the transaction data to the UICC.
+pub fn new_send_transaction_data_command(
+    transaction_id: u8,
+    first_block: bool,
+    last_block: bool,
+    data: &[u8],
+) -> Result<TransactDataCommand, SecureChannelError> {
+    if data.is_empty() || data.len() > MAX_BLOCK_SIZE {
+        return Err(SecureChannelError::IllegalBlockLength(data.len()));
+    }
+    Ok(TransactDataCommand {
+        p1: 0b001 | ((first_block as u8) << 1) | ((last_block as u8) << 2),
+        transaction_id,
+        data: Some(data.to_vec()),
+    })
+}
+
+/// Retrieves a block of the transaction data from the UICC.
+pub fn new_retrieve_transaction_data_command(
+    transaction_id: u8,
+    first_block: bool,
+) -> TransactDataCommand {
+    TransactDataCommand {
+        p1: (first_block as u8) << 1,
+        transaction_id,
+        data: None,
+    }
+}
+
+/// Establishes the secure channel: retrieves the UICC endpoints, establishes the master SA and the
+/// connection SA, and starts the secure channel. `max_blocks` bounds the number of the blocks of each
+/// retrieval.
+pub fn establish_secure_channel(
+    session: &mut Session,
+    class: &Class,
+    crypto: &mut dyn SecureChannelCrypto,
+    max_blocks: usize,
+) -> Result<(), SecureChannelError> {
+    let uicc_endpoints = retrieve_uicc_endpoints(session, class, max_blocks)?;
+    let data = crypto
+        .establish_master_sa(&uicc_endpoints)
+        .map_err(SecureChannelError::CryptographicFailure)?;
+    let response = establish_sa(
+        session,
+        class,
+        SecureChannelOperation::EstablishMasterSa,
+        &data,
+        max_blocks,
+    )?;
+    let data = crypto
+        .establish_connection_sa(&response)
+        .map_err(SecureChannelError::CryptographicFailure)?;
+    let response = establish_sa(
+        session,
+        class,
+        SecureChannelOperation::EstablishConnectionSa,
+        &data,
+        max_blocks,
+    )?;
+    let data = crypto
+        .start_secure_channel(&response)
+        .map_err(SecureChannelError::CryptographicFailure)?;
+    let command = new_start_secure_channel_command(&data)?;
+    command.parse_response(&session.transmit(&command.to_command_apdu(class))?)?;
+    Ok(())
+}
+
+/// Sends the data protected by the secure channel in the transaction and returns the recovered response
+/// data of the UICC, if any.
+pub fn transact_data(
+    session: &mut Session,
+    class: &Class,
+    crypto: &mut dyn SecureChannelCrypto,
+    transaction_id: u8,
+    data: &[u8],
+    max_blocks: usize,
+) -> Result<Vec<u8>, SecureChannelError> {
+    let protected = crypto
+        .protect(data)
+        .map_err(SecureChannelError::CryptographicFailure)?;
+    let blocks: Vec<&[u8]> = protected.chunks(MAX_BLOCK_SIZE).collect();
+    let mut more_data_available = false;
+    for (i, block) in blocks.iter().enumerate() {
+        let command = new_send_transaction_data_command(
+            transaction_id,
+            i == 0,
+            i == blocks.len() - 1,
+            block,
+        )?;
+        more_data_available = command
+            .parse_response(&session.transmit(&command.to_command_apdu(class))?)?
+            .is_more_data_available();
+    }
+    if !more_data_available {
+        return Ok(Vec::new());
+    }
+
+    let mut bytes = Vec::new();
+    for i in 0..max_blocks {
+        let command = new_retrieve_transaction_data_command(transaction_id, i == 0);
+        let block = command.parse_response(&session.transmit(&command.to_command_apdu(class))?)?;
+        bytes.extend_from_slice(block.get_data());
+        if !block.is_more_data_available() {
+            return crypto
+                .unprotect(&bytes)
+                .map_err(SecureChannelError::CryptographicFailure);
+        }
+    }
+    Err(SecureChannelError::TooManyBlocks(max_blocks))
+}
+
+fn retrieve_uicc_endpoints(
+    session: &mut Session,
+    class: &Class,
+    max_blocks: usize,
+) -> Result<Vec<u8>, SecureChannelError> {
+    let mut bytes = Vec::new();
+    let mut block = DataBlock::First;
+    for _ in 0..max_blocks {
+        let command = new_retrieve_uicc_endpoints_command(block);
+        let result = command.parse_response(&session.transmit(&command.to_command_apdu(class))?)?;
+        bytes.extend_from_slice(result.get_data());
+        if !result.is_more_data_available() {
+            return Ok(bytes);
+        }
+        block = DataBlock::Next;
+    }
+    Err(SecureChannelError::TooManyBlocks(max_blocks))
+}
+
+fn establish_sa(
+    session: &mut Session,
+    class: &Class,
+    operation: SecureChannelOperation,
+    data: &[u8],
+    max_blocks: usize,
+) -> Result<Vec<u8>, SecureChannelError> {
+    let mut result = SecureChannelBlock {
+        data: Vec::new(),
+        more_data_available: false,
+    };
+    for (i, block) in data.chunks(MAX_BLOCK_SIZE).enumerate() {
+        let command = new_establish_sa_command(operation, i == 0, block)?;
+        result = command.parse_response(&session.transmit(&command.to_command_apdu(class))?)?;
+    }
+
+    // the response to the last block of the command data may continue in the next blocks
+    let mut bytes = result.data;
+    let mut more_data_available = result.more_data_available;
+    for _ in 1..max_blocks {
+        if !more_data_available {
+            return Ok(bytes);
+        }
+        let command = new_establish_sa_next_block_command(operation);
+        let result = command.parse_response(&session.transmit(&command.to_command_apdu(class))?)?;
+        bytes.extend_from_slice(result.get_data());
+        more_data_available = result.is_more_data_available();
+    }
+    if more_data_available {
+        return Err(SecureChannelError::TooManyBlocks(max_blocks));
+    }
+    Ok(bytes)
+}
+
+fn parse_block(response: &ResponseAPDU) -> Option<SecureChannelBlock> {
+    let more_data_available = match response.get_status_word() {
+        0x62f1 | 0x62f2 => true,
+        _ if response.is_normal_ending() => false,
+        _ => return None,
+    };
+    Some(SecureChannelBlock {
+        data: response.get_data().to_vec(),
+        more_data_available,
+    })
+}
+
+impl ManageSecureChannelCommand {
+    pub fn get_operation(&self) -> SecureChannelOperation {
+        self.operation
+    }
+
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &ManageSecureChannel {},
+            self.operation as u8,
+            self.p2,
+            self.le,
+            self.data.as_deref(),
+        )
+    }
+
+    pub fn parse_response(
+        &self,
+        response: &ResponseAPDU,
+    ) -> Result<SecureChannelBlock, SecureChannelError> {
+        parse_block(response).ok_or(SecureChannelError::UnsuccessfulStatusWord(
+            self.operation,
+            response.get_status_word(),
+        ))
+    }
+}
+
+impl TransactDataCommand {
+    pub fn get_p1(&self) -> u8 {
+        self.p1
+    }
+
+    pub fn get_transaction_id(&self) -> u8 {
+        self.transaction_id
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        let le = match self.data {
+            Some(_) => None,
+            None => Some(0x00),
+        };
+        new_command_apdu(
+            class,
+            &TransactData {},
+            self.p1,
+            self.transaction_id,
+            le,
+            self.data.as_deref(),
+        )
+    }
+
+    pub fn parse_response(
+        &self,
+        response: &ResponseAPDU,
+    ) -> Result<SecureChannelBlock, SecureChannelError> {
+        parse_block(response).ok_or(SecureChannelError::UnsuccessfulTransaction(
+            response.get_status_word(),
+        ))
+    }
+}
+
+impl SecureChannelBlock {
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn is_more_data_available(&self) -> bool {
+        self.more_data_available
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::retrieve_data::DataBlock;
+    use crate::secure_channel::{
+        establish_secure_channel, new_establish_sa_command, new_establish_sa_next_block_command,
+        new_retrieve_uicc_endpoints_command, new_send_transaction_data_command,
+        new_terminate_secure_channel_command, transact_data, SecureChannelCrypto,
+        SecureChannelError, SecureChannelOperation,
+    };
+    use crate::session::{new_session, SessionConfig};
+    use crate::testing::new_scripted_card;
+
+    /// XorCrypto echoes the previous response in the next command and masks the transaction data.
+    struct XorCrypto {}
+
+    impl SecureChannelCrypto for XorCrypto {
+        fn establish_master_sa(&mut self, uicc_endpoints: &[u8]) -> Result<Vec<u8>, String> {
+            Ok(uicc_endpoints.to_vec())
+        }
+
+        fn establish_connection_sa(
+            &mut self,
+            master_sa_response: &[u8],
+        ) -> Result<Vec<u8>, String> {
+            Ok(master_sa_response.to_vec())
+        }
+
+        fn start_secure_channel(
+            &mut self,
+            connection_sa_response: &[u8],
+        ) -> Result<Vec<u8>, String> {
+            Ok(connection_sa_response.to_vec())
+        }
+
+        fn protect(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+            Ok(data.iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        fn unprotect(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+            Ok(data.iter().map(|b| b ^ 0x5a).collect())
+        }
+    }
+
+    #[test]
+    fn should_construct_secure_channel_commands() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            new_retrieve_uicc_endpoints_command(DataBlock::Next)
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x00, 0x73, 0x00, 0x01, 0x00])
+        );
+        assert_eq!(
+            new_establish_sa_command(SecureChannelOperation::EstablishMasterSa, true, &[0x01])
+                .unwrap()
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x00, 0x73, 0x01, 0x80, 0x01, 0x01, 0x00])
+        );
+        assert_eq!(
+            new_terminate_secure_channel_command(None)
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x00, 0x73, 0x04, 0x00])
+        );
+        assert_eq!(
+            new_send_transaction_data_command(0x07, true, true, &[0xaa])
+                .unwrap()
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x00, 0x75, 0x07, 0x07, 0x01, 0xaa])
+        );
+        assert_eq!(
+            new_send_transaction_data_command(0x07, true, true, &[]).err(),
+            Some(SecureChannelError::IllegalBlockLength(0))
+        );
+    }
+
+    #[test]
+    fn should_establish_secure_channel_and_transact_data() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let mut card = new_scripted_card(&[
+            &[0x01, 0x02, 0x62, 0xf1],
+            &[0x03, 0x90, 0x00],
+            &[0x11, 0x90, 0x00],
+            &[0x22, 0x90, 0x00],
+            &[0x90, 0x00],
+            &[0x62, 0xf1],
+            &[0x5b, 0x62, 0xf1],
+            &[0x58, 0x90, 0x00],
+        ]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        let mut crypto = XorCrypto {};
+        establish_secure_channel(&mut session, &class, &mut crypto, 4).unwrap();
+        assert_eq!(
+            transact_data(&mut session, &class, &mut crypto, 0x01, &[0x00], 4).unwrap(),
+            Vec::from([0x01, 0x02])
+        );
+        assert_eq!(
+            card.commands[2],
+            Vec::from([0x00, 0x73, 0x01, 0x80, 0x03, 0x01, 0x02, 0x03, 0x00])
+        );
+        assert_eq!(card.commands[3][..6], [0x00, 0x73, 0x02, 0x80, 0x01, 0x11]);
+        assert_eq!(
+            card.commands[4],
+            Vec::from([0x00, 0x73, 0x03, 0x00, 0x01, 0x22])
+        );
+        assert_eq!(
+            card.commands[5],
+            Vec::from([0x00, 0x75, 0x07, 0x01, 0x01, 0x5a])
+        );
+        assert_eq!(card.commands[6], Vec::from([0x00, 0x75, 0x02, 0x01, 0x00]));
+        assert_eq!(card.commands[7], Vec::from([0x00, 0x75, 0x00, 0x01, 0x00]));
+    }
+
+    #[test]
+    fn should_retrieve_next_block_of_establish_sa_response() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            new_establish_sa_next_block_command(SecureChannelOperation::EstablishMasterSa)
+                .to_command_apdu(&class)
+                .to_bytes()
+                .unwrap(),
+            Vec::from([0x00, 0x73, 0x01, 0x01, 0x00])
+        );
+
+        let mut card = new_scripted_card(&[
+            &[0x01, 0x90, 0x00],
+            &[0x11, 0x62, 0xf2],
+            &[0x12, 0x90, 0x00],
+            &[0x22, 0x90, 0x00],
+            &[0x90, 0x00],
+        ]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        establish_secure_channel(&mut session, &class, &mut XorCrypto {}, 4).unwrap();
+        assert_eq!(card.commands[2], Vec::from([0x00, 0x73, 0x01, 0x01, 0x00]));
+        assert_eq!(
+            card.commands[3][..7],
+            [0x00, 0x73, 0x02, 0x80, 0x02, 0x11, 0x12]
+        );
+
+        let mut card = new_scripted_card(&[&[0x01, 0x90, 0x00], &[0x11, 0x62, 0xf1]]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        assert_eq!(
+            establish_secure_channel(&mut session, &class, &mut XorCrypto {}, 1).err(),
+            Some(SecureChannelError::TooManyBlocks(1))
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{find_ber_tlv, new_ber_tlv, parse_ber_tlvs, BerTlvError};
+use crate::command_apdu::{new_command_apdu_builder, CommandAPDU, CommandAPDUError};
+use crate::response_apdu::{new_response_apdu, ResponseAPDU};
+use crate::session::{Session, SessionError};
+
+const PADDING_CONTENT_INDICATOR: u8 = 0x01;
+const ENCRYPTED_DATA_TAG: u32 = 0x87;
+const ENCRYPTED_BER_TLV_DATA_TAG: u32 = 0x85;
+const LE_TAG: u32 = 0x97;
+const PROCESSING_STATUS_TAG: u32 = 0x99;
+const MAC_TAG: u32 = 0x8e;
+
+/// SecureMessagingCipher: the cipher of the secure messaging, e.g. 3DES or AES in CBC mode with the session
+/// key. The data is padded to the block size before the encryption.
+pub trait SecureMessagingCipher {
+    fn get_block_size(&self) -> usize;
+    fn encrypt(&mut self, data: &[u8]) -> Result<Vec<u8>, String>;
+    fn decrypt(&mut self, data: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+/// SecureMessagingMac: the cryptographic checksum of the secure messaging, e.g. the retail MAC or CMAC with
+/// the send sequence counter. The implementation pads the input as the algorithm requires.
+pub trait SecureMessagingMac {
+    fn get_block_size(&self) -> usize;
+    fn calculate(&mut self, data: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+/// SecureMessaging: ref 10 / ISO/IEC 7816-4
+///
+/// Wraps the command into the SM data objects, i.e. '87' or '85' for the encrypted command data, '97' for
+/// Le and '8E' for the MAC, and unwraps the response from '87' or '85', '99' and '8E'. The class byte of the
+/// command tells whether the command header is authenticated.
+pub struct SecureMessaging<'c> {
+    cipher: &'c mut dyn SecureMessagingCipher,
+    mac: &'c mut dyn SecureMessagingMac,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SecureMessagingError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("{0}")]
+    CommandAPDU(#[from] CommandAPDUError),
+    #[error("class byte '{0:02X}' does not indicate the secure messaging of ISO/IEC 7816-4")]
+    SecureMessagingNotIndicated(u8),
+    #[error("invalid SM data objects in the response: {0}")]
+    InvalidDataObject(#[from] BerTlvError),
+    #[error("malformed '87' of the response; the padding content indicator must be '01'")]
+    InvalidPaddingIndicator,
+    #[error("MAC '8E' is missing in the response")]
+    MissingMac,
+    #[error("MAC of the response does not match")]
+    MacMismatch,
+    #[error("invalid padding of the decrypted data")]
+    InvalidPadding,
+    #[error("cryptographic failure of the secure messaging: {0}")]
+    CryptographicFailure(String),
+}
+
+pub fn new_secure_messaging<'c>(
+    cipher: &'c mut dyn SecureMessagingCipher,
+    mac: &'c mut dyn SecureMessagingMac,
+) -> SecureMessaging<'c> {
+    SecureMessaging { cipher, mac }
+}
+
+/// Returns whether the class byte indicates that the command header is authenticated; `None` when it does
+/// not indicate the secure messaging of ISO/IEC 7816-4; ref 10.1.1 / ETSI TS 102 221 V15.0.0
+pub fn is_command_header_authenticated(class: u8) -> Option<bool> {
+    if class & 0b01000000 != 0 {
+        // extended logical channels: b6 only indicates SM without the header authentication
+        return (class & 0b00100000 != 0).then_some(false);
+    }
+    match class & 0b00001100 {
+        0b00001000 => Some(false),
+        0b00001100 => Some(true),
+        _ => None,
+    }
+}
+
+/// Pads the data by '80' and '00's to a multiple of the block size; ref 5.6.3 / ISO/IEC 7816-4
+fn pad(data: &[u8], block_size: usize) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while !padded.len().is_multiple_of(block_size) {
+        padded.push(0x00);
+    }
+    padded
+}
+
+fn unpad(data: &[u8]) -> Result<Vec<u8>, SecureMessagingError> {
+    match data.iter().rposition(|b| *b != 0x00) {
+        Some(i) if data[i] == 0x80 => Ok(data[..i].to_vec()),
+        _ => Err(SecureMessagingError::InvalidPadding),
+    }
+}
+
+impl<'c> SecureMessaging<'c> {
+    /// Wraps the command and returns the bytes of the protected command APDU.
+    pub fn wrap(&mut self, command: &CommandAPDU) -> Result<Vec<u8>, SecureMessagingError> {
+        let command = command.to_owned_command_apdu()?;
+        let cla = command.get_cla();
+        let header_authenticated = is_command_header_authenticated(cla)
+            .ok_or(SecureMessagingError::SecureMessagingNotIndicated(cla))?;
+
+        let mut data_objects = Vec::new();
+        if let Some(data) = command.get_command_data() {
+            let cryptogram = self
+                .cipher
+                .encrypt(&pad(data, self.cipher.get_block_size()))
+                .map_err(SecureMessagingError::CryptographicFailure)?;
+            // an odd INS means that the command data is BER-TLV, which is encrypted without the indicator
+            let tlv = if command.get_ins() & 0x01 == 0x01 {
+                new_ber_tlv(ENCRYPTED_BER_TLV_DATA_TAG, cryptogram)
+            } else {
+                new_ber_tlv(
+                    ENCRYPTED_DATA_TAG,
+                    [&[PADDING_CONTENT_INDICATOR], &cryptogram[..]].concat(),
+                )
+            };
+            data_objects.extend(tlv.to_bytes());
+        }
+        if let Some(ne) = command.get_max_response_byte_size() {
+            // Le of the extended length is 2 bytes, where 65536 is encoded as '0000'
+            let le = if command.is_extended() {
+                Vec::from((ne as u16).to_be_bytes())
+            } else {
+                Vec::from([ne as u8])
+            };
+            data_objects.extend(new_ber_tlv(LE_TAG, le).to_bytes());
+        }
+
+        let mut mac_input = Vec::new();
+        if header_authenticated {
+            let header = [cla, command.get_ins(), command.get_p1(), command.get_p2()];
+            mac_input.extend(pad(&header, self.mac.get_block_size()));
+        }
+        mac_input.extend_from_slice(&data_objects);
+        let mac = self
+            .mac
+            .calculate(&mac_input)
+            .map_err(SecureMessagingError::CryptographicFailure)?;
+        data_objects.extend(new_ber_tlv(MAC_TAG, mac).to_bytes());
+
+        // the protected command keeps the extended length of the command and may need it for the data objects
+        let extended = command.is_extended() || data_objects.len() > 0xff;
+        let protected = new_command_apdu_builder()
+            .cla(cla)
+            .ins(command.get_ins())
+            .p1(command.get_p1())
+            .p2(command.get_p2())
+            .data(&data_objects)
+            .le(if extended { 0x10000 } else { 0x100 })
+            .build()?;
+        Ok(protected.to_bytes()?)
+    }
+
+    /// Verifies the MAC of the protected response and returns the response of the plain data and the status
+    /// word of '99'. Only the error without the data, e.g. '6988' for the incorrect SM data objects, is
+    /// returned as it is; '9000' or '61XX' without the data fails as the missing MAC.
+    ///
+    /// The errors are '64XX' to '6FXX' of ISO/IEC 7816-4 and '94XX' and '98XX' of ETSI TS 102 221.
+    pub fn unwrap(
+        &mut self,
+        response: &ResponseAPDU,
+    ) -> Result<ResponseAPDU, SecureMessagingError> {
+        if response.get_data().is_empty() && matches!(response.get_sw1(), 0x64..=0x6f | 0x94 | 0x98)
+        {
+            return Ok(new_response_apdu(
+                Vec::new(),
+                response.get_sw1(),
+                response.get_sw2(),
+            ));
+        }
+        let tlvs = parse_ber_tlvs(response.get_data())?;
+        let mac = find_ber_tlv(&tlvs, MAC_TAG).ok_or(SecureMessagingError::MissingMac)?;
+        let mac_input: Vec<u8> = tlvs
+            .iter()
+            .take_while(|tlv| tlv.get_tag() != MAC_TAG)
+            .flat_map(|tlv| tlv.to_bytes())
+            .collect();
+        let expected = self
+            .mac
+            .calculate(&mac_input)
+            .map_err(SecureMessagingError::CryptographicFailure)?;
+        if expected != mac.get_value() {
+            return Err(SecureMessagingError::MacMismatch);
+        }
+
+        let cryptogram = match (
+            find_ber_tlv(&tlvs, ENCRYPTED_DATA_TAG),
+            find_ber_tlv(&tlvs, ENCRYPTED_BER_TLV_DATA_TAG),
+        ) {
+            (Some(tlv), _) => match tlv.get_value().split_first() {
+                Some((&PADDING_CONTENT_INDICATOR, cryptogram)) => cryptogram,
+                _ => return Err(SecureMessagingError::InvalidPaddingIndicator),
+            },
+            (None, Some(tlv)) => tlv.get_value(),
+            (None, None) => &[],
+        };
+        let data = if cryptogram.is_empty() {
+            Vec::new()
+        } else {
+            unpad(
+                &self
+                    .cipher
+                    .decrypt(cryptogram)
+                    .map_err(SecureMessagingError::CryptographicFailure)?,
+            )?
+        };
+        let (sw1, sw2) = match find_ber_tlv(&tlvs, PROCESSING_STATUS_TAG) {
+            Some(tlv) if tlv.get_value().len() == 2 => (tlv.get_value()[0], tlv.get_value()[1]),
+            _ => (response.get_sw1(), response.get_sw2()),
+        };
+        Ok(new_response_apdu(data, sw1, sw2))
+    }
+
+    /// Wraps the command, transmits it and unwraps the response.
+    pub fn transmit(
+        &mut self,
+        session: &mut Session,
+        command: &CommandAPDU,
+    ) -> Result<ResponseAPDU, SecureMessagingError> {
+        let protected = self.wrap(command)?;
+        self.unwrap(&session.transmit_bytes(&protected)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_extended_class, new_standard_class, ClassTypeForExtendedLogicalChannels,
+        ClassTypeForStandardLogicalChannels, SecureMessagingIndicationForExtendedLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::command_apdu::{new_command_apdu, new_extended_command_apdu};
+    use crate::instruction::{ReadBinary, UpdateBinary};
+    use crate::response_apdu::new_response_apdu;
+    use crate::secure_messaging::{
+        is_command_header_authenticated, new_secure_messaging, SecureMessagingCipher,
+        SecureMessagingError, SecureMessagingMac,
+    };
+
+    /// XorCipher masks the data by a constant of the block size 8.
+    struct XorCipher {}
+
+    impl SecureMessagingCipher for XorCipher {
+        fn get_block_size(&self) -> usize {
+            8
+        }
+
+        fn encrypt(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+            Ok(data.iter().map(|b| b ^ 0xff).collect())
+        }
+
+        fn decrypt(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+            Ok(data.iter().map(|b| b ^ 0xff).collect())
+        }
+    }
+
+    /// SumMac records the inputs and returns the byte sum as a 1-byte MAC.
+    struct SumMac {
+        inputs: Vec<Vec<u8>>,
+    }
+
+    impl SecureMessagingMac for SumMac {
+        fn get_block_size(&self) -> usize {
+            8
+        }
+
+        fn calculate(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+            self.inputs.push(data.to_vec());
+            Ok(Vec::from([data
+                .iter()
+                .fold(0u8, |s, b| s.wrapping_add(*b))]))
+        }
+    }
+
+    #[test]
+    fn should_tell_command_header_authentication() {
+        let class = |sm| {
+            new_standard_class(ClassTypeForStandardLogicalChannels::ISOIEC7816_4, sm, 1)
+                .unwrap()
+                .get_byte()
+        };
+        assert_eq!(
+            is_command_header_authenticated(class(
+                SecureMessagingIndicationForStandardLogicalChannels::CommandHeaderAuthenticated
+            )),
+            Some(true)
+        );
+        assert_eq!(
+            is_command_header_authenticated(class(
+                SecureMessagingIndicationForStandardLogicalChannels::NoSM
+            )),
+            None
+        );
+        let extended = new_extended_class(
+            ClassTypeForExtendedLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForExtendedLogicalChannels::CommandHeaderNotAuthenticated,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            is_command_header_authenticated(extended.get_byte()),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn should_wrap_command_and_unwrap_response() {
+        let mut cipher = XorCipher {};
+        let mut mac = SumMac { inputs: Vec::new() };
+        let mut sm = new_secure_messaging(&mut cipher, &mut mac);
+
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::CommandHeaderAuthenticated,
+            0,
+        )
+        .unwrap();
+        let data = [0x01, 0x02];
+        let command = new_command_apdu(&class, &UpdateBinary {}, 0x00, 0x00, None, Some(&data));
+        let protected = sm.wrap(&command).unwrap();
+        let encrypted = [0xfe, 0xfd, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let mut expected = Vec::from([0x0c, 0xd6, 0x00, 0x00, 0x0e, 0x87, 0x09, 0x01]);
+        expected.extend_from_slice(&encrypted);
+        expected.extend_from_slice(&[0x8e, 0x01, 0x68, 0x00]);
+        assert_eq!(protected, expected);
+
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::CommandHeaderNotAuthenticated,
+            0,
+        )
+        .unwrap();
+        let command = new_command_apdu(&class, &ReadBinary {}, 0x00, 0x00, Some(0x02), None);
+        assert_eq!(
+            sm.wrap(&command).unwrap(),
+            Vec::from([0x08, 0xb0, 0x00, 0x00, 0x06, 0x97, 0x01, 0x02, 0x8e, 0x01, 0x9a, 0x00])
+        );
+
+        let mut response = Vec::from([0x87, 0x09, 0x01]);
+        response.extend_from_slice(&encrypted);
+        response.extend_from_slice(&[0x99, 0x02, 0x90, 0x00, 0x8e, 0x01, 0x31]);
+        let plain = sm
+            .unwrap(&new_response_apdu(response.clone(), 0x90, 0x00))
+            .unwrap();
+        assert_eq!(plain.get_data(), &[0x01, 0x02]);
+        assert_eq!(plain.get_status_word(), 0x9000);
+
+        let last = response.len() - 1;
+        response[last] = 0x00;
+        assert_eq!(
+            sm.unwrap(&new_response_apdu(response, 0x90, 0x00)),
+            Err(SecureMessagingError::MacMismatch)
+        );
+
+        // the header authentication pads CLA INS P1 P2 to the block size in the MAC input
+        assert_eq!(
+            mac.inputs[0][..8],
+            [0x0c, 0xd6, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn should_require_mac_without_response_data() {
+        let mut cipher = XorCipher {};
+        let mut mac = SumMac { inputs: Vec::new() };
+        let mut sm = new_secure_messaging(&mut cipher, &mut mac);
+
+        // the SM errors and the other errors have no data objects
+        for (sw1, sw2) in [(0x69, 0x88), (0x69, 0x87), (0x6a, 0x82), (0x98, 0x62)] {
+            let response = sm.unwrap(&new_response_apdu(Vec::new(), sw1, sw2)).unwrap();
+            assert_eq!((response.get_sw1(), response.get_sw2()), (sw1, sw2));
+        }
+        for (sw1, sw2) in [(0x90, 0x00), (0x61, 0x10), (0x62, 0x83)] {
+            assert_eq!(
+                sm.unwrap(&new_response_apdu(Vec::new(), sw1, sw2)),
+                Err(SecureMessagingError::MissingMac)
+            );
+        }
+        assert!(mac.inputs.is_empty());
+    }
+
+    #[test]
+    fn should_wrap_extended_command() {
+        let mut cipher = XorCipher {};
+        let mut mac = SumMac { inputs: Vec::new() };
+        let mut sm = new_secure_messaging(&mut cipher, &mut mac);
+
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::CommandHeaderNotAuthenticated,
+            0,
+        )
+        .unwrap();
+        // 300 bytes are padded to 304 bytes and '87' has 305 bytes with the indicator
+        let data = [0x00; 300];
+        let command =
+            new_extended_command_apdu(&class, &UpdateBinary {}, 0x00, 0x00, None, Some(&data));
+        let protected = sm.wrap(&command).unwrap();
+        assert_eq!(
+            protected[..11],
+            [0x08, 0xd6, 0x00, 0x00, 0x00, 0x01, 0x38, 0x87, 0x82, 0x01, 0x31]
+        );
+        assert_eq!(protected.len(), 7 + 0x138 + 2);
+        assert_eq!(protected[protected.len() - 5..][..2], [0x8e, 0x01]);
+        assert_eq!(protected[protected.len() - 2..], [0x00, 0x00]);
+
+        let command =
+            new_extended_command_apdu(&class, &ReadBinary {}, 0x00, 0x00, Some(0x1000), None);
+        assert_eq!(
+            sm.wrap(&command).unwrap(),
+            Vec::from([
+                0x08, 0xb0, 0x00, 0x00, 0x00, 0x00, 0x07, 0x97, 0x02, 0x10, 0x00, 0x8e, 0x01, 0xa9,
+                0x00, 0x00
+            ])
+        );
+    }
+
+    #[test]
+    fn should_fail_unwrap_with_invalid_padding_indicator() {
+        let mut cipher = XorCipher {};
+        let mut mac = SumMac { inputs: Vec::new() };
+        let mut sm = new_secure_messaging(&mut cipher, &mut mac);
+
+        for value in [
+            &[0x02, 0xfe, 0xfd, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff][..],
+            &[],
+        ] {
+            let mut response = Vec::from([0x87, value.len() as u8]);
+            response.extend_from_slice(value);
+            let sum = response.iter().fold(0u8, |s, b| s.wrapping_add(*b));
+            response.extend_from_slice(&[0x8e, 0x01, sum]);
+            assert_eq!(
+                sm.unwrap(&new_response_apdu(response, 0x90, 0x00)),
+                Err(SecureMessagingError::InvalidPaddingIndicator)
+            );
+        }
+    }
+}
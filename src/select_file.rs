@@ -0,0 +1,127 @@
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::file::{FileId, Path};
+use crate::instruction::SelectFile;
+
+/// FileSelection: selection method of SELECT; ref 11.1.1.2 / ETSI TS 102 221 V15.0.0
+pub enum FileSelection<'a> {
+    /// Select DF, EF or MF by file id (P1 = '00')
+    FileId(FileId),
+    /// Select parent DF of the current DF (P1 = '03')
+    ParentDF,
+    /// Select by DF name, i.e. the AID of an application (P1 = '04')
+    DFName(&'a [u8]),
+    /// Select by path from MF (P1 = '08') or from the current DF (P1 = '09'), according to the path
+    Path(&'a Path),
+}
+
+/// SelectResponse: the data that is requested by SELECT; ref 11.1.1.2 / ETSI TS 102 221 V15.0.0
+pub enum SelectResponse {
+    /// Return FCP template (P2 = '04')
+    FCP,
+    /// No data returned (P2 = '0C')
+    NoData,
+}
+
+/// SelectFileCommand: ref 11.1.1 / ETSI TS 102 221 V15.0.0
+pub struct SelectFileCommand {
+    p1: u8,
+    p2: u8,
+    command_data: Vec<u8>,
+    le: Option<u8>,
+}
+
+pub fn new_select_file_command(
+    selection: FileSelection,
+    response: SelectResponse,
+) -> SelectFileCommand {
+    let (p1, command_data) = match selection {
+        FileSelection::FileId(file_id) => (0x00, file_id.get_bytes().to_vec()),
+        FileSelection::ParentDF => (0x03, Vec::new()),
+        FileSelection::DFName(aid) => (0x04, aid.to_vec()),
+        FileSelection::Path(path) => (path.get_select_p1(), path.to_select_bytes()),
+    };
+    let (p2, le) = match response {
+        SelectResponse::FCP => (0x04, Some(0x00)),
+        SelectResponse::NoData => (0x0c, None),
+    };
+
+    SelectFileCommand {
+        p1,
+        p2,
+        command_data,
+        le,
+    }
+}
+
+impl SelectFileCommand {
+    pub fn get_p1(&self) -> u8 {
+        self.p1
+    }
+
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn get_command_data(&self) -> &[u8] {
+        &self.command_data
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        let command_data = if self.command_data.is_empty() {
+            None
+        } else {
+            Some(&self.command_data[..])
+        };
+        new_command_apdu(
+            class,
+            &SelectFile {},
+            self.p1,
+            self.p2,
+            self.le,
+            command_data,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::file::{new_file_id, parse_path};
+    use crate::select_file::{new_select_file_command, FileSelection, SelectResponse};
+
+    #[test]
+    fn should_construct_select_file_command() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let command = new_select_file_command(
+            FileSelection::FileId(new_file_id(0x2f00).unwrap()),
+            SelectResponse::FCP,
+        );
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0xa4, 0x00, 0x04, 0x02, 0x2f, 0x00, 0x00])
+        );
+
+        let path = parse_path("3F00/7FFF/6F07").unwrap();
+        let command = new_select_file_command(FileSelection::Path(&path), SelectResponse::NoData);
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0xa4, 0x08, 0x0c, 0x04, 0x7f, 0xff, 0x6f, 0x07])
+        );
+
+        let command = new_select_file_command(FileSelection::ParentDF, SelectResponse::NoData);
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x00, 0xa4, 0x03, 0x0c])
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::{
+    new_extended_class, new_standard_class, Class, ClassTypeForExtendedLogicalChannels,
+    ClassTypeForStandardLogicalChannels, SecureMessagingIndicationForExtendedLogicalChannels,
+    SecureMessagingIndicationForStandardLogicalChannels,
+};
+use crate::command_apdu::{
+    is_extended_length, new_command_apdu, CommandAPDU, CommandAPDUError, OwnedCommandAPDU,
+};
+use crate::instruction::GetResponse;
+use crate::response_apdu::{
+    new_response_apdu, parse_response_apdu, ResponseAPDU, ResponseAPDUError,
+};
+use crate::transport::{Transport, TransportError};
+
+/// SessionConfig: the behaviour of the procedure bytes handling in `Session`.
+pub struct SessionConfig {
+    /// The maximum number of the GET RESPONSE and the re-sent commands for a command
+    pub max_chained_responses: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            max_chained_responses: 16,
+        }
+    }
+}
+
+/// Session transmits the commands through a transport, handling '61XX' by GET RESPONSE and '6CXX' by
+/// re-sending the command with the correct Le; ref 7.3.1.1.4 and 10.2.1.2 / ETSI TS 102 221 V15.0.0
+pub struct Session<'a> {
+    transport: &'a mut dyn Transport,
+    config: SessionConfig,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SessionError {
+    #[error("failed to transmit: {0}")]
+    Transport(#[from] TransportError),
+    #[error("failed to construct command APDU: {0}")]
+    CommandAPDU(#[from] CommandAPDUError),
+    #[error("invalid response APDU: {0}")]
+    ResponseAPDU(#[from] ResponseAPDUError),
+    #[error("too many chained responses; the limit is {0}")]
+    TooManyChainedResponses(usize),
+    #[error("the transport does not support the extended Lc and Le")]
+    ExtendedLengthNotSupported,
+}
+
+pub fn new_session(transport: &mut dyn Transport, config: SessionConfig) -> Session<'_> {
+    Session { transport, config }
+}
+
+/// Returns the class of GET RESPONSE on the same logical channel as the given class byte.
+fn get_response_class(class_byte: u8) -> Class {
+    if class_byte & 0b01000000 == 0 {
+        new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            class_byte & 0b00000011,
+        )
+        .unwrap()
+    } else {
+        new_extended_class(
+            ClassTypeForExtendedLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForExtendedLogicalChannels::NoSM,
+            class_byte & 0b00001111,
+        )
+        .unwrap()
+    }
+}
+
+/// Returns the command bytes whose Le is replaced with (or appended by) the given Le.
+fn with_le(command: &[u8], le: u8) -> Vec<u8> {
+    if is_extended_length(command) {
+        // the extended Le is 2 bytes after Lc, or '00' and 2 bytes without Lc
+        let mut bytes = match command.len() {
+            7 => command[..5].to_vec(),
+            _ => {
+                let lc = u16::from_be_bytes([command[5], command[6]]) as usize;
+                command[..(7 + lc).min(command.len())].to_vec()
+            }
+        };
+        bytes.extend_from_slice(&[0x00, le]);
+        return bytes;
+    }
+    let has_le = match command.len() {
+        0..=4 => false,
+        5 => true,
+        len => len > 5 + command[4] as usize,
+    };
+    let mut bytes = command.to_vec();
+    if has_le {
+        bytes.pop();
+    }
+    bytes.push(le);
+    bytes
+}
+
+impl<'a> Session<'a> {
+    pub fn get_config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    pub fn transmit(&mut self, command: &CommandAPDU) -> Result<ResponseAPDU, SessionError> {
+        self.transmit_bytes(&command.to_bytes()?)
+    }
+
+    pub fn transmit_owned(
+        &mut self,
+        command: &OwnedCommandAPDU,
+    ) -> Result<ResponseAPDU, SessionError> {
+        self.transmit_bytes(&command.to_bytes()?)
+    }
+
+    /// Transmits the command bytes and returns one final response. The data of the chained responses are
+    /// concatenated, and the final status word is the one of the last response.
+    pub fn transmit_bytes(&mut self, command: &[u8]) -> Result<ResponseAPDU, SessionError> {
+        if is_extended_length(command) && !self.transport.supports_extended_length() {
+            return Err(SessionError::ExtendedLengthNotSupported);
+        }
+        let mut data = Vec::new();
+        let mut response = parse_response_apdu(&self.transport.transmit(command)?)?;
+        let mut last_command = command.to_vec();
+        let mut chained = 0;
+
+        loop {
+            let next = match (
+                response.get_available_response_bytes(),
+                response.get_correct_le(),
+            ) {
+                (Some(available), _) => {
+                    let class = get_response_class(command.first().copied().unwrap_or(0x00));
+                    new_command_apdu(&class, &GetResponse {}, 0x00, 0x00, Some(available), None)
+                        .to_bytes()?
+                }
+                (_, Some(le)) => with_le(&last_command, le),
+                (None, None) => break,
+            };
+
+            chained += 1;
+            if chained > self.config.max_chained_responses {
+                return Err(SessionError::TooManyChainedResponses(
+                    self.config.max_chained_responses,
+                ));
+            }
+            data.extend_from_slice(response.get_data());
+            response = parse_response_apdu(&self.transport.transmit(&next)?)?;
+            last_command = next;
+        }
+
+        data.extend_from_slice(response.get_data());
+        Ok(new_response_apdu(
+            data,
+            response.get_sw1(),
+            response.get_sw2(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::session::{new_session, SessionConfig, SessionError};
+    use crate::testing::new_scripted_card;
+    use crate::transport::{Transport, TransportError};
+
+    #[test]
+    fn should_chain_get_response() {
+        let mut card = new_scripted_card(&[
+            &[0x61, 0x02],
+            &[0x01, 0x02, 0x61, 0x01],
+            &[0x03, 0x90, 0x00],
+        ]);
+        let response = new_session(&mut card, SessionConfig::default())
+            .transmit_bytes(&[0x01, 0xa4, 0x00, 0x04, 0x02, 0x3f, 0x00])
+            .unwrap();
+        assert_eq!(response.get_data(), &[0x01, 0x02, 0x03]);
+        assert_eq!(response.get_status_word(), 0x9000);
+        assert_eq!(
+            card.commands[1..],
+            [
+                Vec::from([0x01, 0xc0, 0x00, 0x00, 0x02]),
+                Vec::from([0x01, 0xc0, 0x00, 0x00, 0x01])
+            ]
+        );
+    }
+
+    #[test]
+    fn should_resend_with_correct_le() {
+        let mut card = new_scripted_card(&[&[0x6c, 0x03], &[0x01, 0x02, 0x03, 0x90, 0x00]]);
+        let response = new_session(&mut card, SessionConfig::default())
+            .transmit_bytes(&[0x00, 0xb0, 0x00, 0x00, 0x10])
+            .unwrap();
+        assert_eq!(response.get_data(), &[0x01, 0x02, 0x03]);
+        assert_eq!(card.commands[1], Vec::from([0x00, 0xb0, 0x00, 0x00, 0x03]));
+
+        let mut card = new_scripted_card(&[&[0x6c, 0x01], &[0x01, 0x90, 0x00]]);
+        new_session(&mut card, SessionConfig::default())
+            .transmit_bytes(&[0x00, 0xb2, 0x01, 0x04])
+            .unwrap();
+        assert_eq!(card.commands[1], Vec::from([0x00, 0xb2, 0x01, 0x04, 0x01]));
+    }
+
+    #[test]
+    fn should_fail_by_too_many_chained_responses() {
+        let mut card =
+            new_scripted_card(&[&[0x61, 0x01], &[0x01, 0x61, 0x01], &[0x02, 0x61, 0x01]]);
+        let result = new_session(
+            &mut card,
+            SessionConfig {
+                max_chained_responses: 1,
+            },
+        )
+        .transmit_bytes(&[0x00, 0xf2, 0x00, 0x00]);
+        assert_eq!(
+            result.unwrap_err(),
+            SessionError::TooManyChainedResponses(1)
+        );
+    }
+
+    /// ExtendedCard answers '9000' to any command and accepts the extended Lc and Le.
+    struct ExtendedCard {}
+
+    impl Transport for ExtendedCard {
+        fn transmit(&mut self, _: &[u8]) -> Result<Vec<u8>, TransportError> {
+            Ok(Vec::from([0x90, 0x00]))
+        }
+
+        fn supports_extended_length(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn should_refuse_extended_length_unless_supported() {
+        let command = [0x00, 0xb0, 0x00, 0x00, 0x00, 0x02, 0x00];
+        let mut card = new_scripted_card(&[&[0x90, 0x00]]);
+        assert_eq!(
+            new_session(&mut card, SessionConfig::default())
+                .transmit_bytes(&command)
+                .unwrap_err(),
+            SessionError::ExtendedLengthNotSupported
+        );
+        assert!(card.commands.is_empty());
+
+        let mut card = ExtendedCard {};
+        let response = new_session(&mut card, SessionConfig::default())
+            .transmit_bytes(&command)
+            .unwrap();
+        assert_eq!(response.get_status_word(), 0x9000);
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::new_ber_tlv;
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::SetData;
+use crate::response_apdu::ResponseAPDU;
+use crate::retrieve_data::DataBlock;
+use crate::session::{Session, SessionError};
+
+const MAX_BLOCK_SIZE: usize = 0xff;
+
+/// SetDataCommand: ref 11.3.2 / ETSI TS 102 221 V15.0.0
+///
+/// The first block starts with the tag and the length of the data object, and the next blocks continue
+/// its value. A data object of the length zero deletes the object of the tag.
+pub struct SetDataCommand {
+    block: DataBlock,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SetDataError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("unsuccessful status word of SET DATA: '{0:04X}'")]
+    UnsuccessfulStatusWord(u16),
+    #[error("status word '{0:04X}' does not agree with the remaining blocks")]
+    UnexpectedStatusWord(u16),
+    #[error("illegal length of the block; this must be within [1, 255] bytes but {0} bytes")]
+    IllegalBlockLength(usize),
+}
+
+fn new_set_data_command(block: DataBlock, data: &[u8]) -> Result<SetDataCommand, SetDataError> {
+    if data.is_empty() || data.len() > MAX_BLOCK_SIZE {
+        return Err(SetDataError::IllegalBlockLength(data.len()));
+    }
+    Ok(SetDataCommand {
+        block,
+        data: data.to_vec(),
+    })
+}
+
+pub fn new_set_first_block_command(data: &[u8]) -> Result<SetDataCommand, SetDataError> {
+    new_set_data_command(DataBlock::First, data)
+}
+
+pub fn new_set_next_block_command(data: &[u8]) -> Result<SetDataCommand, SetDataError> {
+    new_set_data_command(DataBlock::Next, data)
+}
+
+pub fn new_set_previous_block_command(data: &[u8]) -> Result<SetDataCommand, SetDataError> {
+    new_set_data_command(DataBlock::Retransmit, data)
+}
+
+/// Splits the data object into the SET DATA commands of the blocks.
+pub fn new_set_data_commands(tag: u32, value: &[u8]) -> Vec<SetDataCommand> {
+    new_ber_tlv(tag, value.to_vec())
+        .to_bytes()
+        .chunks(MAX_BLOCK_SIZE)
+        .enumerate()
+        .map(|(i, chunk)| SetDataCommand {
+            block: if i == 0 {
+                DataBlock::First
+            } else {
+                DataBlock::Next
+            },
+            data: chunk.to_vec(),
+        })
+        .collect()
+}
+
+/// Creates or replaces the data object of the tag in the current BER-TLV structure EF, transmitting the
+/// blocks while the UICC answers '63F1' or '63F2'.
+pub fn set_data_object(
+    session: &mut Session,
+    class: &Class,
+    tag: u32,
+    value: &[u8],
+) -> Result<(), SetDataError> {
+    let commands = new_set_data_commands(tag, value);
+    let last = commands.len() - 1;
+    for (i, command) in commands.iter().enumerate() {
+        let response = session.transmit(&command.to_command_apdu(class))?;
+        if command.parse_response(&response)? != (i < last) {
+            return Err(SetDataError::UnexpectedStatusWord(
+                response.get_status_word(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the data object of the tag by the zero length.
+pub fn delete_data_object(
+    session: &mut Session,
+    class: &Class,
+    tag: u32,
+) -> Result<(), SetDataError> {
+    set_data_object(session, class, tag, &[])
+}
+
+impl SetDataCommand {
+    pub fn get_block(&self) -> DataBlock {
+        self.block
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &SetData {},
+            self.block as u8,
+            0x00,
+            None,
+            Some(&self.data),
+        )
+    }
+
+    /// Parses the response and returns whether the UICC expects more data by '63F1' or '63F2'.
+    pub fn parse_response(&self, response: &ResponseAPDU) -> Result<bool, SetDataError> {
+        match response.get_status_word() {
+            0x63f1 | 0x63f2 => Ok(true),
+            _ if response.is_normal_ending() => Ok(false),
+            sw => Err(SetDataError::UnsuccessfulStatusWord(sw)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::retrieve_data::DataBlock;
+    use crate::session::{new_session, SessionConfig};
+    use crate::set_data::{
+        delete_data_object, new_set_data_commands, new_set_first_block_command, set_data_object,
+        SetDataError,
+    };
+    use crate::testing::new_scripted_card;
+
+    #[test]
+    fn should_split_data_object_into_blocks() {
+        let commands = new_set_data_commands(0xdf21, &[0xaa; 300]);
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].get_block(), DataBlock::First);
+        assert_eq!(commands[0].get_data()[..5], [0xdf, 0x21, 0x82, 0x01, 0x2c]);
+        assert_eq!(commands[1].get_block(), DataBlock::Next);
+        assert_eq!(commands[1].get_data().len(), 305 - 255);
+        assert_eq!(
+            new_set_first_block_command(&[]).err(),
+            Some(SetDataError::IllegalBlockLength(0))
+        );
+    }
+
+    #[test]
+    fn should_set_and_delete_data_object() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let mut card =
+            new_scripted_card(&[&[0x63, 0xf1], &[0x90, 0x00], &[0x90, 0x00], &[0x6a, 0x84]]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        set_data_object(&mut session, &class, 0xdf21, &[0x55; 260]).unwrap();
+        delete_data_object(&mut session, &class, 0xdf21).unwrap();
+        assert_eq!(
+            set_data_object(&mut session, &class, 0xdf21, &[0x01]).unwrap_err(),
+            SetDataError::UnsuccessfulStatusWord(0x6a84)
+        );
+        assert_eq!(
+            card.commands[0][..7],
+            [0x80, 0xdb, 0x00, 0x00, 0xff, 0xdf, 0x21]
+        );
+        assert_eq!(card.commands[1][..5], [0x80, 0xdb, 0x01, 0x00, 0x0a]);
+        assert_eq!(
+            card.commands[2],
+            Vec::from([0x80, 0xdb, 0x00, 0x00, 0x03, 0xdf, 0x21, 0x00])
+        );
+    }
+}
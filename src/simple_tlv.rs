@@ -0,0 +1,930 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::comprehension_tlv::{
+    new_comprehension_tlv, ComprehensionTlv, ADDRESS_TAG, ALPHA_IDENTIFIER_TAG,
+    BEARER_DESCRIPTION_TAG, CHANNEL_DATA_TAG, CHANNEL_STATUS_TAG, DURATION_TAG, ITEM_TAG,
+    LOCATION_INFORMATION_TAG,
+};
+
+/// GSM_DEFAULT_ALPHABET: the characters of the GSM 7 bit default alphabet; ref 6.2.1 / 3GPP TS 23.038
+///
+/// '1B' is the escape to the extension table and is held as the escape character.
+const GSM_DEFAULT_ALPHABET: [char; 128] = [
+    '@', '£', '$', '¥', 'è', 'é', 'ù', 'ì', 'ò', 'Ç', '\n', 'Ø', 'ø', '\r', 'Å', 'å', //
+    'Δ', '_', 'Φ', 'Γ', 'Λ', 'Ω', 'Π', 'Ψ', 'Σ', 'Θ', 'Ξ', '\u{1b}', 'Æ', 'æ', 'ß', 'É', //
+    ' ', '!', '"', '#', '¤', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/', //
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?', //
+    '¡', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', //
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', 'Ä', 'Ö', 'Ñ', 'Ü', '§', //
+    '¿', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', //
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', 'ä', 'ö', 'ñ', 'ü', 'à', //
+];
+
+/// GSM_EXTENSION_TABLE: the characters that follow the escape; ref 6.2.1.1 / 3GPP TS 23.038
+const GSM_EXTENSION_TABLE: [(u8, char); 10] = [
+    (0x0a, '\u{0c}'),
+    (0x14, '^'),
+    (0x28, '{'),
+    (0x29, '}'),
+    (0x2f, '\\'),
+    (0x3c, '['),
+    (0x3d, '~'),
+    (0x3e, ']'),
+    (0x40, '|'),
+    (0x65, '€'),
+];
+
+const GSM_ESCAPE: u8 = 0x1b;
+const GSM_CARRIAGE_RETURN: u8 = 0x0d;
+
+/// TextCoding: the alphabet of the text string that is indicated by the data coding scheme; ref 4 / 3GPP
+/// TS 23.038
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextCoding {
+    /// the GSM default alphabet packed in 7 bits
+    Gsm7Bit,
+    /// the GSM default alphabet unpacked in 8 bits
+    Gsm8Bit,
+    /// UCS2 in big endian
+    Ucs2,
+}
+
+/// AlphaIdentifier: ref 8.2 / ETSI TS 102 223 V15.0.0
+///
+/// The text is held as it is encoded; see `decode_alpha_identifier`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlphaIdentifier {
+    text: Vec<u8>,
+}
+
+/// TextString: ref 8.15 / ETSI TS 102 223 V15.0.0
+///
+/// The data coding scheme is absent for the null text string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextString {
+    data_coding_scheme: Option<u8>,
+    text: Vec<u8>,
+}
+
+/// Item: ref 8.9 / ETSI TS 102 223 V15.0.0
+///
+/// The text is coded as the alpha identifier.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    identifier: u8,
+    text: Vec<u8>,
+}
+
+/// TimeUnit: ref 8.8 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum TimeUnit {
+    Minutes = 0x00,
+    Seconds = 0x01,
+    TenthsOfSeconds = 0x02,
+}
+
+/// Duration: ref 8.8 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Duration {
+    unit: TimeUnit,
+    interval: u8,
+}
+
+/// Address: the TON/NPI and the BCD-coded dialling number; ref 8.1 / ETSI TS 102 223 V15.0.0
+///
+/// The digits 'A', 'B' and 'C' of the dialling number are represented by '*', '#' and 'p', and the wild
+/// value 'D' is represented by '?'.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Address {
+    ton_npi: u8,
+    dialling_number: String,
+}
+
+/// LocationInformation: ref 8.19 / ETSI TS 102 223 V15.0.0 and 3GPP TS 31.111
+///
+/// The cell identity holds the rest of the value, whose length depends on the access technology.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationInformation {
+    mcc_mnc: [u8; 3],
+    location_area_code: u16,
+    cell_identity: Vec<u8>,
+}
+
+/// ChannelStatus: ref 8.56 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelStatus {
+    channel_identifier: u8,
+    link_established: bool,
+    further_information: u8,
+}
+
+/// BearerType: ref 8.52 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum BearerType {
+    Csd = 0x01,
+    Gprs = 0x02,
+    Default = 0x03,
+    LocalLinkTechnologyIndependent = 0x04,
+    Bluetooth = 0x05,
+    IrDA = 0x06,
+    Rs232 = 0x07,
+    UtranPacketServiceWithExtendedParameters = 0x09,
+    EUtranOrMappedUtranPacketService = 0x0b,
+}
+
+const BEARER_TYPES: [BearerType; 9] = {
+    use BearerType::*;
+    [
+        Csd,
+        Gprs,
+        Default,
+        LocalLinkTechnologyIndependent,
+        Bluetooth,
+        IrDA,
+        Rs232,
+        UtranPacketServiceWithExtendedParameters,
+        EUtranOrMappedUtranPacketService,
+    ]
+};
+
+/// BearerDescription: the bearer type and its parameters; ref 8.52 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct BearerDescription {
+    bearer_type: u8,
+    parameters: Vec<u8>,
+}
+
+/// ChannelData: ref 8.53 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelData {
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SimpleTlvError {
+    #[error("invalid length of the data object '{0:02X}': {1} bytes")]
+    InvalidLength(u16, usize),
+    #[error("invalid value of the data object '{0:02X}'")]
+    InvalidValue(u16),
+    #[error("unsupported data coding scheme: '{0:02X}'")]
+    UnsupportedDataCodingScheme(u8),
+    #[error("invalid UCS2 text")]
+    InvalidUcs2Text,
+    #[error("character '{0}' cannot be coded in the GSM default alphabet")]
+    UnencodableCharacter(char),
+    #[error("character '{0}' cannot be coded as the dialling number")]
+    InvalidDiallingNumber(char),
+}
+
+/// Returns the value of the data object that must be at least the given length.
+pub(crate) fn value_at_least(tlv: &ComprehensionTlv, len: usize) -> Result<&[u8], SimpleTlvError> {
+    let value = tlv.get_value();
+    if value.len() < len {
+        return Err(SimpleTlvError::InvalidLength(tlv.get_tag(), value.len()));
+    }
+    Ok(value)
+}
+
+/// Decodes the MCC of the BCD-coded PLMN identity; ref 10.5.1.3 / 3GPP TS 24.008
+pub(crate) fn decode_mcc(plmn: &[u8; 3]) -> String {
+    [plmn[0] & 0x0f, plmn[0] >> 4, plmn[1] & 0x0f]
+        .iter()
+        .map(|d| (b'0' + d) as char)
+        .collect()
+}
+
+/// Decodes the MNC of the BCD-coded PLMN identity; the third digit is 'F' for the 2 digit MNC.
+pub(crate) fn decode_mnc(plmn: &[u8; 3]) -> String {
+    [plmn[2] & 0x0f, plmn[2] >> 4, plmn[1] >> 4]
+        .iter()
+        .filter(|d| **d != 0xf)
+        .map(|d| (b'0' + d) as char)
+        .collect()
+}
+
+/// Decodes the unpacked GSM default alphabet.
+fn decode_gsm_septets(septets: &[u8]) -> String {
+    let mut text = String::new();
+    let mut escaped = false;
+    for septet in septets.iter().map(|b| b & 0x7f) {
+        if escaped {
+            escaped = false;
+            // an unknown extension is displayed as the character of the default alphabet
+            let c = GSM_EXTENSION_TABLE
+                .iter()
+                .find(|(code, _)| *code == septet)
+                .map(|(_, c)| *c)
+                .unwrap_or(GSM_DEFAULT_ALPHABET[septet as usize]);
+            text.push(c);
+        } else if septet == GSM_ESCAPE {
+            escaped = true;
+        } else {
+            text.push(GSM_DEFAULT_ALPHABET[septet as usize]);
+        }
+    }
+    text
+}
+
+/// Encodes the text in the unpacked GSM default alphabet.
+fn encode_gsm_septets(text: &str) -> Result<Vec<u8>, SimpleTlvError> {
+    let mut septets = Vec::new();
+    for c in text.chars() {
+        if c != '\u{1b}' {
+            if let Some(code) = GSM_DEFAULT_ALPHABET.iter().position(|d| *d == c) {
+                septets.push(code as u8);
+                continue;
+            }
+        }
+        match GSM_EXTENSION_TABLE.iter().find(|(_, e)| *e == c) {
+            Some((code, _)) => septets.extend([GSM_ESCAPE, *code]),
+            None => return Err(SimpleTlvError::UnencodableCharacter(c)),
+        }
+    }
+    Ok(septets)
+}
+
+/// Unpacks the septets that are packed in 7 bits; ref 6.1.2.1 / 3GPP TS 23.038
+///
+/// The carriage return that pads the last 7 bits of the octets is removed.
+fn unpack_septets(bytes: &[u8]) -> Vec<u8> {
+    let count = bytes.len() * 8 / 7;
+    let mut septets = Vec::with_capacity(count);
+    for i in 0..count {
+        let bit = i * 7;
+        let (index, shift) = (bit / 8, bit % 8);
+        let mut septet = bytes[index] >> shift;
+        if shift > 1 {
+            septet |= bytes[index + 1] << (8 - shift);
+        }
+        septets.push(septet & 0x7f);
+    }
+    if bytes.len().is_multiple_of(7) && septets.last() == Some(&GSM_CARRIAGE_RETURN) {
+        septets.pop();
+    }
+    septets
+}
+
+/// Packs the septets in 7 bits; the carriage return pads the last 7 bits of the octets so that they are
+/// not taken for '@'.
+fn pack_septets(septets: &[u8]) -> Vec<u8> {
+    let mut septets = septets.to_vec();
+    if septets.len() % 8 == 7 {
+        septets.push(GSM_CARRIAGE_RETURN);
+    }
+    let mut bytes = vec![0u8; (septets.len() * 7).div_ceil(8)];
+    for (i, septet) in septets.iter().enumerate() {
+        let bit = i * 7;
+        let (index, shift) = (bit / 8, bit % 8);
+        bytes[index] |= septet << shift;
+        if shift > 1 {
+            bytes[index + 1] |= septet >> (8 - shift);
+        }
+    }
+    bytes
+}
+
+fn decode_ucs2(bytes: &[u8]) -> Result<String, SimpleTlvError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(SimpleTlvError::InvalidUcs2Text);
+    }
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16(&units).map_err(|_| SimpleTlvError::InvalidUcs2Text)
+}
+
+fn encode_ucs2(text: &str) -> Vec<u8> {
+    text.encode_utf16()
+        .flat_map(|unit| unit.to_be_bytes())
+        .collect()
+}
+
+/// Decodes the alpha identifier in the GSM default alphabet or in one of the UCS2 formats; ref Annex A /
+/// ETSI TS 102 221 V15.0.0
+///
+/// The trailing 'FF' bytes are the padding and are ignored.
+pub fn decode_alpha_identifier(bytes: &[u8]) -> Result<String, SimpleTlvError> {
+    let ucs2_with_base = |count: usize, base: u16, chars: &[u8]| {
+        if chars.len() < count {
+            return Err(SimpleTlvError::InvalidUcs2Text);
+        }
+        let mut units = Vec::new();
+        for c in &chars[..count] {
+            if c & 0x80 == 0 {
+                units.extend(decode_gsm_septets(&[*c]).encode_utf16());
+            } else {
+                units.push(base.wrapping_add((c & 0x7f) as u16));
+            }
+        }
+        String::from_utf16(&units).map_err(|_| SimpleTlvError::InvalidUcs2Text)
+    };
+
+    match bytes.first() {
+        Some(0x80) => {
+            let mut ucs2 = &bytes[1..];
+            while ucs2.len() >= 2 && ucs2[ucs2.len() - 2..] == [0xff, 0xff] {
+                ucs2 = &ucs2[..ucs2.len() - 2];
+            }
+            // the odd byte that remains is the padding
+            decode_ucs2(&ucs2[..ucs2.len() - ucs2.len() % 2])
+        }
+        Some(0x81) if bytes.len() >= 3 => {
+            ucs2_with_base(bytes[1] as usize, (bytes[2] as u16) << 7, &bytes[3..])
+        }
+        Some(0x82) if bytes.len() >= 4 => ucs2_with_base(
+            bytes[1] as usize,
+            u16::from_be_bytes([bytes[2], bytes[3]]),
+            &bytes[4..],
+        ),
+        Some(0x81 | 0x82) => Err(SimpleTlvError::InvalidUcs2Text),
+        _ => {
+            let end = bytes
+                .iter()
+                .rposition(|b| *b != 0xff)
+                .map_or(0, |last| last + 1);
+            Ok(decode_gsm_septets(&bytes[..end]))
+        }
+    }
+}
+
+/// Encodes the alpha identifier in the GSM default alphabet, or in the UCS2 format '80' when the text has
+/// characters that the alphabet does not have.
+pub fn encode_alpha_identifier(text: &str) -> Vec<u8> {
+    match encode_gsm_septets(text) {
+        Ok(septets) => septets,
+        Err(_) => {
+            let mut bytes = Vec::from([0x80]);
+            bytes.extend(encode_ucs2(text));
+            bytes
+        }
+    }
+}
+
+impl TextCoding {
+    /// Returns the alphabet of the data coding scheme of the general data coding or of the message class;
+    /// `None` for the other coding groups and the reserved alphabet.
+    pub fn from_data_coding_scheme(dcs: u8) -> Option<TextCoding> {
+        match dcs & 0xc0 {
+            0x00 | 0x40 => match dcs & 0x0c {
+                0x00 => Some(TextCoding::Gsm7Bit),
+                0x04 => Some(TextCoding::Gsm8Bit),
+                0x08 => Some(TextCoding::Ucs2),
+                _ => None,
+            },
+            _ if dcs & 0xf0 == 0xf0 => match dcs & 0x04 {
+                0x00 => Some(TextCoding::Gsm7Bit),
+                _ => Some(TextCoding::Gsm8Bit),
+            },
+            _ => None,
+        }
+    }
+
+    pub fn get_data_coding_scheme(&self) -> u8 {
+        match self {
+            TextCoding::Gsm7Bit => 0x00,
+            TextCoding::Gsm8Bit => 0x04,
+            TextCoding::Ucs2 => 0x08,
+        }
+    }
+}
+
+pub fn new_alpha_identifier(text: &str) -> AlphaIdentifier {
+    AlphaIdentifier {
+        text: encode_alpha_identifier(text),
+    }
+}
+
+/// Parses the alpha identifier; the empty value is the null alpha identifier.
+pub fn parse_alpha_identifier(tlv: &ComprehensionTlv) -> AlphaIdentifier {
+    AlphaIdentifier {
+        text: tlv.get_value().to_vec(),
+    }
+}
+
+/// Creates a text string that has the text in the coding.
+pub fn new_text_string(coding: TextCoding, text: &str) -> Result<TextString, SimpleTlvError> {
+    let text = match coding {
+        TextCoding::Gsm7Bit => pack_septets(&encode_gsm_septets(text)?),
+        TextCoding::Gsm8Bit => encode_gsm_septets(text)?,
+        TextCoding::Ucs2 => encode_ucs2(text),
+    };
+    Ok(TextString {
+        data_coding_scheme: Some(coding.get_data_coding_scheme()),
+        text,
+    })
+}
+
+pub fn new_null_text_string() -> TextString {
+    TextString {
+        data_coding_scheme: None,
+        text: Vec::new(),
+    }
+}
+
+/// Parses the text string or the default text; the empty value is the null text string.
+pub fn parse_text_string(tlv: &ComprehensionTlv) -> TextString {
+    match tlv.get_value().split_first() {
+        Some((dcs, text)) => TextString {
+            data_coding_scheme: Some(*dcs),
+            text: text.to_vec(),
+        },
+        None => new_null_text_string(),
+    }
+}
+
+pub fn new_item(identifier: u8, text: &str) -> Item {
+    Item {
+        identifier,
+        text: encode_alpha_identifier(text),
+    }
+}
+
+/// Parses the item; `None` for the null item, which has no identifier.
+pub fn parse_item(tlv: &ComprehensionTlv) -> Option<Item> {
+    tlv.get_value()
+        .split_first()
+        .map(|(identifier, text)| Item {
+            identifier: *identifier,
+            text: text.to_vec(),
+        })
+}
+
+pub fn parse_item_identifier(tlv: &ComprehensionTlv) -> Result<u8, SimpleTlvError> {
+    Ok(value_at_least(tlv, 1)?[0])
+}
+
+pub fn new_duration(unit: TimeUnit, interval: u8) -> Duration {
+    Duration { unit, interval }
+}
+
+pub fn parse_duration(tlv: &ComprehensionTlv) -> Result<Duration, SimpleTlvError> {
+    let value = value_at_least(tlv, 2)?;
+    let unit = match value[0] {
+        0x00 => TimeUnit::Minutes,
+        0x01 => TimeUnit::Seconds,
+        0x02 => TimeUnit::TenthsOfSeconds,
+        _ => return Err(SimpleTlvError::InvalidValue(tlv.get_tag())),
+    };
+    Ok(Duration {
+        unit,
+        interval: value[1],
+    })
+}
+
+pub fn new_address(ton_npi: u8, dialling_number: &str) -> Result<Address, SimpleTlvError> {
+    for c in dialling_number.chars() {
+        if !matches!(c, '0'..='9' | '*' | '#' | 'p' | '?') {
+            return Err(SimpleTlvError::InvalidDiallingNumber(c));
+        }
+    }
+    Ok(Address {
+        ton_npi,
+        dialling_number: dialling_number.to_string(),
+    })
+}
+
+pub fn parse_address(tlv: &ComprehensionTlv) -> Result<Address, SimpleTlvError> {
+    let value = value_at_least(tlv, 1)?;
+    let mut dialling_number = String::new();
+    for digit in value[1..].iter().flat_map(|b| [b & 0x0f, b >> 4]) {
+        let c = match digit {
+            0x0..=0x9 => (b'0' + digit) as char,
+            0xa => '*',
+            0xb => '#',
+            0xc => 'p',
+            0xd => '?',
+            0xf => break,
+            _ => return Err(SimpleTlvError::InvalidValue(tlv.get_tag())),
+        };
+        dialling_number.push(c);
+    }
+    Ok(Address {
+        ton_npi: value[0],
+        dialling_number,
+    })
+}
+
+pub fn new_location_information(
+    mcc_mnc: [u8; 3],
+    location_area_code: u16,
+    cell_identity: &[u8],
+) -> LocationInformation {
+    LocationInformation {
+        mcc_mnc,
+        location_area_code,
+        cell_identity: cell_identity.to_vec(),
+    }
+}
+
+pub fn parse_location_information(
+    tlv: &ComprehensionTlv,
+) -> Result<LocationInformation, SimpleTlvError> {
+    let value = value_at_least(tlv, 5)?;
+    Ok(LocationInformation {
+        mcc_mnc: [value[0], value[1], value[2]],
+        location_area_code: u16::from_be_bytes([value[3], value[4]]),
+        cell_identity: value[5..].to_vec(),
+    })
+}
+
+pub fn new_channel_status(
+    channel_identifier: u8,
+    link_established: bool,
+    further_information: u8,
+) -> ChannelStatus {
+    ChannelStatus {
+        channel_identifier: channel_identifier & 0x07,
+        link_established,
+        further_information,
+    }
+}
+
+pub fn parse_channel_status(tlv: &ComprehensionTlv) -> Result<ChannelStatus, SimpleTlvError> {
+    let value = value_at_least(tlv, 2)?;
+    Ok(ChannelStatus {
+        channel_identifier: value[0] & 0x07,
+        link_established: value[0] & 0x80 != 0,
+        further_information: value[1],
+    })
+}
+
+pub fn new_bearer_description(bearer_type: BearerType, parameters: &[u8]) -> BearerDescription {
+    BearerDescription {
+        bearer_type: bearer_type as u8,
+        parameters: parameters.to_vec(),
+    }
+}
+
+pub fn parse_bearer_description(
+    tlv: &ComprehensionTlv,
+) -> Result<BearerDescription, SimpleTlvError> {
+    let value = value_at_least(tlv, 1)?;
+    Ok(BearerDescription {
+        bearer_type: value[0],
+        parameters: value[1..].to_vec(),
+    })
+}
+
+pub fn new_channel_data(data: &[u8]) -> ChannelData {
+    ChannelData {
+        data: data.to_vec(),
+    }
+}
+
+pub fn parse_channel_data(tlv: &ComprehensionTlv) -> ChannelData {
+    new_channel_data(tlv.get_value())
+}
+
+impl AlphaIdentifier {
+    pub fn get_text(&self) -> &[u8] {
+        &self.text
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn decode(&self) -> Result<String, SimpleTlvError> {
+        decode_alpha_identifier(&self.text)
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        new_comprehension_tlv(ALPHA_IDENTIFIER_TAG, false, self.text.clone())
+    }
+}
+
+impl TextString {
+    pub fn get_data_coding_scheme(&self) -> Option<u8> {
+        self.data_coding_scheme
+    }
+
+    pub fn get_text(&self) -> &[u8] {
+        &self.text
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.data_coding_scheme.is_none()
+    }
+
+    /// Returns the coding of the text; `None` for the null text string and the unsupported data coding
+    /// schemes.
+    pub fn get_coding(&self) -> Option<TextCoding> {
+        self.data_coding_scheme
+            .and_then(TextCoding::from_data_coding_scheme)
+    }
+
+    /// Decodes the text; the null text string is decoded as the empty string.
+    pub fn decode(&self) -> Result<String, SimpleTlvError> {
+        let dcs = match self.data_coding_scheme {
+            Some(dcs) => dcs,
+            None => return Ok(String::new()),
+        };
+        match TextCoding::from_data_coding_scheme(dcs) {
+            Some(TextCoding::Gsm7Bit) => Ok(decode_gsm_septets(&unpack_septets(&self.text))),
+            Some(TextCoding::Gsm8Bit) => Ok(decode_gsm_septets(&self.text)),
+            Some(TextCoding::Ucs2) => decode_ucs2(&self.text),
+            None => Err(SimpleTlvError::UnsupportedDataCodingScheme(dcs)),
+        }
+    }
+
+    /// Returns the data object of the tag, i.e. the text string or the default text.
+    pub fn to_comprehension_tlv(&self, tag: u16) -> ComprehensionTlv {
+        let mut value = Vec::new();
+        if let Some(dcs) = self.data_coding_scheme {
+            value.push(dcs);
+            value.extend_from_slice(&self.text);
+        }
+        new_comprehension_tlv(tag, true, value)
+    }
+}
+
+impl Item {
+    pub fn get_identifier(&self) -> u8 {
+        self.identifier
+    }
+
+    pub fn get_text(&self) -> &[u8] {
+        &self.text
+    }
+
+    pub fn decode_text(&self) -> Result<String, SimpleTlvError> {
+        decode_alpha_identifier(&self.text)
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        let mut value = Vec::from([self.identifier]);
+        value.extend_from_slice(&self.text);
+        new_comprehension_tlv(ITEM_TAG, false, value)
+    }
+}
+
+impl Duration {
+    pub fn get_unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    pub fn get_interval(&self) -> u8 {
+        self.interval
+    }
+
+    pub fn to_std_duration(&self) -> std::time::Duration {
+        let interval = self.interval as u64;
+        match self.unit {
+            TimeUnit::Minutes => std::time::Duration::from_secs(interval * 60),
+            TimeUnit::Seconds => std::time::Duration::from_secs(interval),
+            TimeUnit::TenthsOfSeconds => std::time::Duration::from_millis(interval * 100),
+        }
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        new_comprehension_tlv(
+            DURATION_TAG,
+            true,
+            Vec::from([self.unit as u8, self.interval]),
+        )
+    }
+}
+
+impl Address {
+    pub fn get_ton_npi(&self) -> u8 {
+        self.ton_npi
+    }
+
+    pub fn get_dialling_number(&self) -> &str {
+        &self.dialling_number
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        let digits: Vec<u8> = self
+            .dialling_number
+            .chars()
+            .map(|c| match c {
+                '*' => 0xa,
+                '#' => 0xb,
+                'p' => 0xc,
+                '?' => 0xd,
+                c => c as u8 - b'0',
+            })
+            .collect();
+        let mut value = Vec::from([self.ton_npi]);
+        value.extend(
+            digits
+                .chunks(2)
+                .map(|pair| pair[0] | pair.get(1).copied().unwrap_or(0xf) << 4),
+        );
+        new_comprehension_tlv(ADDRESS_TAG, true, value)
+    }
+}
+
+impl LocationInformation {
+    pub fn get_mcc(&self) -> String {
+        decode_mcc(&self.mcc_mnc)
+    }
+
+    /// Returns the MNC of the 2 or 3 digits.
+    pub fn get_mnc(&self) -> String {
+        decode_mnc(&self.mcc_mnc)
+    }
+
+    pub fn get_location_area_code(&self) -> u16 {
+        self.location_area_code
+    }
+
+    pub fn get_cell_identity(&self) -> &[u8] {
+        &self.cell_identity
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        let mut value = self.mcc_mnc.to_vec();
+        value.extend(self.location_area_code.to_be_bytes());
+        value.extend_from_slice(&self.cell_identity);
+        new_comprehension_tlv(LOCATION_INFORMATION_TAG, true, value)
+    }
+}
+
+impl ChannelStatus {
+    pub fn get_channel_identifier(&self) -> u8 {
+        self.channel_identifier
+    }
+
+    pub fn is_link_established(&self) -> bool {
+        self.link_established
+    }
+
+    pub fn get_further_information(&self) -> u8 {
+        self.further_information
+    }
+
+    pub fn to_bytes(&self) -> [u8; 2] {
+        let link = if self.link_established { 0x80 } else { 0x00 };
+        [link | self.channel_identifier, self.further_information]
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        new_comprehension_tlv(CHANNEL_STATUS_TAG, true, self.to_bytes().to_vec())
+    }
+}
+
+impl BearerType {
+    pub fn from_byte(b: u8) -> Option<BearerType> {
+        BEARER_TYPES.iter().copied().find(|t| *t as u8 == b)
+    }
+}
+
+impl BearerDescription {
+    /// Returns the bearer type; `None` for the types that are not known.
+    pub fn get_bearer_type(&self) -> Option<BearerType> {
+        BearerType::from_byte(self.bearer_type)
+    }
+
+    pub fn get_bearer_type_byte(&self) -> u8 {
+        self.bearer_type
+    }
+
+    pub fn get_parameters(&self) -> &[u8] {
+        &self.parameters
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        let mut value = Vec::from([self.bearer_type]);
+        value.extend_from_slice(&self.parameters);
+        new_comprehension_tlv(BEARER_DESCRIPTION_TAG, true, value)
+    }
+}
+
+impl ChannelData {
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn to_comprehension_tlv(&self) -> ComprehensionTlv {
+        new_comprehension_tlv(CHANNEL_DATA_TAG, true, self.data.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::comprehension_tlv::{
+        new_comprehension_tlv, ADDRESS_TAG, ALPHA_IDENTIFIER_TAG, TEXT_STRING_TAG,
+    };
+    use crate::simple_tlv::{
+        decode_alpha_identifier, encode_alpha_identifier, new_address, new_alpha_identifier,
+        new_location_information, new_text_string, parse_address, parse_alpha_identifier,
+        parse_location_information, parse_text_string, SimpleTlvError, TextCoding,
+    };
+
+    #[test]
+    fn should_code_text_strings() {
+        let text = new_text_string(TextCoding::Gsm7Bit, "hellohello").unwrap();
+        assert_eq!(
+            text.get_text(),
+            &[0xe8, 0x32, 0x9b, 0xfd, 0x46, 0x97, 0xd9, 0xec, 0x37]
+        );
+        assert_eq!(text.decode().unwrap(), "hellohello");
+
+        // the 7 spare bits are padded with the carriage return
+        let text = new_text_string(TextCoding::Gsm7Bit, "1234567").unwrap();
+        assert_eq!(text.get_text().len(), 7);
+        assert_eq!(text.get_text()[6], 0x1a);
+        assert_eq!(text.decode().unwrap(), "1234567");
+
+        let tlv = new_text_string(TextCoding::Gsm8Bit, "{€}")
+            .unwrap()
+            .to_comprehension_tlv(TEXT_STRING_TAG);
+        assert_eq!(
+            tlv.to_bytes(),
+            Vec::from([0x8d, 0x07, 0x04, 0x1b, 0x28, 0x1b, 0x65, 0x1b, 0x29])
+        );
+        assert_eq!(parse_text_string(&tlv).decode().unwrap(), "{€}");
+
+        let text = new_text_string(TextCoding::Ucs2, "Привет").unwrap();
+        assert_eq!(text.get_data_coding_scheme(), Some(0x08));
+        assert_eq!(text.get_text()[..2], [0x04, 0x1f]);
+        assert_eq!(text.decode().unwrap(), "Привет");
+
+        assert_eq!(
+            new_text_string(TextCoding::Gsm8Bit, "Привет").unwrap_err(),
+            SimpleTlvError::UnencodableCharacter('П')
+        );
+        assert_eq!(
+            parse_text_string(&new_comprehension_tlv(
+                TEXT_STRING_TAG,
+                true,
+                vec![0xc4, 0x41]
+            ))
+            .decode()
+            .unwrap_err(),
+            SimpleTlvError::UnsupportedDataCodingScheme(0xc4)
+        );
+    }
+
+    #[test]
+    fn should_code_alpha_identifiers() {
+        assert_eq!(
+            decode_alpha_identifier(&[0x4d, 0x65, 0x6e, 0x75, 0xff, 0xff]).unwrap(),
+            "Menu"
+        );
+        assert_eq!(
+            decode_alpha_identifier(&[0x80, 0x04, 0x1f, 0x04, 0x40, 0xff, 0xff]).unwrap(),
+            "Пр"
+        );
+        assert_eq!(
+            decode_alpha_identifier(&[0x81, 0x03, 0x08, 0x9f, 0xc0, 0x41]).unwrap(),
+            "ПрA"
+        );
+        assert_eq!(
+            decode_alpha_identifier(&[0x82, 0x02, 0x04, 0x00, 0x9f, 0x42]).unwrap(),
+            "ПB"
+        );
+        assert_eq!(encode_alpha_identifier("Menu"), b"Menu");
+        assert_eq!(encode_alpha_identifier("П"), &[0x80, 0x04, 0x1f]);
+
+        let tlv = new_alpha_identifier("Menu").to_comprehension_tlv();
+        assert_eq!(
+            tlv.to_bytes(),
+            Vec::from([0x05, 0x04, 0x4d, 0x65, 0x6e, 0x75])
+        );
+        assert_eq!(parse_alpha_identifier(&tlv).decode().unwrap(), "Menu");
+        assert!(parse_alpha_identifier(&new_comprehension_tlv(
+            ALPHA_IDENTIFIER_TAG,
+            false,
+            vec![]
+        ))
+        .is_null());
+    }
+
+    #[test]
+    fn should_code_address_and_location_information() {
+        let tlv = new_comprehension_tlv(ADDRESS_TAG, false, vec![0x91, 0x21, 0x43, 0xf5]);
+        let address = parse_address(&tlv).unwrap();
+        assert_eq!(address.get_ton_npi(), 0x91);
+        assert_eq!(address.get_dialling_number(), "12345");
+        assert_eq!(
+            new_address(0x91, "12345")
+                .unwrap()
+                .to_comprehension_tlv()
+                .get_value(),
+            tlv.get_value()
+        );
+        assert_eq!(
+            new_address(0x81, "12a").unwrap_err(),
+            SimpleTlvError::InvalidDiallingNumber('a')
+        );
+
+        let tlv = new_comprehension_tlv(0x13, true, vec![0x32, 0xf4, 0x01, 0x12, 0x34, 0x00, 0x56]);
+        let location = parse_location_information(&tlv).unwrap();
+        assert_eq!(
+            new_location_information([0x32, 0xf4, 0x01], 0x1234, &[0x00, 0x56]),
+            location
+        );
+        assert_eq!(location.to_comprehension_tlv(), tlv);
+        assert_eq!(location.get_mcc(), "234");
+        assert_eq!(location.get_mnc(), "10");
+        assert_eq!(location.get_location_area_code(), 0x1234);
+        assert_eq!(location.get_cell_identity(), &[0x00, 0x56]);
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{parse_ber_tlv, BerTlvError};
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::fcp::{parse_fcp, FcpError, FileControlParameters};
+use crate::instruction::Status;
+use crate::response_apdu::ResponseAPDU;
+
+const DF_NAME_TAG: u32 = 0x84;
+
+/// StatusIndication: the application status indication of STATUS; ref 11.1.2.2 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum StatusIndication {
+    /// No indication (P1 = '00')
+    NoIndication = 0x00,
+    /// Current application is initialized in the terminal (P1 = '01')
+    ApplicationInitialized = 0x01,
+    /// The terminal will initiate the termination of the current application (P1 = '02')
+    ApplicationTerminating = 0x02,
+}
+
+/// StatusResponse: the data that is requested by STATUS; ref 11.1.2.2 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusResponse {
+    /// Response parameters and data are identical to the response of SELECT (P2 = '00')
+    FCP,
+    /// The DF name TLV-object of the currently selected application is returned (P2 = '01')
+    DFName,
+    /// No data returned (P2 = '0C')
+    NoData,
+}
+
+/// StatusCommand: ref 11.1.2 / ETSI TS 102 221 V15.0.0
+pub struct StatusCommand {
+    response: StatusResponse,
+    p1: u8,
+    p2: u8,
+    le: Option<u8>,
+}
+
+/// StatusData: the response data of STATUS that is parsed according to P2.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusData {
+    FCP(FileControlParameters),
+    DFName(Vec<u8>),
+    NoData,
+}
+
+/// StatusResult: the parsed response of STATUS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusResult {
+    data: StatusData,
+    pending_proactive_command_length: Option<u8>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum StatusError {
+    #[error("STATUS has not been executed successfully; status word is '{0:04X}'")]
+    UnsuccessfulStatusWord(u16),
+    #[error("invalid FCP in the response of STATUS: {0}")]
+    Fcp(#[from] FcpError),
+    #[error("invalid BER-TLV in the response of STATUS: {0}")]
+    InvalidBerTlv(#[from] BerTlvError),
+    #[error("unexpected tag of the DF name TLV-object; this must be '84' but '{0:X}'")]
+    UnexpectedDFNameTag(u32),
+}
+
+pub fn new_status_command(indication: StatusIndication, response: StatusResponse) -> StatusCommand {
+    let (p2, le) = match response {
+        StatusResponse::FCP => (0x00, Some(0x00)),
+        StatusResponse::DFName => (0x01, Some(0x00)),
+        StatusResponse::NoData => (0x0c, None),
+    };
+
+    StatusCommand {
+        response,
+        p1: indication as u8,
+        p2,
+        le,
+    }
+}
+
+impl StatusCommand {
+    pub fn get_p1(&self) -> u8 {
+        self.p1
+    }
+
+    pub fn get_p2(&self) -> u8 {
+        self.p2
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(class, &Status {}, self.p1, self.p2, self.le, None)
+    }
+
+    /// Parses the response of this command. The status word of '91XX' is accepted and its length of the
+    /// pending proactive command is kept, so that STATUS can be used as the poll in the proactive session.
+    pub fn parse_response(&self, response: &ResponseAPDU) -> Result<StatusResult, StatusError> {
+        if !matches!(response.get_sw1(), 0x90 | 0x91) {
+            return Err(StatusError::UnsuccessfulStatusWord(
+                response.get_status_word(),
+            ));
+        }
+
+        let data = match self.response {
+            StatusResponse::FCP => StatusData::FCP(parse_fcp(response.get_data())?),
+            StatusResponse::DFName => {
+                let (tlv, _) = parse_ber_tlv(response.get_data())?;
+                if tlv.get_tag() != DF_NAME_TAG {
+                    return Err(StatusError::UnexpectedDFNameTag(tlv.get_tag()));
+                }
+                StatusData::DFName(tlv.get_value().to_vec())
+            }
+            StatusResponse::NoData => StatusData::NoData,
+        };
+
+        Ok(StatusResult {
+            data,
+            pending_proactive_command_length: response.get_pending_proactive_command_length(),
+        })
+    }
+}
+
+impl StatusResult {
+    pub fn get_data(&self) -> &StatusData {
+        &self.data
+    }
+
+    /// Returns the length of the proactive command that is pending by '91XX'.
+    pub fn get_pending_proactive_command_length(&self) -> Option<u8> {
+        self.pending_proactive_command_length
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::response_apdu::new_response_apdu;
+    use crate::status::{
+        new_status_command, StatusData, StatusError, StatusIndication, StatusResponse,
+    };
+
+    #[test]
+    fn should_construct_status_command() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let command = new_status_command(StatusIndication::NoIndication, StatusResponse::FCP);
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x80, 0xf2, 0x00, 0x00, 0x00])
+        );
+        let command = new_status_command(
+            StatusIndication::ApplicationTerminating,
+            StatusResponse::NoData,
+        );
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x80, 0xf2, 0x02, 0x0c])
+        );
+    }
+
+    #[test]
+    fn should_parse_status_response() {
+        let command = new_status_command(
+            StatusIndication::ApplicationInitialized,
+            StatusResponse::DFName,
+        );
+        let result = command
+            .parse_response(&new_response_apdu(
+                Vec::from([0x84, 0x03, 0xa0, 0x00, 0x00]),
+                0x90,
+                0x00,
+            ))
+            .unwrap();
+        assert_eq!(
+            result.get_data(),
+            &StatusData::DFName(Vec::from([0xa0, 0x00, 0x00]))
+        );
+        assert_eq!(result.get_pending_proactive_command_length(), None);
+
+        let command = new_status_command(StatusIndication::NoIndication, StatusResponse::FCP);
+        let result = command
+            .parse_response(&new_response_apdu(
+                Vec::from([0x62, 0x08, 0x82, 0x02, 0x78, 0x21, 0x83, 0x02, 0x3f, 0x00]),
+                0x91,
+                0x1a,
+            ))
+            .unwrap();
+        match result.get_data() {
+            StatusData::FCP(fcp) => assert!(fcp.get_file_id().unwrap().is_master_file()),
+            data => panic!("unexpected data: {:?}", data),
+        }
+        assert_eq!(result.get_pending_proactive_command_length(), Some(0x1a));
+
+        let command = new_status_command(StatusIndication::NoIndication, StatusResponse::NoData);
+        let result = command
+            .parse_response(&new_response_apdu(Vec::new(), 0x91, 0x1a))
+            .unwrap();
+        assert_eq!(result.get_data(), &StatusData::NoData);
+        assert_eq!(result.get_pending_proactive_command_length(), Some(0x1a));
+
+        assert_eq!(
+            command
+                .parse_response(&new_response_apdu(Vec::new(), 0x6f, 0x00))
+                .unwrap_err(),
+            StatusError::UnsuccessfulStatusWord(0x6f00)
+        );
+    }
+}
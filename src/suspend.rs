@@ -0,0 +1,375 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::class::{new_basic_class, Class, ClassTypeForStandardLogicalChannels};
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::hex::{hex_array, hex_bytes_list};
+use crate::instruction::SuspendUICC;
+use crate::response_apdu::ResponseAPDU;
+use crate::session::{Session, SessionError};
+
+const RESUME_TOKEN_LENGTH: usize = 8;
+
+/// SuspendTimeUnit: the time unit of the suspension duration; ref 11.1.22.2 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum SuspendTimeUnit {
+    Seconds = 0x00,
+    Minutes = 0x01,
+    Hours = 0x02,
+    Days = 0x03,
+    TenDays = 0x04,
+}
+
+/// SuspendDuration: the time unit and the length of time; ref 11.1.22.2 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SuspendDuration {
+    unit: SuspendTimeUnit,
+    length: u8,
+}
+
+/// SuspendCommand: SUSPEND UICC with the minimum and the maximum duration of the suspension; ref 11.1.22 /
+/// ETSI TS 102 221 V15.0.0
+pub struct SuspendCommand {
+    data: [u8; 4],
+}
+
+/// SuspendResponse: the maximum duration that the UICC accepts and the token to resume it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuspendResponse {
+    maximum_duration: SuspendDuration,
+    resume_token: [u8; RESUME_TOKEN_LENGTH],
+}
+
+/// ResumeCommand: SUSPEND UICC to resume the UICC with the token; ref 11.1.22 / ETSI TS 102 221 V15.0.0
+pub struct ResumeCommand {
+    resume_token: [u8; RESUME_TOKEN_LENGTH],
+}
+
+/// SuspendedUicc holds what the terminal needs to resume the suspended UICC. It can be written in JSON
+/// before the terminal powers down, and read after the power is back.
+///
+/// The commands to replay are transmitted after the successful RESUME in order, e.g. to restore the state
+/// of the terminal application that the UICC does not keep.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuspendedUicc {
+    #[serde(with = "hex_array")]
+    resume_token: [u8; RESUME_TOKEN_LENGTH],
+    maximum_duration: SuspendDuration,
+    suspended_at: SystemTime,
+    #[serde(default, with = "hex_bytes_list")]
+    replay_commands: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SuspendError {
+    #[error("{0}")]
+    Session(#[from] SessionError),
+    #[error("unsuccessful status word of SUSPEND UICC: '{0:04X}'")]
+    UnsuccessfulStatusWord(u16),
+    #[error("invalid length of the response of SUSPEND UICC; this must be 10 bytes but {0} bytes")]
+    InvalidResponseLength(usize),
+    #[error("invalid time unit of the suspension duration: '{0:02X}'")]
+    InvalidTimeUnit(u8),
+    #[error("invalid suspended UICC: {0}")]
+    InvalidSuspendedUicc(String),
+}
+
+pub fn new_suspend_duration(unit: SuspendTimeUnit, length: u8) -> SuspendDuration {
+    SuspendDuration { unit, length }
+}
+
+pub fn parse_suspend_duration(bytes: [u8; 2]) -> Result<SuspendDuration, SuspendError> {
+    let unit = match bytes[0] {
+        0x00 => SuspendTimeUnit::Seconds,
+        0x01 => SuspendTimeUnit::Minutes,
+        0x02 => SuspendTimeUnit::Hours,
+        0x03 => SuspendTimeUnit::Days,
+        0x04 => SuspendTimeUnit::TenDays,
+        b => return Err(SuspendError::InvalidTimeUnit(b)),
+    };
+    Ok(SuspendDuration {
+        unit,
+        length: bytes[1],
+    })
+}
+
+pub fn new_suspend_command(minimum: SuspendDuration, maximum: SuspendDuration) -> SuspendCommand {
+    let [min_unit, min_length] = minimum.to_bytes();
+    let [max_unit, max_length] = maximum.to_bytes();
+    SuspendCommand {
+        data: [min_unit, min_length, max_unit, max_length],
+    }
+}
+
+pub fn new_resume_command(resume_token: [u8; RESUME_TOKEN_LENGTH]) -> ResumeCommand {
+    ResumeCommand { resume_token }
+}
+
+/// Suspends the UICC and returns what is needed to resume it.
+pub fn suspend_uicc(
+    session: &mut Session,
+    minimum: SuspendDuration,
+    maximum: SuspendDuration,
+) -> Result<SuspendedUicc, SuspendError> {
+    let class = new_basic_class(ClassTypeForStandardLogicalChannels::TS102_221);
+    let command = new_suspend_command(minimum, maximum);
+    let response = command.parse_response(&session.transmit(&command.to_command_apdu(&class))?)?;
+    Ok(SuspendedUicc {
+        resume_token: response.resume_token,
+        maximum_duration: response.maximum_duration,
+        suspended_at: SystemTime::now(),
+        replay_commands: Vec::new(),
+    })
+}
+
+pub fn parse_suspended_uicc_json(json: &str) -> Result<SuspendedUicc, SuspendError> {
+    serde_json::from_str(json).map_err(|e| SuspendError::InvalidSuspendedUicc(e.to_string()))
+}
+
+impl SuspendDuration {
+    pub fn get_unit(&self) -> SuspendTimeUnit {
+        self.unit
+    }
+
+    pub fn get_length(&self) -> u8 {
+        self.length
+    }
+
+    pub fn to_bytes(&self) -> [u8; 2] {
+        [self.unit as u8, self.length]
+    }
+
+    pub fn to_std_duration(&self) -> Duration {
+        let seconds = match self.unit {
+            SuspendTimeUnit::Seconds => 1,
+            SuspendTimeUnit::Minutes => 60,
+            SuspendTimeUnit::Hours => 60 * 60,
+            SuspendTimeUnit::Days => 24 * 60 * 60,
+            SuspendTimeUnit::TenDays => 10 * 24 * 60 * 60,
+        };
+        Duration::from_secs(seconds * self.length as u64)
+    }
+}
+
+impl SuspendCommand {
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &SuspendUICC {},
+            0x00,
+            0x00,
+            Some(0x0a),
+            Some(&self.data),
+        )
+    }
+
+    pub fn parse_response(&self, response: &ResponseAPDU) -> Result<SuspendResponse, SuspendError> {
+        if !response.is_normal_ending() {
+            return Err(SuspendError::UnsuccessfulStatusWord(
+                response.get_status_word(),
+            ));
+        }
+        let data = response.get_data();
+        if data.len() != 2 + RESUME_TOKEN_LENGTH {
+            return Err(SuspendError::InvalidResponseLength(data.len()));
+        }
+        let mut resume_token = [0u8; RESUME_TOKEN_LENGTH];
+        resume_token.copy_from_slice(&data[2..]);
+        Ok(SuspendResponse {
+            maximum_duration: parse_suspend_duration([data[0], data[1]])?,
+            resume_token,
+        })
+    }
+}
+
+impl SuspendResponse {
+    pub fn get_maximum_duration(&self) -> SuspendDuration {
+        self.maximum_duration
+    }
+
+    pub fn get_resume_token(&self) -> &[u8; RESUME_TOKEN_LENGTH] {
+        &self.resume_token
+    }
+}
+
+impl ResumeCommand {
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &SuspendUICC {},
+            0x01,
+            0x00,
+            None,
+            Some(&self.resume_token),
+        )
+    }
+}
+
+impl SuspendedUicc {
+    pub fn get_resume_token(&self) -> &[u8; RESUME_TOKEN_LENGTH] {
+        &self.resume_token
+    }
+
+    pub fn get_maximum_duration(&self) -> SuspendDuration {
+        self.maximum_duration
+    }
+
+    pub fn get_replay_commands(&self) -> &[Vec<u8>] {
+        &self.replay_commands
+    }
+
+    /// Adds the command bytes to replay after the successful RESUME.
+    pub fn add_replay_command(&mut self, command: &[u8]) {
+        self.replay_commands.push(command.to_vec());
+    }
+
+    /// Returns whether the maximum duration has passed; the UICC may refuse RESUME after that.
+    pub fn is_expired(&self) -> bool {
+        self.suspended_at
+            .elapsed()
+            .map(|elapsed| elapsed > self.maximum_duration.to_std_duration())
+            .unwrap_or(false)
+    }
+
+    /// Resumes the UICC by the token, and then replays the commands. The responses to the replayed
+    /// commands are returned in order.
+    pub fn resume(&self, session: &mut Session) -> Result<Vec<ResponseAPDU>, SuspendError> {
+        let class = new_basic_class(ClassTypeForStandardLogicalChannels::TS102_221);
+        let response =
+            session.transmit(&new_resume_command(self.resume_token).to_command_apdu(&class))?;
+        if !response.is_normal_ending() {
+            return Err(SuspendError::UnsuccessfulStatusWord(
+                response.get_status_word(),
+            ));
+        }
+
+        let mut responses = Vec::new();
+        for command in &self.replay_commands {
+            responses.push(session.transmit_bytes(command)?);
+        }
+        Ok(responses)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::response_apdu::new_response_apdu;
+    use crate::session::{new_session, SessionConfig};
+    use crate::suspend::{
+        new_suspend_command, new_suspend_duration, parse_suspended_uicc_json, suspend_uicc,
+        SuspendError, SuspendTimeUnit, SuspendedUicc,
+    };
+    use crate::testing::new_scripted_card;
+
+    #[test]
+    fn should_construct_suspend_command_and_parse_response() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let command = new_suspend_command(
+            new_suspend_duration(SuspendTimeUnit::Minutes, 10),
+            new_suspend_duration(SuspendTimeUnit::Hours, 2),
+        );
+        assert_eq!(
+            command.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x80, 0x76, 0x00, 0x00, 0x04, 0x01, 0x0a, 0x02, 0x02, 0x0a])
+        );
+
+        let response = command
+            .parse_response(&new_response_apdu(
+                Vec::from([0x01, 0x1e, 1, 2, 3, 4, 5, 6, 7, 8]),
+                0x90,
+                0x00,
+            ))
+            .unwrap();
+        assert_eq!(
+            response.get_maximum_duration().to_std_duration(),
+            Duration::from_secs(30 * 60)
+        );
+        assert_eq!(response.get_resume_token(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        assert_eq!(
+            command
+                .parse_response(&new_response_apdu(Vec::new(), 0x6d, 0x00))
+                .unwrap_err(),
+            SuspendError::UnsuccessfulStatusWord(0x6d00)
+        );
+        assert_eq!(
+            command
+                .parse_response(&new_response_apdu(
+                    Vec::from([0x07, 0x1e, 1, 2, 3, 4, 5, 6, 7, 8]),
+                    0x90,
+                    0x00,
+                ))
+                .unwrap_err(),
+            SuspendError::InvalidTimeUnit(0x07)
+        );
+    }
+
+    #[test]
+    fn should_suspend_and_resume_after_power_cycle() {
+        let mut card = new_scripted_card(&[
+            &[0x02, 0x01, 1, 2, 3, 4, 5, 6, 7, 8, 0x90, 0x00],
+            &[0x90, 0x00],
+            &[0x90, 0x00],
+        ]);
+        let mut session = new_session(&mut card, SessionConfig::default());
+        let mut suspended = suspend_uicc(
+            &mut session,
+            new_suspend_duration(SuspendTimeUnit::Seconds, 30),
+            new_suspend_duration(SuspendTimeUnit::Hours, 1),
+        )
+        .unwrap();
+        suspended.add_replay_command(&[0x80, 0xf2, 0x01, 0x0c]);
+        assert!(!suspended.is_expired());
+
+        let restored = parse_suspended_uicc_json(&suspended.to_json().unwrap()).unwrap();
+        assert_eq!(restored, suspended);
+        let responses = restored.resume(&mut session).unwrap();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(
+            card.commands[1..],
+            [
+                Vec::from([0x80, 0x76, 0x01, 0x00, 0x08, 1, 2, 3, 4, 5, 6, 7, 8]),
+                Vec::from([0x80, 0xf2, 0x01, 0x0c])
+            ]
+        );
+    }
+
+    #[test]
+    fn should_fail_deserialize_resume_token_with_invalid_length() {
+        let json = r#"{
+  "resume_token": "01020304050607",
+  "maximum_duration": { "unit": "Hours", "length": 1 },
+  "suspended_at": { "secs_since_epoch": 1700000000, "nanos_since_epoch": 0 }
+}"#;
+        let error = serde_json::from_str::<SuspendedUicc>(json).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("this must be 8 bytes but 7 bytes"));
+        assert!(matches!(
+            parse_suspended_uicc_json(json).unwrap_err(),
+            SuspendError::InvalidSuspendedUicc(_)
+        ));
+        assert!(
+            parse_suspended_uicc_json(&json.replace("01020304050607", "0102030405060708")).is_ok()
+        );
+    }
+}
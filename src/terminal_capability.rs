@@ -0,0 +1,385 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{new_ber_tlv, parse_ber_tlv, BerTlv, BerTlvError};
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::TerminalCapability as TerminalCapabilityInstruction;
+
+const TERMINAL_CAPABILITY_TEMPLATE_TAG: u32 = 0xa9;
+const TERMINAL_POWER_SUPPLY_TAG: u32 = 0x80;
+const EXTENDED_LOGICAL_CHANNELS_TAG: u32 = 0x81;
+const ADDITIONAL_INTERFACES_TAG: u32 = 0x82;
+const EUICC_CAPABILITIES_TAG: u32 = 0x83;
+
+/// SupplyVoltageClass: the actual used supply voltage class; ref 11.1.19.2.1 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum SupplyVoltageClass {
+    A = 0b00000001,
+    B = 0b00000010,
+    C = 0b00000100,
+    D = 0b00001000,
+    E = 0b00010000,
+}
+
+/// TerminalPowerSupply: ref 11.1.19.2.1 / ETSI TS 102 221 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerminalPowerSupply {
+    voltage_class: SupplyVoltageClass,
+    maximum_available_power: u8,
+    actual_clock_frequency: u8,
+}
+
+/// EuiccCapability: the additional terminal capability indications related to eUICC in the first byte;
+/// ref 11.1.19.2.4 / ETSI TS 102 221 V15.0.0 and GSMA SGP.22
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum EuiccCapability {
+    LocalUserInterface = 0b00000001,
+    LocalProfileDownload = 0b00000010,
+    LocalDiscoveryService = 0b00000100,
+    LuieBasedOnScws = 0b00001000,
+    MetadataUpdateAlerting = 0b00010000,
+    EnterpriseCapableDevice = 0b00100000,
+    LuieUsingE4 = 0b01000000,
+    LpaProxy = 0b10000000,
+}
+
+const EUICC_CAPABILITIES: [EuiccCapability; 8] = {
+    use EuiccCapability::*;
+    [
+        LocalUserInterface,
+        LocalProfileDownload,
+        LocalDiscoveryService,
+        LuieBasedOnScws,
+        MetadataUpdateAlerting,
+        EnterpriseCapableDevice,
+        LuieUsingE4,
+        LpaProxy,
+    ]
+};
+
+/// TerminalCapability: the terminal capability template that TERMINAL CAPABILITY sends; ref 11.1.19 / ETSI
+/// TS 102 221 V15.0.0
+///
+/// The eUICC capabilities hold all the bytes; the bytes after the first one are kept as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalCapability {
+    power_supply: Option<TerminalPowerSupply>,
+    extended_logical_channels: bool,
+    uicc_clf_interface: Option<bool>,
+    euicc_capabilities: Option<Vec<u8>>,
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TerminalCapabilityBuilder {
+    power_supply: Option<TerminalPowerSupply>,
+    extended_logical_channels: bool,
+    uicc_clf_interface: Option<bool>,
+    euicc_capabilities: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TerminalCapabilityError {
+    #[error("maximum available power must be within [10, 60] mA but {0}")]
+    InvalidMaximumAvailablePower(u8),
+    #[error("actual used clock frequency must be within [10, 255] in 0.1 MHz but {0}")]
+    InvalidClockFrequency(u8),
+    #[error("invalid supply voltage class: '{0:02X}'")]
+    InvalidVoltageClass(u8),
+    #[error("invalid BER-TLV of the terminal capability: {0}")]
+    InvalidBerTlv(#[from] BerTlvError),
+    #[error("unexpected tag of the terminal capability; this must be 'A9' but '{0:X}'")]
+    UnexpectedTemplateTag(u32),
+    #[error("invalid length of the data object '{0:02X}': {1} bytes")]
+    InvalidDataObjectLength(u32, usize),
+}
+
+pub fn new_terminal_capability_builder() -> TerminalCapabilityBuilder {
+    TerminalCapabilityBuilder::default()
+}
+
+fn validate_power_supply(
+    power_supply: &TerminalPowerSupply,
+) -> Result<(), TerminalCapabilityError> {
+    if !(0x0a..=0x3c).contains(&power_supply.maximum_available_power) {
+        return Err(TerminalCapabilityError::InvalidMaximumAvailablePower(
+            power_supply.maximum_available_power,
+        ));
+    }
+    if power_supply.actual_clock_frequency < 0x0a {
+        return Err(TerminalCapabilityError::InvalidClockFrequency(
+            power_supply.actual_clock_frequency,
+        ));
+    }
+    Ok(())
+}
+
+fn value_of_length(tlv: &BerTlv, len: usize) -> Result<&[u8], TerminalCapabilityError> {
+    let value = tlv.get_value();
+    if value.len() < len {
+        return Err(TerminalCapabilityError::InvalidDataObjectLength(
+            tlv.get_tag(),
+            value.len(),
+        ));
+    }
+    Ok(value)
+}
+
+/// Parses the command data of TERMINAL CAPABILITY; the data objects of the unknown tags are ignored.
+pub fn parse_terminal_capability(
+    bytes: &[u8],
+) -> Result<TerminalCapability, TerminalCapabilityError> {
+    let (template, _) = parse_ber_tlv(bytes)?;
+    if template.get_tag() != TERMINAL_CAPABILITY_TEMPLATE_TAG {
+        return Err(TerminalCapabilityError::UnexpectedTemplateTag(
+            template.get_tag(),
+        ));
+    }
+
+    let mut builder = new_terminal_capability_builder();
+    for tlv in template.get_children()? {
+        match tlv.get_tag() {
+            TERMINAL_POWER_SUPPLY_TAG => {
+                let value = value_of_length(&tlv, 3)?;
+                let voltage_class = [
+                    SupplyVoltageClass::A,
+                    SupplyVoltageClass::B,
+                    SupplyVoltageClass::C,
+                    SupplyVoltageClass::D,
+                    SupplyVoltageClass::E,
+                ]
+                .into_iter()
+                .find(|c| *c as u8 == value[0])
+                .ok_or(TerminalCapabilityError::InvalidVoltageClass(value[0]))?;
+                let power_supply = TerminalPowerSupply {
+                    voltage_class,
+                    maximum_available_power: value[1],
+                    actual_clock_frequency: value[2],
+                };
+                validate_power_supply(&power_supply)?;
+                builder.power_supply = Some(power_supply);
+            }
+            EXTENDED_LOGICAL_CHANNELS_TAG => builder.extended_logical_channels = true,
+            ADDITIONAL_INTERFACES_TAG => {
+                builder.uicc_clf_interface = Some(value_of_length(&tlv, 1)?[0] & 0x01 != 0);
+            }
+            EUICC_CAPABILITIES_TAG => {
+                builder.euicc_capabilities = Some(value_of_length(&tlv, 1)?.to_vec());
+            }
+            _ => (),
+        }
+    }
+    Ok(builder.build_with_bytes(bytes.to_vec()))
+}
+
+impl TerminalPowerSupply {
+    pub fn get_voltage_class(&self) -> SupplyVoltageClass {
+        self.voltage_class
+    }
+
+    /// Returns the maximum available power supply of the terminal in mA.
+    pub fn get_maximum_available_power(&self) -> u8 {
+        self.maximum_available_power
+    }
+
+    /// Returns the actual used clock frequency in 0.1 MHz.
+    pub fn get_actual_clock_frequency(&self) -> u8 {
+        self.actual_clock_frequency
+    }
+}
+
+impl TerminalCapabilityBuilder {
+    /// Sets the terminal power supply; the power is in mA and the frequency is in 0.1 MHz.
+    pub fn power_supply(
+        mut self,
+        voltage_class: SupplyVoltageClass,
+        maximum_available_power: u8,
+        actual_clock_frequency: u8,
+    ) -> Self {
+        self.power_supply = Some(TerminalPowerSupply {
+            voltage_class,
+            maximum_available_power,
+            actual_clock_frequency,
+        });
+        self
+    }
+
+    pub fn extended_logical_channels(mut self) -> Self {
+        self.extended_logical_channels = true;
+        self
+    }
+
+    /// Indicates whether the UICC-CLF interface (SWP) is supported in the additional interfaces support.
+    pub fn uicc_clf_interface(mut self, supported: bool) -> Self {
+        self.uicc_clf_interface = Some(supported);
+        self
+    }
+
+    pub fn euicc_capabilities(mut self, capabilities: &[EuiccCapability]) -> Self {
+        let first = capabilities.iter().fold(0u8, |byte, c| byte | *c as u8);
+        self.euicc_capabilities = Some(Vec::from([first]));
+        self
+    }
+
+    pub fn build(&self) -> Result<TerminalCapability, TerminalCapabilityError> {
+        let mut value = Vec::new();
+        if let Some(power_supply) = &self.power_supply {
+            validate_power_supply(power_supply)?;
+            value.extend(
+                new_ber_tlv(
+                    TERMINAL_POWER_SUPPLY_TAG,
+                    Vec::from([
+                        power_supply.voltage_class as u8,
+                        power_supply.maximum_available_power,
+                        power_supply.actual_clock_frequency,
+                    ]),
+                )
+                .to_bytes(),
+            );
+        }
+        if self.extended_logical_channels {
+            value.extend(new_ber_tlv(EXTENDED_LOGICAL_CHANNELS_TAG, Vec::new()).to_bytes());
+        }
+        if let Some(supported) = self.uicc_clf_interface {
+            value.extend(
+                new_ber_tlv(ADDITIONAL_INTERFACES_TAG, Vec::from([supported as u8])).to_bytes(),
+            );
+        }
+        if let Some(capabilities) = &self.euicc_capabilities {
+            value.extend(new_ber_tlv(EUICC_CAPABILITIES_TAG, capabilities.clone()).to_bytes());
+        }
+        Ok(self.build_with_bytes(new_ber_tlv(TERMINAL_CAPABILITY_TEMPLATE_TAG, value).to_bytes()))
+    }
+
+    fn build_with_bytes(&self, bytes: Vec<u8>) -> TerminalCapability {
+        TerminalCapability {
+            power_supply: self.power_supply,
+            extended_logical_channels: self.extended_logical_channels,
+            uicc_clf_interface: self.uicc_clf_interface,
+            euicc_capabilities: self.euicc_capabilities.clone(),
+            bytes,
+        }
+    }
+}
+
+impl TerminalCapability {
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn get_power_supply(&self) -> Option<&TerminalPowerSupply> {
+        self.power_supply.as_ref()
+    }
+
+    pub fn supports_extended_logical_channels(&self) -> bool {
+        self.extended_logical_channels
+    }
+
+    /// Returns whether the UICC-CLF interface is supported; `None` when the additional interfaces support
+    /// is absent.
+    pub fn supports_uicc_clf_interface(&self) -> Option<bool> {
+        self.uicc_clf_interface
+    }
+
+    pub fn get_euicc_capabilities(&self) -> Vec<EuiccCapability> {
+        match self.euicc_capabilities.as_deref() {
+            Some([first, ..]) => EUICC_CAPABILITIES
+                .iter()
+                .copied()
+                .filter(|c| first & *c as u8 != 0)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn get_euicc_capability_bytes(&self) -> Option<&[u8]> {
+        self.euicc_capabilities.as_deref()
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &TerminalCapabilityInstruction {},
+            0x00,
+            0x00,
+            None,
+            Some(&self.bytes),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::terminal_capability::{
+        new_terminal_capability_builder, parse_terminal_capability, EuiccCapability,
+        SupplyVoltageClass, TerminalCapabilityError,
+    };
+
+    #[test]
+    fn should_build_and_parse_terminal_capability() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let capability = new_terminal_capability_builder()
+            .power_supply(SupplyVoltageClass::C, 60, 50)
+            .extended_logical_channels()
+            .uicc_clf_interface(true)
+            .euicc_capabilities(&[
+                EuiccCapability::LocalUserInterface,
+                EuiccCapability::LocalProfileDownload,
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            capability.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([
+                0x80, 0xaa, 0x00, 0x00, 0x0f, 0xa9, 0x0d, 0x80, 0x03, 0x04, 0x3c, 0x32, 0x81, 0x00,
+                0x82, 0x01, 0x01, 0x83, 0x01, 0x03
+            ])
+        );
+
+        let parsed = parse_terminal_capability(capability.get_bytes()).unwrap();
+        assert_eq!(parsed, capability);
+        let power_supply = parsed.get_power_supply().unwrap();
+        assert_eq!(power_supply.get_voltage_class(), SupplyVoltageClass::C);
+        assert_eq!(power_supply.get_actual_clock_frequency(), 50);
+        assert!(parsed.supports_extended_logical_channels());
+        assert_eq!(parsed.supports_uicc_clf_interface(), Some(true));
+        assert_eq!(
+            parsed.get_euicc_capabilities(),
+            Vec::from([
+                EuiccCapability::LocalUserInterface,
+                EuiccCapability::LocalProfileDownload
+            ])
+        );
+    }
+
+    #[test]
+    fn should_fail_invalid_terminal_capability() {
+        assert_eq!(
+            new_terminal_capability_builder()
+                .power_supply(SupplyVoltageClass::B, 61, 50)
+                .build()
+                .unwrap_err(),
+            TerminalCapabilityError::InvalidMaximumAvailablePower(61)
+        );
+        assert_eq!(
+            parse_terminal_capability(&[0xa9, 0x05, 0x80, 0x03, 0x03, 0x3c, 0x32]).unwrap_err(),
+            TerminalCapabilityError::InvalidVoltageClass(0x03)
+        );
+        assert_eq!(
+            parse_terminal_capability(&[0xa8, 0x00]).unwrap_err(),
+            TerminalCapabilityError::UnexpectedTemplateTag(0xa8)
+        );
+    }
+}
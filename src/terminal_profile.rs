@@ -0,0 +1,523 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::instruction::TerminalProfile as TerminalProfileInstruction;
+
+/// TerminalFacility: a facility of the terminal that is indicated by a bit of TERMINAL PROFILE;
+/// ref 5.2 / ETSI TS 102 223 V15.0.0
+///
+/// The facilities that are reserved by 3GPP in TS 102 223 are named after 3GPP TS 31.111.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TerminalFacility {
+    // First byte (Download)
+    ProfileDownload,
+    SmsPpDataDownload,
+    CellBroadcastDataDownload,
+    MenuSelection,
+    SmsPpDataDownloadWithResponse,
+    TimerExpiration,
+    UssdStringInCallControl,
+    EnvelopeCallControlAlwaysSent,
+
+    // Second byte (Other)
+    CommandResult,
+    CallControlByNaa,
+    CallControlWithCellId,
+    MoShortMessageControl,
+    CallControlAlphaIdentifierHandling,
+    Ucs2Entry,
+    Ucs2Display,
+    DisplayTextExtensionText,
+
+    // Third byte (Proactive UICC)
+    DisplayText,
+    GetInkey,
+    GetInput,
+    MoreTime,
+    PlayTone,
+    PollInterval,
+    PollingOff,
+    Refresh,
+
+    // Fourth byte (Proactive UICC)
+    SelectItem,
+    SendShortMessage,
+    SendSs,
+    SendUssd,
+    SetUpCall,
+    SetUpMenu,
+    ProvideLocalInformationLocation,
+    ProvideLocalInformationNmr,
+
+    // Fifth byte (Event driven information)
+    SetUpEventList,
+    EventMtCall,
+    EventCallConnected,
+    EventCallDisconnected,
+    EventLocationStatus,
+    EventUserActivity,
+    EventIdleScreenAvailable,
+    EventCardReaderStatus,
+
+    // Sixth byte (Event driven information extensions)
+    EventLanguageSelection,
+    EventBrowserTermination,
+    EventDataAvailable,
+    EventChannelStatus,
+    EventAccessTechnologyChange,
+    EventDisplayParametersChanged,
+    EventLocalConnection,
+    EventNetworkSearchModeChange,
+
+    // Seventh byte (Multiple card proactive commands)
+    PowerOnCard,
+    PowerOffCard,
+    PerformCardApdu,
+    GetReaderStatusStatus,
+    GetReaderStatusIdentifier,
+
+    // Eighth byte (Proactive UICC)
+    TimerManagementStartStop,
+    TimerManagementGetCurrentValue,
+    ProvideLocalInformationDateTime,
+    GetInkeyBinaryChoice,
+    SetUpIdleModeText,
+    RunAtCommand,
+    SetUpCallSecondAlphaIdentifier,
+    CallControlSecondCapabilityConfiguration,
+
+    // Ninth byte
+    SustainedDisplayText,
+    SendDtmf,
+    ProvideLocalInformationBcchChannelList,
+    ProvideLocalInformationLanguage,
+    ProvideLocalInformationTimingAdvance,
+    LanguageNotification,
+    LaunchBrowser,
+    ProvideLocalInformationAccessTechnology,
+
+    // Tenth byte (Soft keys support)
+    SoftKeysForSelectItem,
+    SoftKeysForSetUpMenu,
+
+    // Twelfth byte (Bearer Independent protocol proactive commands)
+    OpenChannel,
+    CloseChannel,
+    ReceiveData,
+    SendData,
+    GetChannelStatus,
+    ServiceSearch,
+    GetServiceInformation,
+    DeclareService,
+
+    // Thirteenth byte (Bearer Independent protocol supported bearers)
+    BearerCsd,
+    BearerGprs,
+    BearerBluetooth,
+    BearerIrda,
+    BearerRs232,
+
+    // Fourteenth to sixteenth byte (Screen)
+    ScreenSizingParameters,
+    VariableSizeFonts,
+    DisplayResize,
+    TextWrapping,
+    TextScrolling,
+    TextAttributes,
+
+    // Seventeenth byte (Bearer independent protocol supported transport interface)
+    TcpClientRemote,
+    UdpClientRemote,
+    TcpServer,
+    TcpClientLocal,
+    UdpClientLocal,
+    DirectCommunicationChannel,
+}
+
+/// All the facilities with the index of the byte and the bit mask, in the order of the bits.
+const TERMINAL_FACILITY_BITS: [(TerminalFacility, usize, u8); 96] = {
+    use TerminalFacility::*;
+    [
+        (ProfileDownload, 0, 0b00000001),
+        (SmsPpDataDownload, 0, 0b00000010),
+        (CellBroadcastDataDownload, 0, 0b00000100),
+        (MenuSelection, 0, 0b00001000),
+        (SmsPpDataDownloadWithResponse, 0, 0b00010000),
+        (TimerExpiration, 0, 0b00100000),
+        (UssdStringInCallControl, 0, 0b01000000),
+        (EnvelopeCallControlAlwaysSent, 0, 0b10000000),
+        (CommandResult, 1, 0b00000001),
+        (CallControlByNaa, 1, 0b00000010),
+        (CallControlWithCellId, 1, 0b00000100),
+        (MoShortMessageControl, 1, 0b00001000),
+        (CallControlAlphaIdentifierHandling, 1, 0b00010000),
+        (Ucs2Entry, 1, 0b00100000),
+        (Ucs2Display, 1, 0b01000000),
+        (DisplayTextExtensionText, 1, 0b10000000),
+        (DisplayText, 2, 0b00000001),
+        (GetInkey, 2, 0b00000010),
+        (GetInput, 2, 0b00000100),
+        (MoreTime, 2, 0b00001000),
+        (PlayTone, 2, 0b00010000),
+        (PollInterval, 2, 0b00100000),
+        (PollingOff, 2, 0b01000000),
+        (Refresh, 2, 0b10000000),
+        (SelectItem, 3, 0b00000001),
+        (SendShortMessage, 3, 0b00000010),
+        (SendSs, 3, 0b00000100),
+        (SendUssd, 3, 0b00001000),
+        (SetUpCall, 3, 0b00010000),
+        (SetUpMenu, 3, 0b00100000),
+        (ProvideLocalInformationLocation, 3, 0b01000000),
+        (ProvideLocalInformationNmr, 3, 0b10000000),
+        (SetUpEventList, 4, 0b00000001),
+        (EventMtCall, 4, 0b00000010),
+        (EventCallConnected, 4, 0b00000100),
+        (EventCallDisconnected, 4, 0b00001000),
+        (EventLocationStatus, 4, 0b00010000),
+        (EventUserActivity, 4, 0b00100000),
+        (EventIdleScreenAvailable, 4, 0b01000000),
+        (EventCardReaderStatus, 4, 0b10000000),
+        (EventLanguageSelection, 5, 0b00000001),
+        (EventBrowserTermination, 5, 0b00000010),
+        (EventDataAvailable, 5, 0b00000100),
+        (EventChannelStatus, 5, 0b00001000),
+        (EventAccessTechnologyChange, 5, 0b00010000),
+        (EventDisplayParametersChanged, 5, 0b00100000),
+        (EventLocalConnection, 5, 0b01000000),
+        (EventNetworkSearchModeChange, 5, 0b10000000),
+        (PowerOnCard, 6, 0b00000001),
+        (PowerOffCard, 6, 0b00000010),
+        (PerformCardApdu, 6, 0b00000100),
+        (GetReaderStatusStatus, 6, 0b00001000),
+        (GetReaderStatusIdentifier, 6, 0b00010000),
+        (TimerManagementStartStop, 7, 0b00000001),
+        (TimerManagementGetCurrentValue, 7, 0b00000010),
+        (ProvideLocalInformationDateTime, 7, 0b00000100),
+        (GetInkeyBinaryChoice, 7, 0b00001000),
+        (SetUpIdleModeText, 7, 0b00010000),
+        (RunAtCommand, 7, 0b00100000),
+        (SetUpCallSecondAlphaIdentifier, 7, 0b01000000),
+        (CallControlSecondCapabilityConfiguration, 7, 0b10000000),
+        (SustainedDisplayText, 8, 0b00000001),
+        (SendDtmf, 8, 0b00000010),
+        (ProvideLocalInformationBcchChannelList, 8, 0b00000100),
+        (ProvideLocalInformationLanguage, 8, 0b00001000),
+        (ProvideLocalInformationTimingAdvance, 8, 0b00010000),
+        (LanguageNotification, 8, 0b00100000),
+        (LaunchBrowser, 8, 0b01000000),
+        (ProvideLocalInformationAccessTechnology, 8, 0b10000000),
+        (SoftKeysForSelectItem, 9, 0b00000001),
+        (SoftKeysForSetUpMenu, 9, 0b00000010),
+        (OpenChannel, 11, 0b00000001),
+        (CloseChannel, 11, 0b00000010),
+        (ReceiveData, 11, 0b00000100),
+        (SendData, 11, 0b00001000),
+        (GetChannelStatus, 11, 0b00010000),
+        (ServiceSearch, 11, 0b00100000),
+        (GetServiceInformation, 11, 0b01000000),
+        (DeclareService, 11, 0b10000000),
+        (BearerCsd, 12, 0b00000001),
+        (BearerGprs, 12, 0b00000010),
+        (BearerBluetooth, 12, 0b00000100),
+        (BearerIrda, 12, 0b00001000),
+        (BearerRs232, 12, 0b00010000),
+        (ScreenSizingParameters, 13, 0b10000000),
+        (VariableSizeFonts, 14, 0b10000000),
+        (DisplayResize, 15, 0b00000001),
+        (TextWrapping, 15, 0b00000010),
+        (TextScrolling, 15, 0b00000100),
+        (TextAttributes, 15, 0b00001000),
+        (TcpClientRemote, 16, 0b00000001),
+        (UdpClientRemote, 16, 0b00000010),
+        (TcpServer, 16, 0b00000100),
+        (TcpClientLocal, 16, 0b00001000),
+        (UdpClientLocal, 16, 0b00010000),
+        (DirectCommunicationChannel, 16, 0b00100000),
+    ]
+};
+
+impl TerminalFacility {
+    /// Returns the index of the byte and the bit mask of the facility.
+    pub fn get_position(&self) -> (usize, u8) {
+        TERMINAL_FACILITY_BITS
+            .iter()
+            .find(|(facility, _, _)| facility == self)
+            .map(|(_, index, mask)| (*index, *mask))
+            .unwrap()
+    }
+}
+
+/// TerminalProfile: the facilities of the terminal sent by TERMINAL PROFILE; ref 11.2.1 / ETSI TS 102 221
+/// V15.0.0 and 5.2 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalProfile {
+    bytes: Vec<u8>,
+}
+
+/// TerminalProfileBuilder builds `TerminalProfile` from the named facilities and the screen and channel
+/// parameters.
+#[derive(Debug, Clone, Default)]
+pub struct TerminalProfileBuilder {
+    facilities: Vec<TerminalFacility>,
+    maximum_number_of_soft_keys: u8,
+    number_of_channels: u8,
+    screen_height: u8,
+    screen_width: u8,
+    menu_width_reduction: u8,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TerminalProfileError {
+    #[error("number of channels must be within [0, 7] but {0}")]
+    InvalidNumberOfChannels(u8),
+    #[error("screen height must be within [0, 31] characters but {0}")]
+    InvalidScreenHeight(u8),
+    #[error("screen width must be within [0, 127] characters but {0}")]
+    InvalidScreenWidth(u8),
+    #[error("width reduction in a menu must be within [0, 7] but {0}")]
+    InvalidMenuWidthReduction(u8),
+    #[error(
+        "illegal length of the terminal profile; this must be within [1, 255] bytes but {0} bytes"
+    )]
+    IllegalLength(usize),
+}
+
+pub fn new_terminal_profile_builder() -> TerminalProfileBuilder {
+    TerminalProfileBuilder::default()
+}
+
+pub fn parse_terminal_profile(bytes: &[u8]) -> Result<TerminalProfile, TerminalProfileError> {
+    if bytes.is_empty() || bytes.len() > 0xff {
+        return Err(TerminalProfileError::IllegalLength(bytes.len()));
+    }
+    Ok(TerminalProfile {
+        bytes: bytes.to_vec(),
+    })
+}
+
+impl TerminalProfileBuilder {
+    pub fn facility(mut self, facility: TerminalFacility) -> Self {
+        self.facilities.push(facility);
+        self
+    }
+
+    pub fn facilities(mut self, facilities: &[TerminalFacility]) -> Self {
+        self.facilities.extend_from_slice(facilities);
+        self
+    }
+
+    pub fn maximum_number_of_soft_keys(mut self, number: u8) -> Self {
+        self.maximum_number_of_soft_keys = number;
+        self
+    }
+
+    /// Sets the number of channels supported by the terminal for the Bearer Independent Protocol.
+    pub fn number_of_channels(mut self, number: u8) -> Self {
+        self.number_of_channels = number;
+        self
+    }
+
+    /// Sets the number of characters supported down and across the terminal display.
+    pub fn screen_size(mut self, height: u8, width: u8) -> Self {
+        self.screen_height = height;
+        self.screen_width = width;
+        self
+    }
+
+    /// Sets the width reduction in characters when the terminal is in a menu.
+    pub fn menu_width_reduction(mut self, reduction: u8) -> Self {
+        self.menu_width_reduction = reduction;
+        self
+    }
+
+    pub fn build(&self) -> Result<TerminalProfile, TerminalProfileError> {
+        if self.number_of_channels > 0b111 {
+            return Err(TerminalProfileError::InvalidNumberOfChannels(
+                self.number_of_channels,
+            ));
+        }
+        if self.screen_height > 0b11111 {
+            return Err(TerminalProfileError::InvalidScreenHeight(
+                self.screen_height,
+            ));
+        }
+        if self.screen_width > 0b1111111 {
+            return Err(TerminalProfileError::InvalidScreenWidth(self.screen_width));
+        }
+        if self.menu_width_reduction > 0b111 {
+            return Err(TerminalProfileError::InvalidMenuWidthReduction(
+                self.menu_width_reduction,
+            ));
+        }
+
+        let mut bytes = vec![0u8; 17];
+        for facility in &self.facilities {
+            let (index, mask) = facility.get_position();
+            bytes[index] |= mask;
+        }
+        bytes[10] = self.maximum_number_of_soft_keys;
+        bytes[12] |= self.number_of_channels << 5;
+        bytes[13] |= self.screen_height;
+        bytes[14] |= self.screen_width;
+        bytes[15] |= self.menu_width_reduction << 5;
+
+        // the terminal sends the bytes up to the last one that indicates any facility
+        let len = bytes.iter().rposition(|b| *b != 0).map_or(1, |i| i + 1);
+        bytes.truncate(len);
+        Ok(TerminalProfile { bytes })
+    }
+}
+
+impl TerminalProfile {
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn get_byte(&self, index: usize) -> u8 {
+        self.bytes.get(index).copied().unwrap_or(0x00)
+    }
+
+    pub fn has_facility(&self, facility: TerminalFacility) -> bool {
+        let (index, mask) = facility.get_position();
+        self.get_byte(index) & mask != 0
+    }
+
+    /// Returns the enabled facilities in the order of the bits.
+    pub fn get_facilities(&self) -> Vec<TerminalFacility> {
+        TERMINAL_FACILITY_BITS
+            .iter()
+            .map(|(facility, _, _)| *facility)
+            .filter(|facility| self.has_facility(*facility))
+            .collect()
+    }
+
+    pub fn get_maximum_number_of_soft_keys(&self) -> u8 {
+        self.get_byte(10)
+    }
+
+    pub fn get_number_of_channels(&self) -> u8 {
+        self.get_byte(12) >> 5
+    }
+
+    /// Returns the number of characters supported down and across the terminal display.
+    pub fn get_screen_size(&self) -> (u8, u8) {
+        (
+            self.get_byte(13) & 0b00011111,
+            self.get_byte(14) & 0b01111111,
+        )
+    }
+
+    pub fn get_menu_width_reduction(&self) -> u8 {
+        self.get_byte(15) >> 5
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &TerminalProfileInstruction {},
+            0x00,
+            0x00,
+            None,
+            Some(&self.bytes),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::terminal_profile::{
+        new_terminal_profile_builder, parse_terminal_profile, TerminalFacility,
+        TerminalProfileError, TERMINAL_FACILITY_BITS,
+    };
+
+    #[test]
+    fn should_build_terminal_profile() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let profile = new_terminal_profile_builder()
+            .facilities(&[
+                TerminalFacility::ProfileDownload,
+                TerminalFacility::MenuSelection,
+                TerminalFacility::CommandResult,
+                TerminalFacility::DisplayText,
+                TerminalFacility::SetUpMenu,
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            profile.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([0x80, 0x10, 0x00, 0x00, 0x04, 0x09, 0x01, 0x01, 0x20])
+        );
+
+        let profile = new_terminal_profile_builder()
+            .facility(TerminalFacility::TimerManagementStartStop)
+            .facility(TerminalFacility::OpenChannel)
+            .facility(TerminalFacility::BearerGprs)
+            .facility(TerminalFacility::TcpClientRemote)
+            .facility(TerminalFacility::PerformCardApdu)
+            .number_of_channels(7)
+            .screen_size(10, 20)
+            .build()
+            .unwrap();
+        assert_eq!(
+            profile.get_bytes(),
+            &[
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x01, 0x00, 0x00, 0x00, 0x01, 0xe2, 0x0a,
+                0x14, 0x00, 0x01
+            ]
+        );
+
+        assert_eq!(
+            new_terminal_profile_builder()
+                .number_of_channels(8)
+                .build()
+                .unwrap_err(),
+            TerminalProfileError::InvalidNumberOfChannels(8)
+        );
+    }
+
+    #[test]
+    fn should_decode_terminal_profile() {
+        let profile = parse_terminal_profile(&[0x09, 0x01, 0x00, 0x20]).unwrap();
+        assert_eq!(
+            profile.get_facilities(),
+            Vec::from([
+                TerminalFacility::ProfileDownload,
+                TerminalFacility::MenuSelection,
+                TerminalFacility::CommandResult,
+                TerminalFacility::SetUpMenu,
+            ])
+        );
+        assert_eq!(profile.get_number_of_channels(), 0);
+
+        // every facility occupies its own bit
+        let facilities: Vec<TerminalFacility> = TERMINAL_FACILITY_BITS
+            .iter()
+            .map(|(facility, _, _)| *facility)
+            .collect();
+        let profile = new_terminal_profile_builder()
+            .facilities(&facilities)
+            .build()
+            .unwrap();
+        assert_eq!(profile.get_facilities(), facilities);
+        let bits: u32 = profile.get_bytes().iter().map(|b| b.count_ones()).sum();
+        assert_eq!(bits as usize, facilities.len());
+
+        assert_eq!(
+            parse_terminal_profile(&[]).unwrap_err(),
+            TerminalProfileError::IllegalLength(0)
+        );
+    }
+}
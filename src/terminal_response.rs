@@ -0,0 +1,491 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::class::Class;
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::comprehension_tlv::{
+    find_comprehension_tlv, new_comprehension_tlv, ComprehensionTlv, BEARER_DESCRIPTION_TAG,
+    BUFFER_SIZE_TAG, CHANNEL_DATA_LENGTH_TAG, CHANNEL_DATA_TAG, COMMAND_DETAILS_TAG,
+    DEVICE_IDENTITY_TAG, DURATION_TAG, ITEM_IDENTIFIER_TAG, RESULT_TAG, TEXT_STRING_TAG,
+};
+use crate::instruction::TerminalResponse as TerminalResponseInstruction;
+use crate::proactive_command::{ProactiveCommand, TypeOfCommand, DEVICE_TERMINAL, DEVICE_UICC};
+use crate::simple_tlv::{
+    BearerDescription, ChannelData, ChannelStatus, Duration, SimpleTlvError, TextString,
+};
+
+/// GeneralResult: ref 8.12 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum GeneralResult {
+    PerformedSuccessfully = 0x00,
+    PerformedWithPartialComprehension = 0x01,
+    PerformedWithMissingInformation = 0x02,
+    RefreshPerformedWithAdditionalEfsRead = 0x03,
+    PerformedSuccessfullyButIconNotDisplayed = 0x04,
+    PerformedButModifiedByCallControl = 0x05,
+    PerformedSuccessfullyLimitedService = 0x06,
+    PerformedWithModification = 0x07,
+    RefreshPerformedButNaaNotActive = 0x08,
+    PerformedSuccessfullyToneNotPlayed = 0x09,
+    SessionTerminatedByUser = 0x10,
+    BackwardMoveRequestedByUser = 0x11,
+    NoResponseFromUser = 0x12,
+    HelpInformationRequiredByUser = 0x13,
+    TerminalUnableToProcess = 0x20,
+    NetworkUnableToProcess = 0x21,
+    UserDidNotAccept = 0x22,
+    UserClearedDownCall = 0x23,
+    ContradictionWithTimerState = 0x24,
+    CallControlTemporaryProblem = 0x25,
+    LaunchBrowserGenericError = 0x26,
+    BeyondTerminalCapabilities = 0x30,
+    CommandTypeNotUnderstood = 0x31,
+    CommandDataNotUnderstood = 0x32,
+    CommandNumberNotKnown = 0x33,
+    SsReturnError = 0x34,
+    SmsRpError = 0x35,
+    RequiredValuesMissing = 0x36,
+    UssdReturnError = 0x37,
+    MultipleCardCommandsError = 0x38,
+    CallControlPermanentProblem = 0x39,
+    BearerIndependentProtocolError = 0x3a,
+    AccessTechnologyUnableToProcess = 0x3b,
+    FramesError = 0x3c,
+    MmsError = 0x3d,
+}
+
+const GENERAL_RESULTS: [GeneralResult; 35] = {
+    use GeneralResult::*;
+    [
+        PerformedSuccessfully,
+        PerformedWithPartialComprehension,
+        PerformedWithMissingInformation,
+        RefreshPerformedWithAdditionalEfsRead,
+        PerformedSuccessfullyButIconNotDisplayed,
+        PerformedButModifiedByCallControl,
+        PerformedSuccessfullyLimitedService,
+        PerformedWithModification,
+        RefreshPerformedButNaaNotActive,
+        PerformedSuccessfullyToneNotPlayed,
+        SessionTerminatedByUser,
+        BackwardMoveRequestedByUser,
+        NoResponseFromUser,
+        HelpInformationRequiredByUser,
+        TerminalUnableToProcess,
+        NetworkUnableToProcess,
+        UserDidNotAccept,
+        UserClearedDownCall,
+        ContradictionWithTimerState,
+        CallControlTemporaryProblem,
+        LaunchBrowserGenericError,
+        BeyondTerminalCapabilities,
+        CommandTypeNotUnderstood,
+        CommandDataNotUnderstood,
+        CommandNumberNotKnown,
+        SsReturnError,
+        SmsRpError,
+        RequiredValuesMissing,
+        UssdReturnError,
+        MultipleCardCommandsError,
+        CallControlPermanentProblem,
+        BearerIndependentProtocolError,
+        AccessTechnologyUnableToProcess,
+        FramesError,
+        MmsError,
+    ]
+};
+
+impl GeneralResult {
+    pub fn from_byte(b: u8) -> Option<GeneralResult> {
+        GENERAL_RESULTS.iter().copied().find(|t| *t as u8 == b)
+    }
+
+    /// Returns whether the command has been performed; that is the results within ['00', '0F'].
+    pub fn is_performed(&self) -> bool {
+        (*self as u8) < 0x10
+    }
+
+    /// Returns whether the terminal must give the cause in the additional information; ref 8.12 / ETSI TS 102 223
+    /// V15.0.0
+    pub fn requires_additional_information(&self) -> bool {
+        use GeneralResult::*;
+        matches!(
+            self,
+            TerminalUnableToProcess
+                | NetworkUnableToProcess
+                | LaunchBrowserGenericError
+                | SsReturnError
+                | SmsRpError
+                | UssdReturnError
+                | MultipleCardCommandsError
+                | CallControlPermanentProblem
+                | BearerIndependentProtocolError
+                | FramesError
+                | MmsError
+        )
+    }
+}
+
+/// CommandResult: the general result and the additional information; ref 8.12 / ETSI TS 102 223 V15.0.0
+///
+/// e.g. `TerminalUnableToProcess` with the additional information '01' means "screen is busy".
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandResult {
+    general_result: GeneralResult,
+    additional_information: Vec<u8>,
+}
+
+/// TerminalResponse: the response to a proactive command; ref 6.8 / ETSI TS 102 223 V15.0.0
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalResponse {
+    bytes: Vec<u8>,
+}
+
+/// TerminalResponseBuilder builds `TerminalResponse` that echoes the command details of the proactive
+/// command, with the result and the command-specific data objects.
+pub struct TerminalResponseBuilder<'a> {
+    command: &'a ProactiveCommand,
+    result: CommandResult,
+    data_objects: Vec<ComprehensionTlv>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TerminalResponseError {
+    #[error(
+        "data object '{0:02X}' is mandatory in the terminal response to the performed command"
+    )]
+    MissingDataObject(u16),
+    #[error("the general result '{0:02X}' requires the additional information")]
+    MissingAdditionalInformation(u8),
+    #[error(
+        "illegal length of the terminal response; this must be within [0, 255] bytes but {0} bytes"
+    )]
+    IllegalLength(usize),
+}
+
+pub fn new_command_result(
+    general_result: GeneralResult,
+    additional_information: Vec<u8>,
+) -> CommandResult {
+    CommandResult {
+        general_result,
+        additional_information,
+    }
+}
+
+/// Parses the result data object, e.g. of the terminal response that a UICC simulator receives.
+pub fn parse_command_result(tlv: &ComprehensionTlv) -> Result<CommandResult, SimpleTlvError> {
+    let (general_result, additional_information) = tlv
+        .get_value()
+        .split_first()
+        .ok_or(SimpleTlvError::InvalidLength(tlv.get_tag(), 0))?;
+    Ok(CommandResult {
+        general_result: GeneralResult::from_byte(*general_result)
+            .ok_or(SimpleTlvError::InvalidValue(tlv.get_tag()))?,
+        additional_information: additional_information.to_vec(),
+    })
+}
+
+pub fn new_terminal_response_builder(
+    command: &ProactiveCommand,
+    result: CommandResult,
+) -> TerminalResponseBuilder<'_> {
+    TerminalResponseBuilder {
+        command,
+        result,
+        data_objects: Vec::new(),
+    }
+}
+
+/// Creates the terminal response to the proactive command that cannot be decoded, e.g. "command data not
+/// understood by terminal"; the command details are echoed as they are.
+pub fn new_undecoded_command_terminal_response(
+    command_details: &ComprehensionTlv,
+    result: CommandResult,
+) -> Result<TerminalResponse, TerminalResponseError> {
+    encode_terminal_response(command_details, &result, &[])
+}
+
+fn encode_terminal_response(
+    command_details: &ComprehensionTlv,
+    result: &CommandResult,
+    data_objects: &[ComprehensionTlv],
+) -> Result<TerminalResponse, TerminalResponseError> {
+    let general_result = result.general_result;
+    if general_result.requires_additional_information() && result.additional_information.is_empty()
+    {
+        return Err(TerminalResponseError::MissingAdditionalInformation(
+            general_result as u8,
+        ));
+    }
+    let device_identities = new_comprehension_tlv(
+        DEVICE_IDENTITY_TAG,
+        true,
+        Vec::from([DEVICE_TERMINAL, DEVICE_UICC]),
+    );
+    let mut value = Vec::from([general_result as u8]);
+    value.extend_from_slice(&result.additional_information);
+    let result = new_comprehension_tlv(RESULT_TAG, true, value);
+
+    let mut bytes = Vec::new();
+    for tlv in [command_details, &device_identities, &result]
+        .into_iter()
+        .chain(data_objects.iter())
+    {
+        bytes.extend(tlv.to_bytes());
+    }
+    if bytes.len() > 0xff {
+        return Err(TerminalResponseError::IllegalLength(bytes.len()));
+    }
+    Ok(TerminalResponse { bytes })
+}
+
+/// Returns the data objects that must be in the response when the command has been performed.
+fn mandatory_data_objects(type_of_command: Option<TypeOfCommand>) -> &'static [u16] {
+    match type_of_command {
+        Some(TypeOfCommand::GetInkey | TypeOfCommand::GetInput) => &[TEXT_STRING_TAG],
+        Some(TypeOfCommand::SelectItem) => &[ITEM_IDENTIFIER_TAG],
+        Some(TypeOfCommand::PollInterval) => &[DURATION_TAG],
+        Some(TypeOfCommand::OpenChannel) => &[BEARER_DESCRIPTION_TAG, BUFFER_SIZE_TAG],
+        Some(TypeOfCommand::ReceiveData) => &[CHANNEL_DATA_TAG, CHANNEL_DATA_LENGTH_TAG],
+        Some(TypeOfCommand::SendData) => &[CHANNEL_DATA_LENGTH_TAG],
+        _ => &[],
+    }
+}
+
+impl CommandResult {
+    pub fn get_general_result(&self) -> GeneralResult {
+        self.general_result
+    }
+
+    pub fn get_additional_information(&self) -> &[u8] {
+        &self.additional_information
+    }
+}
+
+impl<'a> TerminalResponseBuilder<'a> {
+    /// Adds a command-specific data object as it is.
+    pub fn data_object(mut self, tlv: ComprehensionTlv) -> Self {
+        self.data_objects.push(tlv);
+        self
+    }
+
+    /// Adds the text string that the user entered for GET INKEY and GET INPUT.
+    pub fn text_string(self, text: &TextString) -> Self {
+        self.data_object(text.to_comprehension_tlv(TEXT_STRING_TAG))
+    }
+
+    /// Adds the identifier of the item that the user selected for SELECT ITEM.
+    pub fn item_identifier(self, identifier: u8) -> Self {
+        self.data_object(new_comprehension_tlv(
+            ITEM_IDENTIFIER_TAG,
+            true,
+            Vec::from([identifier]),
+        ))
+    }
+
+    /// Adds the poll interval that the terminal actually uses for POLL INTERVAL.
+    pub fn duration(self, duration: &Duration) -> Self {
+        self.data_object(duration.to_comprehension_tlv())
+    }
+
+    pub fn channel_status(self, status: &ChannelStatus) -> Self {
+        self.data_object(status.to_comprehension_tlv())
+    }
+
+    pub fn bearer_description(self, description: &BearerDescription) -> Self {
+        self.data_object(description.to_comprehension_tlv())
+    }
+
+    pub fn buffer_size(self, size: u16) -> Self {
+        self.data_object(new_comprehension_tlv(
+            BUFFER_SIZE_TAG,
+            true,
+            size.to_be_bytes().to_vec(),
+        ))
+    }
+
+    pub fn channel_data(self, data: &ChannelData) -> Self {
+        self.data_object(data.to_comprehension_tlv())
+    }
+
+    /// Adds the number of bytes that are available in the buffer ('FF' means more than 255 bytes).
+    pub fn channel_data_length(self, length: u8) -> Self {
+        self.data_object(new_comprehension_tlv(
+            CHANNEL_DATA_LENGTH_TAG,
+            true,
+            Vec::from([length]),
+        ))
+    }
+
+    pub fn build(&self) -> Result<TerminalResponse, TerminalResponseError> {
+        if self.result.general_result.is_performed() {
+            let type_of_command = self.command.get_command_details().get_type_of_command();
+            for tag in mandatory_data_objects(type_of_command) {
+                if find_comprehension_tlv(&self.data_objects, *tag).is_none() {
+                    return Err(TerminalResponseError::MissingDataObject(*tag));
+                }
+            }
+        }
+
+        // the command details are echoed as they are, including the comprehension required flag
+        let command_details =
+            find_comprehension_tlv(self.command.get_data_objects(), COMMAND_DETAILS_TAG)
+                .cloned()
+                .unwrap_or_else(|| {
+                    new_comprehension_tlv(
+                        COMMAND_DETAILS_TAG,
+                        true,
+                        self.command.get_command_details().to_bytes().to_vec(),
+                    )
+                });
+        encode_terminal_response(&command_details, &self.result, &self.data_objects)
+    }
+}
+
+impl TerminalResponse {
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn to_command_apdu<'a>(&'a self, class: &'a Class) -> CommandAPDU<'a> {
+        new_command_apdu(
+            class,
+            &TerminalResponseInstruction {},
+            0x00,
+            0x00,
+            None,
+            Some(&self.bytes),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::proactive_command::{find_command_details, parse_proactive_command};
+    use crate::terminal_response::{
+        new_command_result, new_terminal_response_builder, new_undecoded_command_terminal_response,
+        GeneralResult, TerminalResponseError,
+    };
+
+    #[test]
+    fn should_build_terminal_response_to_display_text() {
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::TS102_221,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+        let command = parse_proactive_command(&[
+            0xd0, 0x11, 0x81, 0x03, 0x07, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02, 0x8d, 0x06, 0x04,
+            0x48, 0x65, 0x6c, 0x6c, 0x6f,
+        ])
+        .unwrap();
+
+        let response = new_terminal_response_builder(
+            &command,
+            new_command_result(GeneralResult::PerformedSuccessfully, Vec::new()),
+        )
+        .build()
+        .unwrap();
+        assert_eq!(
+            response.to_command_apdu(&class).to_bytes().unwrap(),
+            Vec::from([
+                0x80, 0x14, 0x00, 0x00, 0x0c, 0x81, 0x03, 0x07, 0x21, 0x80, 0x82, 0x02, 0x82, 0x81,
+                0x83, 0x01, 0x00
+            ])
+        );
+
+        let response = new_terminal_response_builder(
+            &command,
+            new_command_result(GeneralResult::TerminalUnableToProcess, Vec::from([0x01])),
+        )
+        .build()
+        .unwrap();
+        assert_eq!(response.get_bytes()[9..], [0x83, 0x02, 0x20, 0x01]);
+    }
+
+    #[test]
+    fn should_require_additional_information() {
+        let command = parse_proactive_command(&[
+            0xd0, 0x11, 0x81, 0x03, 0x07, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02, 0x8d, 0x06, 0x04,
+            0x48, 0x65, 0x6c, 0x6c, 0x6f,
+        ])
+        .unwrap();
+        for b in [
+            0x20, 0x21, 0x26, 0x34, 0x35, 0x37, 0x38, 0x39, 0x3a, 0x3c, 0x3d,
+        ] {
+            let general_result = GeneralResult::from_byte(b).unwrap();
+            assert!(general_result.requires_additional_information());
+            assert_eq!(
+                new_terminal_response_builder(
+                    &command,
+                    new_command_result(general_result, Vec::new())
+                )
+                .build()
+                .unwrap_err(),
+                TerminalResponseError::MissingAdditionalInformation(b)
+            );
+        }
+
+        let response = new_terminal_response_builder(
+            &command,
+            new_command_result(GeneralResult::SmsRpError, Vec::from([0xa9])),
+        )
+        .build()
+        .unwrap();
+        assert_eq!(response.get_bytes()[9..], [0x83, 0x02, 0x35, 0xa9]);
+        assert!(!GeneralResult::RequiredValuesMissing.requires_additional_information());
+    }
+
+    #[test]
+    fn should_require_command_specific_data() {
+        // SELECT ITEM with two items
+        let command = parse_proactive_command(&[
+            0xd0, 0x11, 0x81, 0x03, 0x01, 0x24, 0x00, 0x82, 0x02, 0x81, 0x82, 0x8f, 0x02, 0x01,
+            0x41, 0x8f, 0x02, 0x02, 0x42,
+        ])
+        .unwrap();
+        let builder = new_terminal_response_builder(
+            &command,
+            new_command_result(GeneralResult::PerformedSuccessfully, Vec::new()),
+        );
+        assert_eq!(
+            builder.build().unwrap_err(),
+            TerminalResponseError::MissingDataObject(0x10)
+        );
+        let response = builder.item_identifier(0x02).build().unwrap();
+        assert_eq!(response.get_bytes()[12..], [0x90, 0x01, 0x02]);
+
+        // the user terminated the session, then no item is selected
+        assert!(new_terminal_response_builder(
+            &command,
+            new_command_result(GeneralResult::SessionTerminatedByUser, Vec::new()),
+        )
+        .build()
+        .is_ok());
+    }
+
+    #[test]
+    fn should_build_terminal_response_to_undecodable_command() {
+        // DISPLAY TEXT without the text string
+        let command_details = find_command_details(&[
+            0xd0, 0x09, 0x81, 0x03, 0x07, 0x21, 0x80, 0x82, 0x02, 0x81, 0x02,
+        ])
+        .unwrap();
+        let response = new_undecoded_command_terminal_response(
+            &command_details,
+            new_command_result(GeneralResult::RequiredValuesMissing, Vec::new()),
+        )
+        .unwrap();
+        assert_eq!(
+            response.get_bytes(),
+            &[0x81, 0x03, 0x07, 0x21, 0x80, 0x82, 0x02, 0x82, 0x81, 0x83, 0x01, 0x36]
+        );
+    }
+}
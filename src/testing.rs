@@ -0,0 +1,26 @@
+use std::collections::VecDeque;
+
+use crate::transport::{Transport, TransportError};
+
+/// ScriptedCard: the transport of the tests that answers the prepared responses in order and records the
+/// commands.
+pub(crate) struct ScriptedCard {
+    responses: VecDeque<Vec<u8>>,
+    pub(crate) commands: Vec<Vec<u8>>,
+}
+
+pub(crate) fn new_scripted_card(responses: &[&[u8]]) -> ScriptedCard {
+    ScriptedCard {
+        responses: responses.iter().map(|r| r.to_vec()).collect(),
+        commands: Vec::new(),
+    }
+}
+
+impl Transport for ScriptedCard {
+    fn transmit(&mut self, command: &[u8]) -> Result<Vec<u8>, TransportError> {
+        self.commands.push(command.to_vec());
+        self.responses
+            .pop_front()
+            .ok_or_else(|| TransportError::TransmissionFailed("no response".to_string()))
+    }
+}
@@ -0,0 +1,671 @@
+use anyhow::Result;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::ber_tlv::{parse_ber_tlv_header, parse_ber_tlvs, BerTlv};
+use crate::command_apdu::{parse_command_apdu, CommandAPDUError};
+use crate::comprehension_tlv::{parse_comprehension_tlvs, ComprehensionTlv};
+use crate::fcp::{parse_fcp, FileControlParameters};
+use crate::hex::{hex_bytes, to_hex};
+use crate::instruction::get_instruction_name;
+use crate::response_apdu::{describe_status_word, parse_response_apdu, ResponseAPDUError};
+
+/// TraceEntry: a pair of the command and the response in the APDU trace with its annotation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceEntry {
+    #[serde(with = "hex_bytes")]
+    command: Vec<u8>,
+    #[serde(with = "hex_bytes")]
+    response: Vec<u8>,
+    #[serde(flatten)]
+    annotation: EntryAnnotation,
+}
+
+/// EntryAnnotation: the annotation of the pair, or the reason why the pair cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum EntryAnnotation {
+    Apdu(Box<ApduAnnotation>),
+    Unparseable { error: String },
+}
+
+/// ApduAnnotation: the decoded command and response of the pair.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApduAnnotation {
+    logical_channel: u8,
+    instruction: String,
+    parameters: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command_data: Option<DataAnnotation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_data: Option<DataAnnotation>,
+    status_word: String,
+    status: String,
+}
+
+/// DataAnnotation: the decoded body of the command or the response.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "format", content = "value")]
+pub enum DataAnnotation {
+    /// The FCP template that SELECT or STATUS returns
+    Fcp(FileControlParameters),
+    /// BER-TLV data objects, e.g. of ENVELOPE or FETCH
+    BerTlv(Vec<TlvAnnotation>),
+    /// COMPREHENSION-TLV data objects, e.g. of TERMINAL RESPONSE
+    ComprehensionTlv(Vec<TlvAnnotation>),
+    /// The first block of SET DATA: the tag and the length of the data object with the beginning of its
+    /// value
+    DataObjectBlock {
+        tag: String,
+        length: usize,
+        #[serde(with = "hex_bytes")]
+        value: Vec<u8>,
+    },
+    /// The data that is not decoded
+    Raw(#[serde(with = "hex_bytes")] Vec<u8>),
+}
+
+/// TlvAnnotation: a data object with the children of the constructed one; the tag is shown as it is
+/// encoded.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TlvAnnotation {
+    tag: String,
+    #[serde(with = "hex_bytes")]
+    value: Vec<u8>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<TlvAnnotation>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TraceError {
+    #[error("invalid command: {0}")]
+    InvalidCommand(CommandAPDUError),
+    #[error("invalid response: {0}")]
+    InvalidResponse(ResponseAPDUError),
+}
+
+/// Instructions whose command data is BER-TLV.
+const TLV_COMMAND_INSTRUCTIONS: [u8; 2] = [0xc2, 0xaa];
+/// TERMINAL RESPONSE, whose command data is COMPREHENSION-TLV
+const TERMINAL_RESPONSE_INSTRUCTION: u8 = 0x14;
+/// SET DATA, whose first block starts with the tag and the length of the data object
+const SET_DATA_INSTRUCTION: u8 = 0xdb;
+const FIRST_BLOCK: u8 = 0x00;
+/// Instructions whose response data may be the FCP template.
+const FCP_RESPONSE_INSTRUCTIONS: [u8; 3] = [0xa4, 0xf2, 0xc0];
+/// Instructions whose response data is BER-TLV.
+const TLV_RESPONSE_INSTRUCTIONS: [u8; 2] = [0x12, 0xcb];
+/// Instructions of the toolkit whose BER-TLV templates, e.g. 'D0' and 'D3', contain the COMPREHENSION-TLV
+/// data objects although the tags are primitive.
+const TOOLKIT_INSTRUCTIONS: [u8; 2] = [0xc2, 0x12];
+
+/// Annotates the pairs of the command bytes and the response bytes in the order of the trace; the pair that
+/// cannot be parsed is kept as the unparseable entry with the reason.
+pub fn annotate_trace(pairs: &[(Vec<u8>, Vec<u8>)]) -> Vec<TraceEntry> {
+    pairs
+        .iter()
+        .map(|(command, response)| {
+            annotate_apdu(command, response).unwrap_or_else(|e| TraceEntry {
+                command: command.clone(),
+                response: response.clone(),
+                annotation: EntryAnnotation::Unparseable {
+                    error: e.to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Annotates the command and the response.
+pub fn annotate_apdu(command: &[u8], response: &[u8]) -> Result<TraceEntry, TraceError> {
+    let apdu = parse_command_apdu(command).map_err(TraceError::InvalidCommand)?;
+    let response_apdu = parse_response_apdu(response).map_err(TraceError::InvalidResponse)?;
+    let ins = apdu.get_ins();
+
+    let command_data = apdu.get_command_data().map(|data| match ins {
+        _ if TLV_COMMAND_INSTRUCTIONS.contains(&ins) => {
+            annotate_tlvs(data, TOOLKIT_INSTRUCTIONS.contains(&ins))
+        }
+        TERMINAL_RESPONSE_INSTRUCTION => annotate_comprehension_tlvs(data),
+        SET_DATA_INSTRUCTION if apdu.get_p1() == FIRST_BLOCK => annotate_first_block(data),
+        _ => DataAnnotation::Raw(data.to_vec()),
+    });
+    let data = response_apdu.get_data();
+    let response_data = if data.is_empty() {
+        None
+    } else if FCP_RESPONSE_INSTRUCTIONS.contains(&ins) {
+        Some(match parse_fcp(data) {
+            Ok(fcp) => DataAnnotation::Fcp(fcp),
+            Err(_) => annotate_tlvs(data, false),
+        })
+    } else if TLV_RESPONSE_INSTRUCTIONS.contains(&ins) {
+        Some(annotate_tlvs(data, TOOLKIT_INSTRUCTIONS.contains(&ins)))
+    } else {
+        Some(DataAnnotation::Raw(data.to_vec()))
+    };
+
+    Ok(TraceEntry {
+        command: command.to_vec(),
+        response: response.to_vec(),
+        annotation: EntryAnnotation::Apdu(Box::new(ApduAnnotation {
+            logical_channel: get_logical_channel(apdu.get_cla()),
+            instruction: match get_instruction_name(ins) {
+                Some(name) => name.to_string(),
+                None => format!("unknown instruction '{:02X}'", ins),
+            },
+            parameters: describe_parameters(ins, apdu.get_p1(), apdu.get_p2()),
+            command_data,
+            response_data,
+            status_word: format!("{:04X}", response_apdu.get_status_word()),
+            status: describe_status_word(response_apdu.get_status_word()),
+        })),
+    })
+}
+
+/// Formats the entries as the plain text log.
+pub fn trace_to_text(entries: &[TraceEntry]) -> String {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| format!("[{}] {}", i + 1, entry.to_text()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the entries as the JSON array.
+pub fn trace_to_json(entries: &[TraceEntry]) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(entries)
+}
+
+/// Returns the logical channel of the class byte; ref 10.1.1 / ETSI TS 102 221 V15.0.0
+fn get_logical_channel(cla: u8) -> u8 {
+    if cla & 0b01000000 == 0 {
+        cla & 0b00000011
+    } else {
+        4 + (cla & 0b00001111)
+    }
+}
+
+fn describe_parameters(ins: u8, p1: u8, p2: u8) -> String {
+    let p1_text = match (ins, p1) {
+        (0xa4, 0x00) => "select by file id",
+        (0xa4, 0x03) => "select parent DF",
+        (0xa4, 0x04) => "select by DF name",
+        (0xa4, 0x08) => "select by path from MF",
+        (0xa4, 0x09) => "select by path from current DF",
+        (0xb0 | 0xd6, _) if p1 & 0x80 != 0 => {
+            return format!(
+                "P1 '{:02X}': short file id {}; P2 '{:02X}': offset {}",
+                p1,
+                p1 & 0x1f,
+                p2,
+                p2
+            )
+        }
+        (0xb0 | 0xd6, _) => {
+            return format!(
+                "P1 '{:02X}' P2 '{:02X}': offset {}",
+                p1,
+                p2,
+                u16::from_be_bytes([p1, p2])
+            )
+        }
+        (0xb2 | 0xdc, _) => {
+            let mode = match p2 & 0b111 {
+                0b010 => "next record",
+                0b011 => "previous record",
+                0b100 => "absolute record",
+                _ => "unknown mode",
+            };
+            return format!(
+                "P1 '{:02X}': record {}; P2 '{:02X}': {}, short file id {}",
+                p1,
+                p1,
+                p2,
+                mode,
+                p2 >> 3
+            );
+        }
+        (0xf2, 0x00) => "no indication",
+        (0xf2, 0x01) => "application initialized",
+        (0xf2, 0x02) => "application termination",
+        (0x70, 0x00) => "open logical channel",
+        (0xcb | 0xdb, 0x00) => "first block",
+        (0xcb | 0xdb, 0x01) => "next block",
+        (0xcb | 0xdb, 0x81) => "previous block again",
+        (0x70, 0x80) => "close logical channel",
+        (0x20 | 0x24 | 0x26 | 0x28 | 0x2c, _) => {
+            return format!("P1 '{:02X}'; P2 '{:02X}': key reference", p1, p2)
+        }
+        _ => return format!("P1 '{:02X}' P2 '{:02X}'", p1, p2),
+    };
+    let p2_text = match (ins, p2) {
+        (0xa4, 0x04) => "return FCP",
+        (0xa4, 0x0c) | (0xf2, 0x0c) => "no data returned",
+        (0xf2, 0x00) => "return FCP of the current DF",
+        (0xf2, 0x01) => "return DF name",
+        (0x70, _) => "logical channel",
+        _ => "",
+    };
+    format!("P1 '{:02X}': {}; P2 '{:02X}': {}", p1, p1_text, p2, p2_text)
+        .trim_end_matches(": ")
+        .to_string()
+}
+
+/// Annotates the BER-TLV data objects; `templates` decodes the values of the top-level primitive ones as
+/// the data objects too.
+fn annotate_tlvs(data: &[u8], templates: bool) -> DataAnnotation {
+    match parse_ber_tlvs(data) {
+        Ok(tlvs) => DataAnnotation::BerTlv(
+            tlvs.iter()
+                .map(|tlv| annotate_tlv(tlv, templates))
+                .collect(),
+        ),
+        Err(_) => DataAnnotation::Raw(data.to_vec()),
+    }
+}
+
+/// Annotates the values of the toolkit templates as the COMPREHENSION-TLV data objects.
+fn annotate_tlv(tlv: &BerTlv, template: bool) -> TlvAnnotation {
+    let children = if template {
+        parse_comprehension_tlvs(tlv.get_value())
+            .map(|children| children.iter().map(annotate_comprehension_tlv).collect())
+            .unwrap_or_default()
+    } else {
+        match parse_ber_tlvs(tlv.get_value()) {
+            Ok(children) if tlv.is_constructed() => children
+                .iter()
+                .map(|child| annotate_tlv(child, false))
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+    TlvAnnotation {
+        tag: format!("{:02X}", tlv.get_tag()),
+        value: tlv.get_value().to_vec(),
+        children,
+    }
+}
+
+fn annotate_comprehension_tlvs(data: &[u8]) -> DataAnnotation {
+    match parse_comprehension_tlvs(data) {
+        Ok(tlvs) => {
+            DataAnnotation::ComprehensionTlv(tlvs.iter().map(annotate_comprehension_tlv).collect())
+        }
+        Err(_) => DataAnnotation::Raw(data.to_vec()),
+    }
+}
+
+/// The tag is shown with the comprehension required flag, i.e. in the single byte or the three byte format.
+fn annotate_comprehension_tlv(tlv: &ComprehensionTlv) -> TlvAnnotation {
+    let tag_size = if tlv.get_tag() <= 0x7e { 1 } else { 3 };
+    TlvAnnotation {
+        tag: to_hex(&tlv.to_bytes()[..tag_size]),
+        value: tlv.get_value().to_vec(),
+        children: Vec::new(),
+    }
+}
+
+/// Annotates the tag and the length of the data object that the first block of SET DATA starts with.
+fn annotate_first_block(data: &[u8]) -> DataAnnotation {
+    match parse_ber_tlv_header(data) {
+        Ok((tag, length, header_size)) => DataAnnotation::DataObjectBlock {
+            tag: format!("{:02X}", tag),
+            length,
+            value: data[header_size..].to_vec(),
+        },
+        Err(_) => DataAnnotation::Raw(data.to_vec()),
+    }
+}
+
+impl TraceEntry {
+    pub fn get_command(&self) -> &[u8] {
+        &self.command
+    }
+
+    pub fn get_response(&self) -> &[u8] {
+        &self.response
+    }
+
+    /// Returns the annotation; `None` for the unparseable entry.
+    pub fn get_apdu_annotation(&self) -> Option<&ApduAnnotation> {
+        match &self.annotation {
+            EntryAnnotation::Apdu(apdu) => Some(apdu.as_ref()),
+            EntryAnnotation::Unparseable { .. } => None,
+        }
+    }
+
+    /// Returns the reason why the pair cannot be parsed.
+    pub fn get_error(&self) -> Option<&str> {
+        match &self.annotation {
+            EntryAnnotation::Apdu(_) => None,
+            EntryAnnotation::Unparseable { error } => Some(error),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let headline = match &self.annotation {
+            EntryAnnotation::Apdu(apdu) => format!(
+                "channel {}: {} ({})",
+                apdu.logical_channel, apdu.instruction, apdu.parameters
+            ),
+            EntryAnnotation::Unparseable { error } => format!("unparseable: {}", error),
+        };
+        let mut lines = Vec::from([
+            headline,
+            format!("  C: {}", to_hex(&self.command)),
+            format!("  R: {}", to_hex(&self.response)),
+        ]);
+        if let EntryAnnotation::Apdu(apdu) = &self.annotation {
+            if let Some(data) = &apdu.command_data {
+                lines.push(format!("  command data: {}", data.to_text()));
+            }
+            if let Some(data) = &apdu.response_data {
+                lines.push(format!("  response data: {}", data.to_text()));
+            }
+            lines.push(format!("  SW '{}': {}", apdu.status_word, apdu.status));
+        }
+        lines.join("\n")
+    }
+}
+
+impl ApduAnnotation {
+    pub fn get_logical_channel(&self) -> u8 {
+        self.logical_channel
+    }
+
+    pub fn get_instruction(&self) -> &str {
+        &self.instruction
+    }
+
+    pub fn get_parameters(&self) -> &str {
+        &self.parameters
+    }
+
+    pub fn get_command_data(&self) -> Option<&DataAnnotation> {
+        self.command_data.as_ref()
+    }
+
+    pub fn get_response_data(&self) -> Option<&DataAnnotation> {
+        self.response_data.as_ref()
+    }
+
+    pub fn get_status(&self) -> &str {
+        &self.status
+    }
+}
+
+impl DataAnnotation {
+    pub fn to_text(&self) -> String {
+        match self {
+            DataAnnotation::Fcp(fcp) => {
+                let mut fields = Vec::new();
+                if let Some(file_id) = fcp.get_file_id() {
+                    fields.push(format!("file id {}", file_id));
+                }
+                if let Some(df_name) = fcp.get_df_name() {
+                    fields.push(format!("DF name {}", to_hex(df_name)));
+                }
+                let descriptor = fcp.get_file_descriptor();
+                fields.push(format!("{:?}", descriptor.get_file_type()));
+                if !fcp.is_df() {
+                    fields.push(format!("{:?}", descriptor.get_structure()));
+                }
+                if let (Some(length), Some(number)) = (
+                    descriptor.get_record_length(),
+                    descriptor.get_number_of_records(),
+                ) {
+                    fields.push(format!("{} records of {} bytes", number, length));
+                }
+                if let Some(size) = fcp.get_file_size() {
+                    fields.push(format!("size {}", size));
+                }
+                if let Some(status) = fcp.get_life_cycle_status() {
+                    fields.push(format!("{:?}", status));
+                }
+                if let Some(sfi) = fcp.get_short_file_id() {
+                    fields.push(format!("short file id {}", sfi));
+                }
+                format!("FCP {}", fields.join(", "))
+            }
+            DataAnnotation::BerTlv(tlvs) | DataAnnotation::ComprehensionTlv(tlvs) => tlvs
+                .iter()
+                .map(|tlv| tlv.to_text())
+                .collect::<Vec<_>>()
+                .join(" "),
+            DataAnnotation::DataObjectBlock { tag, length, value } => {
+                format!("data object {} of {} bytes: {}", tag, length, to_hex(value))
+            }
+            DataAnnotation::Raw(bytes) => to_hex(bytes),
+        }
+    }
+}
+
+impl TlvAnnotation {
+    pub fn get_tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn get_children(&self) -> &[TlvAnnotation] {
+        &self.children
+    }
+
+    fn to_text(&self) -> String {
+        if self.children.is_empty() {
+            return format!("{}={}", self.tag, to_hex(&self.value));
+        }
+        let children: Vec<String> = self.children.iter().map(|c| c.to_text()).collect();
+        format!("{}{{{}}}", self.tag, children.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::command_apdu::CommandAPDUError;
+    use crate::response_apdu::ResponseAPDUError;
+    use crate::trace::{
+        annotate_apdu, annotate_trace, trace_to_json, trace_to_text, DataAnnotation,
+    };
+
+    #[test]
+    fn should_annotate_trace() {
+        let entries = annotate_trace(&[
+            (
+                Vec::from([0x00, 0xa4, 0x00, 0x04, 0x02, 0x2f, 0x00, 0x00]),
+                Vec::from([
+                    0x62, 0x1a, 0x82, 0x05, 0x42, 0x21, 0x00, 0x26, 0x02, 0x83, 0x02, 0x2f, 0x00,
+                    0x8a, 0x01, 0x05, 0x8b, 0x03, 0x2f, 0x06, 0x01, 0x80, 0x02, 0x00, 0x4c, 0x88,
+                    0x01, 0xf0, 0x90, 0x00,
+                ]),
+            ),
+            (
+                Vec::from([
+                    0x81, 0xc2, 0x00, 0x00, 0x06, 0xd3, 0x04, 0x82, 0x02, 0x01, 0x81,
+                ]),
+                Vec::from([0x91, 0x10]),
+            ),
+            (
+                Vec::from([0x41, 0xb2, 0x01, 0x04, 0x26]),
+                Vec::from([0x6a, 0x83]),
+            ),
+        ]);
+        let apdu = |i: usize| entries[i].get_apdu_annotation().unwrap();
+
+        assert_eq!(apdu(0).get_instruction(), "SELECT FILE");
+        assert_eq!(
+            apdu(0).get_parameters(),
+            "P1 '00': select by file id; P2 '04': return FCP"
+        );
+        assert!(matches!(
+            apdu(0).get_response_data(),
+            Some(DataAnnotation::Fcp(_))
+        ));
+        assert_eq!(apdu(1).get_logical_channel(), 1);
+        assert_eq!(apdu(1).get_command_data().unwrap().to_text(), "D3{82=0181}");
+        assert_eq!(apdu(2).get_logical_channel(), 5);
+        assert_eq!(apdu(2).get_status(), "record not found");
+
+        let text = trace_to_text(&entries);
+        assert!(text.starts_with("[1] channel 0: SELECT FILE"));
+        assert!(text.contains(
+            "response data: FCP file id 2F00, WorkingEF, LinearFixed, 2 records of 38 bytes, size 76, \
+             OperationalActivated, short file id 30"
+        ));
+        assert!(text.contains(
+            "[2] channel 1: ENVELOPE (P1 '00' P2 '00')\n  C: 81C2000006D30482020181\n  R: 9110\n  \
+             command data: D3{82=0181}\n"
+        ));
+        assert!(text.contains("SW '9110': normal ending; proactive command of 16 bytes pending"));
+        assert!(text.ends_with("  SW '6A83': record not found"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&trace_to_json(&entries).unwrap()).unwrap();
+        assert_eq!(json[0]["response_data"]["format"], "Fcp");
+        assert_eq!(json[1]["instruction"], "ENVELOPE");
+        assert_eq!(json[1]["logical_channel"], 1);
+        assert_eq!(json[1]["command_data"]["format"], "BerTlv");
+        assert_eq!(json[1]["command_data"]["value"][0]["tag"], "D3");
+        assert_eq!(
+            json[1]["command_data"]["value"][0]["children"][0]["tag"],
+            "82"
+        );
+        assert_eq!(json[2]["status_word"], "6A83");
+        assert!(json[2].get("command_data").is_none());
+        assert!(json[2].get("error").is_none());
+    }
+
+    #[test]
+    fn should_keep_unparseable_entries() {
+        let entries = annotate_trace(&[
+            (Vec::from([0x00, 0xa4]), Vec::from([0x90, 0x00])),
+            (
+                Vec::from([0x80, 0xf2, 0x00, 0x0c, 0x00]),
+                Vec::from([0x90, 0x00]),
+            ),
+            (Vec::from([0x00, 0xb0, 0x00, 0x00, 0x02]), Vec::from([0x90])),
+        ]);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0].get_error(),
+            Some(
+                format!(
+                    "invalid command: {}",
+                    CommandAPDUError::MalformedCommandAPDU(2)
+                )
+                .as_str()
+            )
+        );
+        assert!(entries[0].get_apdu_annotation().is_none());
+        assert_eq!(
+            entries[1].get_apdu_annotation().unwrap().get_instruction(),
+            "STATUS"
+        );
+        assert_eq!(
+            entries[2].get_error(),
+            Some(
+                format!(
+                    "invalid response: {}",
+                    ResponseAPDUError::IllegalResponseLength(1)
+                )
+                .as_str()
+            )
+        );
+        assert_eq!(
+            annotate_apdu(&[0x00, 0xb0, 0x00, 0x00, 0x02], &[0x90]).unwrap_err(),
+            crate::trace::TraceError::InvalidResponse(ResponseAPDUError::IllegalResponseLength(1))
+        );
+
+        let text = trace_to_text(&entries);
+        assert!(text.starts_with("[1] unparseable: invalid command: "));
+        assert!(text.contains("\n  C: 00A4\n  R: 9000\n[2] channel 0: STATUS"));
+        assert!(text.contains("[3] unparseable: invalid response: "));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&trace_to_json(&entries).unwrap()).unwrap();
+        assert_eq!(json[0]["command"], "00A4");
+        assert_eq!(json[0]["response"], "9000");
+        assert!(json[0]["error"].is_string());
+        assert!(json[0].get("instruction").is_none());
+        assert_eq!(json[1]["instruction"], "STATUS");
+    }
+
+    #[test]
+    fn should_annotate_terminal_response_and_set_data() {
+        let entries = annotate_trace(&[
+            (
+                Vec::from([
+                    0x80, 0x14, 0x00, 0x00, 0x0c, 0x81, 0x03, 0x01, 0x21, 0x80, 0x82, 0x02, 0x82,
+                    0x81, 0x83, 0x01, 0x00,
+                ]),
+                Vec::from([0x90, 0x00]),
+            ),
+            (
+                Vec::from([
+                    0x80, 0xdb, 0x00, 0x00, 0x06, 0x5f, 0x20, 0x82, 0x01, 0x00, 0x41,
+                ]),
+                Vec::from([0x63, 0xf1]),
+            ),
+            (
+                Vec::from([0x80, 0xdb, 0x01, 0x00, 0x02, 0x42, 0x43]),
+                Vec::from([0x90, 0x00]),
+            ),
+        ]);
+        let apdu = |i: usize| entries[i].get_apdu_annotation().unwrap();
+
+        assert!(matches!(
+            apdu(0).get_command_data(),
+            Some(DataAnnotation::ComprehensionTlv(_))
+        ));
+        assert_eq!(
+            apdu(0).get_command_data().unwrap().to_text(),
+            "81=012180 82=8281 83=00"
+        );
+        assert_eq!(apdu(1).get_parameters(), "P1 '00': first block; P2 '00'");
+        assert_eq!(
+            apdu(1).get_command_data().unwrap().to_text(),
+            "data object 5F20 of 256 bytes: 41"
+        );
+        assert_eq!(apdu(2).get_command_data().unwrap().to_text(), "4243");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&trace_to_json(&entries).unwrap()).unwrap();
+        assert_eq!(json[0]["command_data"]["format"], "ComprehensionTlv");
+        assert_eq!(json[0]["command_data"]["value"][2]["tag"], "83");
+        assert_eq!(json[1]["command_data"]["format"], "DataObjectBlock");
+        assert_eq!(json[1]["command_data"]["value"]["tag"], "5F20");
+        assert_eq!(json[1]["command_data"]["value"]["length"], 256);
+        assert_eq!(json[2]["command_data"]["format"], "Raw");
+    }
+
+    #[test]
+    fn should_annotate_extended_channels_and_fcp_fallback() {
+        let status = |cla: u8, response: &[u8]| {
+            annotate_apdu(&[cla, 0xf2, 0x00, 0x00, 0x00], response).unwrap()
+        };
+
+        for (cla, channel) in [(0x83, 3), (0xc3, 7), (0xe3, 7), (0x4f, 19), (0x6f, 19)] {
+            let entry = status(cla, &[0x90, 0x00]);
+            assert_eq!(
+                entry.get_apdu_annotation().unwrap().get_logical_channel(),
+                channel
+            );
+        }
+
+        // the response that is not the FCP template is annotated as BER-TLV
+        let entry = status(0xc3, &[0x84, 0x02, 0xa0, 0x00, 0x90, 0x00]);
+        let apdu = entry.get_apdu_annotation().unwrap();
+        assert!(matches!(
+            apdu.get_response_data(),
+            Some(DataAnnotation::BerTlv(_))
+        ));
+        assert_eq!(apdu.get_response_data().unwrap().to_text(), "84=A000");
+        assert!(entry.to_text().starts_with("channel 7: STATUS"));
+
+        // and as it is when it is not BER-TLV either
+        let entry = status(0x80, &[0x62, 0x03, 0x82, 0x01, 0x90, 0x00]);
+        assert_eq!(
+            entry.get_apdu_annotation().unwrap().get_response_data(),
+            Some(&DataAnnotation::Raw(Vec::from([0x62, 0x03, 0x82, 0x01])))
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use thiserror::Error;
+
+use crate::ber_tlv::{find_ber_tlv, parse_ber_tlv, parse_ber_tlvs};
+use crate::fcp::FileControlParameters;
+
+/// The extended length information of ISO/IEC 7816-4; the maximum numbers of the bytes in the command and
+/// the response APDU.
+const EXTENDED_LENGTH_INFORMATION_TAG: u32 = 0x7f66;
+const PROPRIETARY_INFORMATION_TAG: u32 = 0xa5;
+
+/// Transport exchanges the raw APDU bytes with the UICC, e.g. by a PC/SC reader, a modem or a virtual card.
+pub trait Transport {
+    /// Sends the command APDU bytes and returns the response APDU bytes including SW1 and SW2.
+    fn transmit(&mut self, command: &[u8]) -> Result<Vec<u8>, TransportError>;
+
+    /// Returns whether the UICC accepts the extended Lc and Le, e.g. as the card capabilities in the ATR or
+    /// the FCP of the MF advertise; see [`is_extended_length_advertised`] and
+    /// [`is_extended_length_advertised_in_fcp`].
+    fn supports_extended_length(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TransportError {
+    #[error("failed to transmit the command APDU: {0}")]
+    TransmissionFailed(String),
+}
+
+/// Returns whether the third software function byte of the card capabilities (compact-TLV '7X') in the
+/// historical bytes of the ATR indicates the extended Lc and Le fields; ref 8.1.1.2.7 / ISO/IEC 7816-4
+pub fn is_extended_length_advertised(atr: &[u8]) -> bool {
+    if atr.len() < 2 {
+        return false;
+    }
+    let number_of_historical_bytes = (atr[1] & 0x0f) as usize;
+    let mut indicator = atr[1] >> 4;
+    let mut offset = 2;
+    loop {
+        offset += (indicator & 0b0111).count_ones() as usize;
+        if indicator & 0b1000 == 0 {
+            break;
+        }
+        match atr.get(offset) {
+            Some(td) => indicator = td >> 4,
+            None => return false,
+        }
+        offset += 1;
+    }
+    let historical_bytes = match atr.get(offset..offset + number_of_historical_bytes) {
+        Some(bytes) => bytes,
+        None => return false,
+    };
+    // category indicator '00' puts the status indicator of 3 bytes at the end, and '80' does not
+    let objects = match historical_bytes.split_first() {
+        Some((0x00, rest)) if rest.len() >= 3 => &rest[..rest.len() - 3],
+        Some((0x80, rest)) => rest,
+        _ => return false,
+    };
+    let mut i = 0;
+    while i < objects.len() {
+        let (tag, len) = (objects[i] >> 4, (objects[i] & 0x0f) as usize);
+        if tag == 0x7 && len >= 3 {
+            return objects.get(i + 3).is_some_and(|b| b & 0b01000000 != 0);
+        }
+        i += 1 + len;
+    }
+    false
+}
+
+/// Returns whether the FCP template, e.g. of the MF, has the extended length information (tag '7F66') at the
+/// top level or in the proprietary information (tag 'A5').
+pub fn is_extended_length_advertised_in_fcp(fcp: &FileControlParameters) -> bool {
+    let data_objects = match parse_ber_tlv(&fcp.to_bytes()).and_then(|(t, _)| t.get_children()) {
+        Ok(data_objects) => data_objects,
+        Err(_) => return false,
+    };
+    if find_ber_tlv(&data_objects, EXTENDED_LENGTH_INFORMATION_TAG).is_some() {
+        return true;
+    }
+    find_ber_tlv(&data_objects, PROPRIETARY_INFORMATION_TAG)
+        .and_then(|tlv| parse_ber_tlvs(tlv.get_value()).ok())
+        .is_some_and(|tlvs| find_ber_tlv(&tlvs, EXTENDED_LENGTH_INFORMATION_TAG).is_some())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::fcp::parse_fcp;
+    use crate::transport::{is_extended_length_advertised, is_extended_length_advertised_in_fcp};
+
+    #[test]
+    fn should_find_extended_length_in_atr() {
+        // TS, T0 (TD1 and 7 historical bytes), TD1 = T=0 with TD2, TD2 = T=15 with TA3, TA3, and the
+        // historical bytes '80' '73' card capabilities
+        let atr = [
+            0x3b, 0x87, 0x80, 0x1f, 0xc7, 0x80, 0x31, 0xa0, 0x73, 0xbe, 0x21, 0x13,
+        ];
+        assert!(!is_extended_length_advertised(&atr));
+        let atr = [
+            0x3b, 0x87, 0x80, 0x1f, 0xc7, 0x80, 0x31, 0xa0, 0x73, 0xbe, 0x21, 0x53,
+        ];
+        assert!(is_extended_length_advertised(&atr));
+        assert!(!is_extended_length_advertised(&[0x3b]));
+    }
+
+    #[test]
+    fn should_find_extended_length_in_fcp() {
+        // the FCP of the MF with the UICC characteristics and the extended length information of 1024
+        // bytes for both the command and the response in the proprietary information
+        let fcp = parse_fcp(&[
+            0x62, 0x1b, 0x82, 0x02, 0x78, 0x21, 0x83, 0x02, 0x3f, 0x00, 0xa5, 0x0e, 0x80, 0x01,
+            0x71, 0x7f, 0x66, 0x08, 0x02, 0x02, 0x04, 0x00, 0x02, 0x02, 0x04, 0x00, 0x8a, 0x01,
+            0x05,
+        ])
+        .unwrap();
+        assert!(is_extended_length_advertised_in_fcp(&fcp));
+
+        let fcp = parse_fcp(&[
+            0x62, 0x10, 0x82, 0x02, 0x78, 0x21, 0x83, 0x02, 0x3f, 0x00, 0xa5, 0x03, 0x80, 0x01,
+            0x71, 0x8a, 0x01, 0x05,
+        ])
+        .unwrap();
+        assert!(!is_extended_length_advertised_in_fcp(&fcp));
+    }
+}
@@ -0,0 +1,855 @@
+use anyhow::Result;
+
+use crate::authenticate::AuthenticationContext;
+use crate::ber_tlv::new_ber_tlv;
+use crate::fcp::{EFStructure, FileControlParameters};
+use crate::file::{
+    new_file_id, new_file_id_from_bytes, new_path, Path, CURRENT_ADF_ID, MASTER_FILE_ID,
+};
+use crate::milenage::{compute_kc, compute_sres, new_milenage_with_opc, Milenage};
+use crate::profile::{CardProfile, PinProfile};
+use crate::transport::{Transport, TransportError};
+use crate::walker::{FileContent, FileNode};
+
+const SW_SUCCESS: u16 = 0x9000;
+const SW_AUTHENTICATION_ERROR: u16 = 0x9862;
+const SW_WRONG_LENGTH: u16 = 0x6700;
+const SW_INCOMPATIBLE_FILE_STRUCTURE: u16 = 0x6981;
+const SW_SECURITY_STATUS_NOT_SATISFIED: u16 = 0x6982;
+const SW_AUTHENTICATION_METHOD_BLOCKED: u16 = 0x6983;
+const SW_COMMAND_NOT_ALLOWED: u16 = 0x6986;
+const SW_FILE_NOT_FOUND: u16 = 0x6a82;
+const SW_RECORD_NOT_FOUND: u16 = 0x6a83;
+const SW_INCORRECT_P1_P2: u16 = 0x6a86;
+const SW_REFERENCED_DATA_NOT_FOUND: u16 = 0x6a88;
+const SW_WRONG_PARAMETERS: u16 = 0x6b00;
+const SW_INSTRUCTION_NOT_SUPPORTED: u16 = 0x6d00;
+
+struct VirtualFile {
+    path: Path,
+    application: Option<usize>,
+    fcp: FileControlParameters,
+    content: Option<FileContent>,
+}
+
+struct VirtualApplication {
+    aid: Vec<u8>,
+    root: usize,
+}
+
+/// VirtualUICC is a software UICC that serves a `CardProfile` through the `Transport` interface.
+///
+/// It answers SELECT, STATUS, READ/UPDATE BINARY, READ/UPDATE RECORD, VERIFY PIN, AUTHENTICATE and GET
+/// RESPONSE. The access conditions are not evaluated except that the content which could not be read on dumping is
+/// answered by '6982' security status not satisfied. AUTHENTICATE is answered by Milenage in GSM and 3G
+/// context when the profile has the authentication parameters. The GSM context is answered as a USIM does, i.e.
+/// SRES and Kc are derived from the 3G parameters by c2 and c3; the GSM algorithms of a SIM such as COMP128 are
+/// not supported.
+pub struct VirtualUICC {
+    files: Vec<VirtualFile>,
+    applications: Vec<VirtualApplication>,
+    pins: Vec<PinProfile>,
+    milenage: Option<Milenage>,
+    sqn: [u8; 6],
+    current_application: Option<usize>,
+    current_df: usize,
+    current_ef: Option<usize>,
+    pending_response: Vec<u8>,
+}
+
+/// CommandParts: a short command APDU split into the fields; ref 10.1 / ETSI TS 102 221 V15.0.0
+struct CommandParts<'a> {
+    ins: u8,
+    p1: u8,
+    p2: u8,
+    data: &'a [u8],
+    le: Option<usize>,
+}
+
+pub fn new_virtual_uicc(profile: &CardProfile) -> VirtualUICC {
+    let mut uicc = VirtualUICC {
+        files: Vec::new(),
+        applications: Vec::new(),
+        pins: profile.get_pins().to_vec(),
+        milenage: profile.get_authentication().map(|authentication| {
+            new_milenage_with_opc(authentication.get_k(), authentication.get_opc())
+        }),
+        sqn: profile
+            .get_authentication()
+            .map(|authentication| *authentication.get_sqn())
+            .unwrap_or_default(),
+        current_application: None,
+        current_df: 0,
+        current_ef: None,
+        pending_response: Vec::new(),
+    };
+
+    let file_system = profile.get_file_system();
+    uicc.add_files(file_system.get_master_file(), None);
+    for application in file_system.get_applications() {
+        let index = uicc.applications.len();
+        let root = uicc.files.len();
+        uicc.applications.push(VirtualApplication {
+            aid: application.get_template().get_aid().to_vec(),
+            root,
+        });
+        uicc.add_files(application.get_root(), Some(index));
+    }
+
+    uicc
+}
+
+fn parse_command(command: &[u8]) -> Option<CommandParts<'_>> {
+    if command.len() < 4 {
+        return None;
+    }
+    let (data, le) = match command.len() {
+        4 => (&command[4..4], None),
+        5 => (&command[5..5], Some(command[4] as usize)),
+        len => {
+            let lc = command[4] as usize;
+            match len - 5 {
+                rest if rest == lc => (&command[5..5 + lc], None),
+                rest if rest == lc + 1 => (&command[5..5 + lc], Some(command[len - 1] as usize)),
+                _ => return None,
+            }
+        }
+    };
+
+    Some(CommandParts {
+        ins: command[1],
+        p1: command[2],
+        p2: command[3],
+        data,
+        // Le = '00' means the maximum length of the short APDU
+        le: le.map(|le| if le == 0 { 256 } else { le }),
+    })
+}
+
+impl VirtualUICC {
+    fn add_files(&mut self, node: &FileNode, application: Option<usize>) {
+        self.files.push(VirtualFile {
+            path: node.get_path().clone(),
+            application,
+            fcp: node.get_fcp().clone(),
+            content: node.get_content().cloned(),
+        });
+        for child in node.get_children() {
+            self.add_files(child, application);
+        }
+    }
+
+    fn find(&self, application: Option<usize>, path: &Path) -> Option<usize> {
+        let in_adf = path.get_file_ids().get(1).map(|id| id.is_current_adf()) == Some(true);
+        // the files under an ADF are reachable only while the application is the current one
+        let application = match (in_adf, application) {
+            (true, None) => return None,
+            (true, application) => application,
+            (false, _) => None,
+        };
+        self.files
+            .iter()
+            .position(|file| file.application == application && &file.path == path)
+    }
+
+    fn process(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        match command.ins {
+            0xa4 => self.select(command),
+            0xb0 => self.read_binary(command),
+            0xd6 => self.update_binary(command),
+            0xb2 => self.read_record(command),
+            0xdc => self.update_record(command),
+            0x20 => self.verify_pin(command),
+            0x88 => self.authenticate(command),
+            0xf2 => self.status(command),
+            0xc0 => {
+                let len = command.le.unwrap_or(0).min(self.pending_response.len());
+                let data = self.pending_response.drain(..len).collect();
+                (data, SW_SUCCESS)
+            }
+            _ => (Vec::new(), SW_INSTRUCTION_NOT_SUPPORTED),
+        }
+    }
+
+    fn select(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        let current_df_path = self.files[self.current_df].path.clone();
+        let ids = || {
+            command
+                .data
+                .chunks(2)
+                .map(new_file_id_from_bytes)
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+        };
+
+        let found = match command.p1 {
+            0x00 => {
+                let ids = match ids() {
+                    Some(ids) => ids,
+                    None => return (Vec::new(), SW_FILE_NOT_FOUND),
+                };
+                if ids.len() != 1 {
+                    return (Vec::new(), SW_WRONG_LENGTH);
+                }
+                let id = ids[0];
+                let mut candidates = Vec::new();
+                if id.get_value() == MASTER_FILE_ID {
+                    candidates.push(new_path(Vec::from([id])));
+                } else if id.get_value() == CURRENT_ADF_ID {
+                    candidates.push(new_path(Vec::from([
+                        new_file_id(MASTER_FILE_ID).unwrap(),
+                        id,
+                    ])));
+                } else {
+                    let df_ids = current_df_path.get_file_ids();
+                    candidates.push(current_df_path.join(id));
+                    if df_ids.last() == Some(&id) {
+                        candidates.push(Ok(current_df_path.clone()));
+                    }
+                    if df_ids.len() > 1 && df_ids[df_ids.len() - 2] == id {
+                        candidates.push(new_path(df_ids[..df_ids.len() - 1].to_vec()));
+                    }
+                }
+                candidates
+                    .into_iter()
+                    .flatten()
+                    .find_map(|path| self.find(self.current_application, &path))
+            }
+            0x03 => {
+                let df_ids = current_df_path.get_file_ids();
+                let parent = &df_ids[..df_ids.len().saturating_sub(1).max(1)];
+                new_path(parent.to_vec())
+                    .ok()
+                    .and_then(|path| self.find(self.current_application, &path))
+            }
+            0x04 => {
+                if command.data.is_empty() {
+                    return (Vec::new(), SW_WRONG_LENGTH);
+                }
+                match self
+                    .applications
+                    .iter()
+                    .position(|app| app.aid.starts_with(command.data))
+                {
+                    Some(index) => {
+                        self.current_application = Some(index);
+                        Some(self.applications[index].root)
+                    }
+                    None => None,
+                }
+            }
+            0x08 | 0x09 => {
+                let mut path_ids = if command.p1 == 0x08 {
+                    Vec::from([new_file_id(MASTER_FILE_ID).unwrap()])
+                } else {
+                    current_df_path.get_file_ids().to_vec()
+                };
+                match ids() {
+                    Some(ids) => path_ids.extend(ids),
+                    None => return (Vec::new(), SW_FILE_NOT_FOUND),
+                }
+                new_path(path_ids)
+                    .ok()
+                    .and_then(|path| self.find(self.current_application, &path))
+            }
+            _ => return (Vec::new(), SW_INCORRECT_P1_P2),
+        };
+
+        let index = match found {
+            Some(index) => index,
+            None => return (Vec::new(), SW_FILE_NOT_FOUND),
+        };
+        if self.files[index].fcp.is_df() {
+            self.current_df = index;
+            self.current_ef = None;
+        } else {
+            let file = &self.files[index];
+            let ids = file.path.get_file_ids();
+            self.current_df = new_path(ids[..ids.len() - 1].to_vec())
+                .ok()
+                .and_then(|parent| self.find(file.application, &parent))
+                .unwrap_or(0);
+            self.current_ef = Some(index);
+        }
+
+        if command.p2 & 0x0c == 0x0c {
+            return (Vec::new(), SW_SUCCESS);
+        }
+        let fcp = self.files[index].fcp.to_bytes();
+        self.respond(command, fcp)
+    }
+
+    /// Answers the data directly if Le is present, otherwise keeps it for GET RESPONSE by '61XX'.
+    fn respond(&mut self, command: &CommandParts, data: Vec<u8>) -> (Vec<u8>, u16) {
+        match command.le {
+            Some(_) => (data, SW_SUCCESS),
+            None => {
+                let len = data.len();
+                self.pending_response = data;
+                (Vec::new(), 0x6100 | len.min(0xff) as u16)
+            }
+        }
+    }
+
+    /// Selects the EF by the short file identifier in the current DF.
+    fn select_by_short_file_id(&mut self, sfi: u8) -> Option<usize> {
+        let df_path = &self.files[self.current_df].path;
+        let application = self.files[self.current_df].application;
+        let index = self.files.iter().position(|file| {
+            file.application == application
+                && file.fcp.get_short_file_id() == Some(sfi)
+                && file.path.get_file_ids().len() == df_path.get_file_ids().len() + 1
+                && file.path.get_file_ids().starts_with(df_path.get_file_ids())
+        })?;
+        self.current_ef = Some(index);
+        Some(index)
+    }
+
+    fn current_ef_with_structure(
+        &mut self,
+        sfi: Option<u8>,
+        structures: &[EFStructure],
+    ) -> Result<usize, u16> {
+        let index = match sfi {
+            Some(sfi) => self.select_by_short_file_id(sfi).ok_or(SW_FILE_NOT_FOUND)?,
+            None => self.current_ef.ok_or(SW_COMMAND_NOT_ALLOWED)?,
+        };
+        let file = &self.files[index];
+        if !structures.contains(&file.fcp.get_file_descriptor().get_structure()) {
+            return Err(SW_INCOMPATIBLE_FILE_STRUCTURE);
+        }
+        if let Some(FileContent::NotRead { .. }) = file.content {
+            return Err(SW_SECURITY_STATUS_NOT_SATISFIED);
+        }
+        Ok(index)
+    }
+
+    fn binary_target(&mut self, command: &CommandParts) -> Result<(usize, usize), u16> {
+        let (sfi, offset) = if command.p1 & 0x80 != 0 {
+            (Some(command.p1 & 0x1f), command.p2 as usize)
+        } else {
+            (None, u16::from_be_bytes([command.p1, command.p2]) as usize)
+        };
+        let index = self.current_ef_with_structure(sfi, &[EFStructure::Transparent])?;
+        Ok((index, offset))
+    }
+
+    fn read_binary(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        let (index, offset) = match self.binary_target(command) {
+            Ok(target) => target,
+            Err(sw) => return (Vec::new(), sw),
+        };
+        let data = match &self.files[index].content {
+            Some(FileContent::Transparent { data }) => data,
+            _ => return (Vec::new(), SW_SECURITY_STATUS_NOT_SATISFIED),
+        };
+        if offset >= data.len() {
+            return (Vec::new(), SW_WRONG_PARAMETERS);
+        }
+        let end = (offset + command.le.unwrap_or(256)).min(data.len());
+        (data[offset..end].to_vec(), SW_SUCCESS)
+    }
+
+    fn update_binary(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        let (index, offset) = match self.binary_target(command) {
+            Ok(target) => target,
+            Err(sw) => return (Vec::new(), sw),
+        };
+        let size = self.files[index].fcp.get_file_size().unwrap_or(0) as usize;
+        if offset + command.data.len() > size {
+            return (Vec::new(), SW_WRONG_PARAMETERS);
+        }
+        let file = &mut self.files[index];
+        if !matches!(file.content, Some(FileContent::Transparent { .. })) {
+            file.content = Some(FileContent::Transparent {
+                data: vec![0xff; size],
+            });
+        }
+        if let Some(FileContent::Transparent { data }) = &mut file.content {
+            data.resize(size.max(data.len()), 0xff);
+            data[offset..offset + command.data.len()].copy_from_slice(command.data);
+        }
+        (Vec::new(), SW_SUCCESS)
+    }
+
+    fn record_target(&mut self, command: &CommandParts) -> Result<(usize, usize), u16> {
+        // only the absolute mode is supported
+        if command.p2 & 0b111 != 0b100 || command.p1 == 0 {
+            return Err(SW_INCORRECT_P1_P2);
+        }
+        let sfi = match command.p2 >> 3 {
+            0 => None,
+            sfi => Some(sfi),
+        };
+        let index =
+            self.current_ef_with_structure(sfi, &[EFStructure::LinearFixed, EFStructure::Cyclic])?;
+        let number_of_records = self.files[index]
+            .fcp
+            .get_file_descriptor()
+            .get_number_of_records()
+            .unwrap_or(0);
+        if command.p1 > number_of_records {
+            return Err(SW_RECORD_NOT_FOUND);
+        }
+        Ok((index, command.p1 as usize - 1))
+    }
+
+    fn read_record(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        let (index, record_index) = match self.record_target(command) {
+            Ok(target) => target,
+            Err(sw) => return (Vec::new(), sw),
+        };
+        match &self.files[index].content {
+            Some(FileContent::Records { records }) => match records.get(record_index) {
+                Some(record) => (record.clone(), SW_SUCCESS),
+                None => (Vec::new(), SW_RECORD_NOT_FOUND),
+            },
+            _ => (Vec::new(), SW_SECURITY_STATUS_NOT_SATISFIED),
+        }
+    }
+
+    fn update_record(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        let (index, record_index) = match self.record_target(command) {
+            Ok(target) => target,
+            Err(sw) => return (Vec::new(), sw),
+        };
+        let descriptor = self.files[index].fcp.get_file_descriptor();
+        let record_length = descriptor.get_record_length().unwrap_or(0) as usize;
+        let number_of_records = descriptor.get_number_of_records().unwrap_or(0) as usize;
+        if command.data.len() != record_length {
+            return (Vec::new(), SW_WRONG_LENGTH);
+        }
+        let file = &mut self.files[index];
+        if !matches!(file.content, Some(FileContent::Records { .. })) {
+            file.content = Some(FileContent::Records {
+                records: vec![vec![0xff; record_length]; number_of_records],
+            });
+        }
+        if let Some(FileContent::Records { records }) = &mut file.content {
+            records.resize(number_of_records, vec![0xff; record_length]);
+            records[record_index] = command.data.to_vec();
+        }
+        (Vec::new(), SW_SUCCESS)
+    }
+
+    fn verify_pin(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        let pin = match self
+            .pins
+            .iter_mut()
+            .find(|pin| pin.get_key_reference() == command.p2)
+        {
+            Some(pin) => pin,
+            None => return (Vec::new(), SW_REFERENCED_DATA_NOT_FOUND),
+        };
+        if pin.get_remaining_attempts() == 0 {
+            return (Vec::new(), SW_AUTHENTICATION_METHOD_BLOCKED);
+        }
+        if command.data.is_empty() {
+            return (Vec::new(), 0x63c0 | pin.get_remaining_attempts() as u16);
+        }
+        if !pin.is_enabled() || pin.get_value() == command.data {
+            pin.reset_attempts();
+            return (Vec::new(), SW_SUCCESS);
+        }
+
+        pin.consume_attempt();
+        match pin.get_remaining_attempts() {
+            0 => (Vec::new(), SW_AUTHENTICATION_METHOD_BLOCKED),
+            remaining => (Vec::new(), 0x63c0 | remaining as u16),
+        }
+    }
+
+    fn status(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        if command.p1 > 0x02 {
+            return (Vec::new(), SW_INCORRECT_P1_P2);
+        }
+        match command.p2 {
+            0x00 => {
+                let fcp = self.files[self.current_df].fcp.to_bytes();
+                self.respond(command, fcp)
+            }
+            0x01 => match self.current_application {
+                Some(application) => {
+                    let aid = self.applications[application].aid.clone();
+                    let tlv = new_ber_tlv(0x84, aid).to_bytes();
+                    self.respond(command, tlv)
+                }
+                None => (Vec::new(), SW_REFERENCED_DATA_NOT_FOUND),
+            },
+            0x0c => (Vec::new(), SW_SUCCESS),
+            _ => (Vec::new(), SW_INCORRECT_P1_P2),
+        }
+    }
+
+    fn authenticate(&mut self, command: &CommandParts) -> (Vec<u8>, u16) {
+        let milenage = match &self.milenage {
+            Some(milenage) => milenage,
+            None => return (Vec::new(), SW_REFERENCED_DATA_NOT_FOUND),
+        };
+        let gsm = 0x80 | AuthenticationContext::Gsm as u8;
+        let umts = 0x80 | AuthenticationContext::Umts as u8;
+        if command.p1 != 0x00 || (command.p2 != gsm && command.p2 != umts) {
+            return (Vec::new(), SW_INCORRECT_P1_P2);
+        }
+
+        let data = command.data;
+        if data.len() < 17 || data[0] != 0x10 {
+            return (Vec::new(), SW_WRONG_LENGTH);
+        }
+        let rand: [u8; 16] = data[1..17].try_into().unwrap();
+        let output = milenage.f2345(&rand);
+
+        // a USIM in GSM context: ref 6.8.1.2 / 3GPP TS 33.102 and 7.1.2.1 / ETSI TS 131 102
+        if command.p2 == gsm {
+            let mut response = Vec::from([0x04]);
+            response.extend_from_slice(&compute_sres(output.get_res()));
+            response.push(0x08);
+            response.extend_from_slice(&compute_kc(output.get_ck(), output.get_ik()));
+            return self.respond(command, response);
+        }
+
+        if data.len() != 34 || data[17] != 0x10 {
+            return (Vec::new(), SW_WRONG_LENGTH);
+        }
+        let autn = &data[18..34];
+        let mut sqn = [0u8; 6];
+        for (i, b) in sqn.iter_mut().enumerate() {
+            *b = autn[i] ^ output.get_ak()[i];
+        }
+        let amf: [u8; 2] = autn[6..8].try_into().unwrap();
+        if milenage.f1(&rand, &sqn, &amf) != autn[8..] {
+            return (Vec::new(), SW_AUTHENTICATION_ERROR);
+        }
+
+        // the sequence number must be fresh; this does not model the array scheme of Annex C / 3GPP TS 33.102
+        if sqn <= self.sqn {
+            let mut response = Vec::from([0xdc, 0x0e]);
+            response.extend_from_slice(&milenage.generate_auts(&rand, &self.sqn));
+            return self.respond(command, response);
+        }
+        self.sqn = sqn;
+
+        let mut response = Vec::from([0xdb, 0x08]);
+        response.extend_from_slice(output.get_res());
+        response.push(0x10);
+        response.extend_from_slice(output.get_ck());
+        response.push(0x10);
+        response.extend_from_slice(output.get_ik());
+        response.push(0x08);
+        response.extend_from_slice(&compute_kc(output.get_ck(), output.get_ik()));
+        self.respond(command, response)
+    }
+}
+
+impl Transport for VirtualUICC {
+    fn transmit(&mut self, command: &[u8]) -> Result<Vec<u8>, TransportError> {
+        let (mut data, sw) = match parse_command(command) {
+            Some(parts) => self.process(&parts),
+            None => (Vec::new(), SW_WRONG_LENGTH),
+        };
+        data.extend_from_slice(&sw.to_be_bytes());
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::authenticate::{
+        new_gsm_authenticate_command, new_umts_authenticate_command, AuthenticateResponse,
+    };
+    use crate::class::{
+        new_standard_class, ClassTypeForStandardLogicalChannels,
+        SecureMessagingIndicationForStandardLogicalChannels,
+    };
+    use crate::milenage::{compute_sres, new_milenage_with_opc};
+    use crate::profile::{new_authentication_profile, new_card_profile, parse_card_profile_json};
+    use crate::response_apdu::parse_response_apdu;
+    use crate::transport::Transport;
+    use crate::virtual_uicc::new_virtual_uicc;
+    use crate::walker::{new_file_system_walker, WalkerConfig};
+
+    const PROFILE_JSON: &str = r#"{
+  "version": 1,
+  "file_system": {
+    "master_file": {
+      "path": "3F00",
+      "fcp": {
+        "file_descriptor": { "shareable": true, "file_type": "DFOrADF", "structure": "NoInformation" },
+        "file_id": "3F00",
+        "life_cycle_status_integer": 5,
+        "pin_status_template": "90017083010183010A"
+      },
+      "children": [
+        {
+          "path": "3F00/2F00",
+          "fcp": {
+            "file_descriptor": { "shareable": true, "file_type": "WorkingEF", "structure": "LinearFixed",
+                                 "record_length": 20, "number_of_records": 1 },
+            "file_id": "2F00",
+            "short_file_id": 30
+          },
+          "content": { "kind": "Records", "records": ["610F4F07A000000087100250045553494DFFFFFF"] }
+        },
+        {
+          "path": "3F00/2FE2",
+          "fcp": {
+            "file_descriptor": { "shareable": true, "file_type": "WorkingEF", "structure": "Transparent" },
+            "file_id": "2FE2",
+            "security_attributes": { "format": "Referenced", "value": "2F0601" },
+            "file_size": 10
+          },
+          "content": { "kind": "Transparent", "data": "98940000000000000001" }
+        }
+      ]
+    },
+    "applications": [
+      {
+        "template": { "aid": "A0000000871002", "label": "USIM" },
+        "root": {
+          "path": "3F00/7FFF",
+          "fcp": {
+            "file_descriptor": { "shareable": true, "file_type": "DFOrADF", "structure": "NoInformation" },
+            "df_name": "A0000000871002"
+          },
+          "children": [
+            {
+              "path": "3F00/7FFF/6F07",
+              "fcp": {
+                "file_descriptor": { "shareable": true, "file_type": "WorkingEF", "structure": "Transparent" },
+                "file_id": "6F07",
+                "file_size": 9
+              },
+              "content": { "kind": "Transparent", "data": "080910101032547698" }
+            },
+            {
+              "path": "3F00/7FFF/6F08",
+              "fcp": {
+                "file_descriptor": { "shareable": false, "file_type": "WorkingEF", "structure": "Transparent" },
+                "file_id": "6F08",
+                "file_size": 33
+              },
+              "content": { "kind": "NotRead", "status_word": 27010 }
+            }
+          ]
+        }
+      }
+    ]
+  },
+  "pins": [
+    { "key_reference": 1, "value": "31323334FFFFFFFF", "remaining_attempts": 3,
+      "remaining_unblock_attempts": 10, "enabled": true }
+  ]
+}"#;
+
+    #[test]
+    fn should_clone_file_system_losslessly() {
+        let profile = parse_card_profile_json(PROFILE_JSON).unwrap();
+        let mut uicc = new_virtual_uicc(&profile);
+
+        let tree = new_file_system_walker(&mut uicc, WalkerConfig::default())
+            .walk()
+            .unwrap();
+        let dumped = new_card_profile(tree, profile.get_pins().to_vec());
+
+        // the dumped profile keeps the FCP templates, so that the second dump is the same
+        let mut uicc =
+            new_virtual_uicc(&parse_card_profile_json(&dumped.to_json().unwrap()).unwrap());
+        let tree = new_file_system_walker(&mut uicc, WalkerConfig::default())
+            .walk()
+            .unwrap();
+        assert_eq!(new_card_profile(tree, profile.get_pins().to_vec()), dumped);
+        assert_eq!(
+            dumped
+                .get_file_system()
+                .get_master_file()
+                .get_fcp()
+                .to_bytes(),
+            profile
+                .get_file_system()
+                .get_master_file()
+                .get_fcp()
+                .to_bytes()
+        );
+    }
+
+    #[test]
+    fn should_answer_file_access_commands() {
+        let profile = parse_card_profile_json(PROFILE_JSON).unwrap();
+        let mut uicc = new_virtual_uicc(&profile);
+
+        // SELECT USIM by partial AID, then EF.IMSI by file id without Le
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xa4, 0x04, 0x0c, 0x05, 0xa0, 0x00, 0x00, 0x00, 0x87])
+                .unwrap(),
+            Vec::from([0x90, 0x00])
+        );
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xa4, 0x00, 0x04, 0x02, 0x6f, 0x07])
+                .unwrap(),
+            Vec::from([0x61, 0x0e])
+        );
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xc0, 0x00, 0x00, 0x0e]).unwrap()[..2],
+            [0x62, 0x0c]
+        );
+
+        // READ BINARY and UPDATE BINARY
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xb0, 0x00, 0x07, 0x00]).unwrap(),
+            Vec::from([0x76, 0x98, 0x90, 0x00])
+        );
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xd6, 0x00, 0x08, 0x01, 0x99])
+                .unwrap(),
+            Vec::from([0x90, 0x00])
+        );
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xb0, 0x00, 0x08, 0x01]).unwrap(),
+            Vec::from([0x99, 0x90, 0x00])
+        );
+
+        // content that was not readable on dumping
+        uicc.transmit(&[0x00, 0xa4, 0x00, 0x0c, 0x02, 0x6f, 0x08])
+            .unwrap();
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xb0, 0x00, 0x00, 0x21]).unwrap(),
+            Vec::from([0x69, 0x82])
+        );
+
+        // READ RECORD by short file id of EF.DIR
+        uicc.transmit(&[0x00, 0xa4, 0x00, 0x0c, 0x02, 0x3f, 0x00])
+            .unwrap();
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xb2, 0x01, 0xf4, 0x14]).unwrap()[..2],
+            [0x61, 0x0f]
+        );
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xb2, 0x02, 0xf4, 0x14]).unwrap(),
+            Vec::from([0x6a, 0x83])
+        );
+        assert_eq!(
+            uicc.transmit(&[0x00, 0xa4, 0x00, 0x0c, 0x02, 0x6f, 0x99])
+                .unwrap(),
+            Vec::from([0x6a, 0x82])
+        );
+    }
+
+    #[test]
+    fn should_verify_pin() {
+        let profile = parse_card_profile_json(PROFILE_JSON).unwrap();
+        let mut uicc = new_virtual_uicc(&profile);
+
+        let wrong = [
+            0x00, 0x20, 0x00, 0x01, 0x08, 0x30, 0x30, 0x30, 0x30, 0xff, 0xff, 0xff, 0xff,
+        ];
+        let right = [
+            0x00, 0x20, 0x00, 0x01, 0x08, 0x31, 0x32, 0x33, 0x34, 0xff, 0xff, 0xff, 0xff,
+        ];
+        assert_eq!(uicc.transmit(&wrong).unwrap(), Vec::from([0x63, 0xc2]));
+        assert_eq!(
+            uicc.transmit(&[0x00, 0x20, 0x00, 0x01]).unwrap(),
+            Vec::from([0x63, 0xc2])
+        );
+        assert_eq!(uicc.transmit(&right).unwrap(), Vec::from([0x90, 0x00]));
+        assert_eq!(
+            uicc.transmit(&[0x00, 0x20, 0x00, 0x01]).unwrap(),
+            Vec::from([0x63, 0xc3])
+        );
+        assert_eq!(
+            uicc.transmit(&[0x00, 0x20, 0x00, 0x81]).unwrap(),
+            Vec::from([0x6a, 0x88])
+        );
+    }
+
+    #[test]
+    fn should_answer_authenticate_by_milenage() {
+        let k = [0x46; 16];
+        let opc = [0xcd; 16];
+        let mut profile = parse_card_profile_json(PROFILE_JSON).unwrap();
+        profile.set_authentication(Some(new_authentication_profile(&k, &opc, &[0x00; 6])));
+        let mut uicc = new_virtual_uicc(&profile);
+        let class = new_standard_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+            SecureMessagingIndicationForStandardLogicalChannels::NoSM,
+            0,
+        )
+        .unwrap();
+
+        let network = new_milenage_with_opc(&k, &opc);
+        let rand = [0x23; 16];
+        let sqn = [0x00, 0x00, 0x00, 0x00, 0x00, 0x20];
+        let autn = network.generate_autn(&rand, &sqn, &[0x80, 0x00]);
+        let expected = network.f2345(&rand);
+
+        let command = new_umts_authenticate_command(&rand, &autn);
+        let response = uicc
+            .transmit(&command.to_command_apdu(&class).to_bytes().unwrap())
+            .unwrap();
+        let response = parse_response_apdu(&response).unwrap();
+        assert_eq!(response.get_status_word(), 0x9000);
+        match command.parse_response(response.get_data()).unwrap() {
+            AuthenticateResponse::Umts { res, ck, ik, kc } => {
+                assert_eq!(res, expected.get_res());
+                assert_eq!(ck, expected.get_ck());
+                assert_eq!(ik, expected.get_ik());
+                assert!(kc.is_some());
+            }
+            r => panic!("unexpected response: {:?}", r),
+        }
+
+        // replayed AUTN is rejected by the synchronisation failure, and then MAC is checked
+        let response = uicc
+            .transmit(&command.to_command_apdu(&class).to_bytes().unwrap())
+            .unwrap();
+        let response = parse_response_apdu(&response).unwrap();
+        assert_eq!(
+            command.parse_response(response.get_data()).unwrap(),
+            AuthenticateResponse::SynchronisationFailure {
+                auts: network.generate_auts(&rand, &sqn).to_vec()
+            }
+        );
+        let mut forged = autn;
+        forged[15] ^= 0x01;
+        let command = new_umts_authenticate_command(&rand, &forged);
+        assert_eq!(
+            uicc.transmit(&command.to_command_apdu(&class).to_bytes().unwrap())
+                .unwrap(),
+            Vec::from([0x98, 0x62])
+        );
+
+        let command = new_gsm_authenticate_command(&rand);
+        let response = uicc
+            .transmit(&command.to_command_apdu(&class).to_bytes().unwrap())
+            .unwrap();
+        let response = parse_response_apdu(&response).unwrap();
+        match command.parse_response(response.get_data()).unwrap() {
+            AuthenticateResponse::Gsm { sres, .. } => {
+                assert_eq!(sres, compute_sres(expected.get_res()))
+            }
+            r => panic!("unexpected response: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn should_answer_status() {
+        let profile = parse_card_profile_json(PROFILE_JSON).unwrap();
+        let mut uicc = new_virtual_uicc(&profile);
+
+        assert_eq!(
+            uicc.transmit(&[0x80, 0xf2, 0x00, 0x01, 0x00]).unwrap(),
+            Vec::from([0x6a, 0x88])
+        );
+        uicc.transmit(&[
+            0x00, 0xa4, 0x04, 0x0c, 0x07, 0xa0, 0x00, 0x00, 0x00, 0x87, 0x10, 0x02,
+        ])
+        .unwrap();
+        assert_eq!(
+            uicc.transmit(&[0x80, 0xf2, 0x01, 0x01, 0x00]).unwrap(),
+            Vec::from([0x84, 0x07, 0xa0, 0x00, 0x00, 0x00, 0x87, 0x10, 0x02, 0x90, 0x00])
+        );
+        assert_eq!(
+            uicc.transmit(&[0x80, 0xf2, 0x00, 0x0c]).unwrap(),
+            Vec::from([0x90, 0x00])
+        );
+    }
+}
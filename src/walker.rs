@@ -0,0 +1,623 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::class::{new_basic_class, ClassTypeForStandardLogicalChannels};
+use crate::command_apdu::{new_command_apdu, CommandAPDU};
+use crate::ef_dir::{parse_ef_dir_record, ApplicationTemplate, EfDirError, EF_DIR_FILE_ID};
+use crate::fcp::{parse_fcp, EFStructure, FcpError, FileControlParameters};
+use crate::file::{
+    new_file_id, new_path, FileError, Path, CURRENT_ADF_ID, MASTER_FILE_ID,
+    RESERVED_FUTURE_USE_FILE_ID, RESERVED_PATH_FILE_ID,
+};
+use crate::hex::{hex_bytes, hex_bytes_list};
+use crate::instruction::GetResponse;
+use crate::read_binary::new_read_binary_command;
+use crate::read_record::{new_read_record_command, ReadRecordError, RecordMode};
+use crate::response_apdu::ResponseAPDU;
+use crate::select_file::{new_select_file_command, FileSelection, SelectResponse};
+use crate::session::{new_session, Session, SessionConfig, SessionError};
+use crate::transport::Transport;
+
+/// WalkerConfig controls which file identifiers are probed by the `FileSystemWalker`.
+pub struct WalkerConfig {
+    /// File identifiers that are probed in every DF of the matching level.
+    pub known_file_ids: Vec<u16>,
+    /// Probes every file identifier of the level in addition to the known ones, e.g. '6F00' to '6FFF' under the ADF.
+    pub brute_force: bool,
+    /// Maximum depth of the DFs to explore; the MF and the ADFs are at depth 0.
+    pub max_depth: usize,
+    /// Reads the contents of the EFs if the access conditions allow it.
+    pub read_contents: bool,
+}
+
+impl Default for WalkerConfig {
+    fn default() -> Self {
+        WalkerConfig {
+            known_file_ids: Vec::from([
+                // EF.DIR, EF.ICCID, EF.PL, EF.ARR, EF.UMPC
+                0x2f00, 0x2fe2, 0x2f05, 0x2f06, 0x2f08, // DF.TELECOM, DF.GSM
+                0x7f10, 0x7f20,
+                // EF.IMSI, EF.KEYS, EF.ARR, EF.UST, EF.SPN, EF.ACC, EF.FPLMN, EF.LOCI, EF.AD, EF.PSLOCI, EF.EPSLOCI
+                0x6f07, 0x6f08, 0x6f06, 0x6f38, 0x6f46, 0x6f78, 0x6f7b, 0x6f7e, 0x6fad, 0x6f73,
+                0x6fe3, // EF.ADN, EF.SMS, EF.MSISDN
+                0x6f3a, 0x6f3c, 0x6f40, // DF.PHONEBOOK, DF.GRAPHICS, DF.5GS
+                0x5f3a, 0x5f50, 0x5fc0,
+            ]),
+            brute_force: false,
+            max_depth: 3,
+            read_contents: true,
+        }
+    }
+}
+
+/// FileContent: the content of an EF that is collected by the `FileSystemWalker`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum FileContent {
+    Transparent {
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+    },
+    Records {
+        #[serde(with = "hex_bytes_list")]
+        records: Vec<Vec<u8>>,
+    },
+    /// Only the first bytes of the transparent EF could be read, e.g. READ BINARY cannot address the offset
+    /// beyond '7FFF'; the size is the one in the FCP.
+    Truncated {
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+        size: usize,
+    },
+    /// The content could not be read, e.g. '6982' security status not satisfied.
+    NotRead { status_word: u16 },
+}
+
+/// FileNode: a file and its descendants in the file tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileNode {
+    path: Path,
+    fcp: FileControlParameters,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<FileContent>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    children: Vec<FileNode>,
+    /// The file answers SELECT with '6283', i.e. the selected file is deactivated.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    deactivated: bool,
+}
+
+/// ApplicationNode: an ADF that is listed in EF.DIR and its file tree, where the path starts with '3F00/7FFF'.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApplicationNode {
+    template: ApplicationTemplate,
+    root: FileNode,
+}
+
+/// FileSystemTree: the whole file tree of a UICC.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileSystemTree {
+    master_file: FileNode,
+    #[serde(default)]
+    applications: Vec<ApplicationNode>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum WalkerError {
+    #[error("failed to exchange APDU: {0}")]
+    Session(#[from] SessionError),
+    #[error("invalid FCP: {0}")]
+    Fcp(#[from] FcpError),
+    #[error("invalid file identifier or path: {0}")]
+    File(#[from] FileError),
+    #[error("invalid EF.DIR: {0}")]
+    EfDir(#[from] EfDirError),
+    #[error("invalid READ RECORD: {0}")]
+    ReadRecord(#[from] ReadRecordError),
+    #[error("failed to select the MF; status word is '{0:04X}'")]
+    MasterFileNotSelectable(u16),
+}
+
+/// FileSystemWalker explores the file tree of a UICC from the MF and from the ADFs listed in EF.DIR.
+pub struct FileSystemWalker<'a> {
+    session: Session<'a>,
+    config: WalkerConfig,
+}
+
+pub fn new_file_system_walker(
+    transport: &mut dyn Transport,
+    config: WalkerConfig,
+) -> FileSystemWalker<'_> {
+    FileSystemWalker {
+        session: new_session(transport, SessionConfig::default()),
+        config,
+    }
+}
+
+impl<'a> FileSystemWalker<'a> {
+    pub fn walk(&mut self) -> Result<FileSystemTree, WalkerError> {
+        let mf_id = new_file_id(MASTER_FILE_ID)?;
+        let command = new_select_file_command(FileSelection::FileId(mf_id), SelectResponse::FCP);
+        let response = self.transmit(&command.to_command_apdu(&new_basic_class(
+            ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+        )))?;
+        if !response.is_normal_ending() {
+            return Err(WalkerError::MasterFileNotSelectable(
+                response.get_status_word(),
+            ));
+        }
+        let mf_fcp = parse_fcp(response.get_data())?;
+        let master_file = self.explore_df(new_path(Vec::from([mf_id]))?, mf_fcp, false, 0)?;
+
+        let mut applications = Vec::new();
+        let ef_dir = master_file.children.iter().find(|child| {
+            child.path.get_file_ids().last().map(|id| id.get_value()) == Some(EF_DIR_FILE_ID)
+        });
+        if let Some(FileContent::Records { records }) = ef_dir.and_then(|f| f.content.as_ref()) {
+            for record in records {
+                let template = match parse_ef_dir_record(record)? {
+                    Some(template) => template,
+                    None => continue,
+                };
+                let (fcp, deactivated) =
+                    match self.select(FileSelection::DFName(template.get_aid()))? {
+                        Some(selected) => selected,
+                        None => continue,
+                    };
+                let adf_path = new_path(Vec::from([mf_id, new_file_id(CURRENT_ADF_ID)?]))?;
+                let root = self.explore_df(adf_path, fcp, deactivated, 0)?;
+                applications.push(ApplicationNode { template, root });
+            }
+        }
+
+        Ok(FileSystemTree {
+            master_file,
+            applications,
+        })
+    }
+
+    /// Explores the DF; the children of the deactivated DF are not explored and the content of the deactivated
+    /// EF is not read.
+    fn explore_df(
+        &mut self,
+        path: Path,
+        fcp: FileControlParameters,
+        deactivated: bool,
+        depth: usize,
+    ) -> Result<FileNode, WalkerError> {
+        let mut children = Vec::new();
+        if depth < self.config.max_depth && !deactivated {
+            for id in self.candidates(&path) {
+                let child_path = path.join(new_file_id(id)?)?;
+                let (child_fcp, child_deactivated) =
+                    match self.select(FileSelection::Path(&child_path))? {
+                        Some(selected) => selected,
+                        None => continue,
+                    };
+
+                if child_fcp.is_df() {
+                    children.push(self.explore_df(
+                        child_path,
+                        child_fcp,
+                        child_deactivated,
+                        depth + 1,
+                    )?);
+                } else {
+                    let content = if self.config.read_contents && !child_deactivated {
+                        self.read_content(&child_fcp)?
+                    } else {
+                        None
+                    };
+                    children.push(FileNode {
+                        path: child_path,
+                        fcp: child_fcp,
+                        content,
+                        children: Vec::new(),
+                        deactivated: child_deactivated,
+                    });
+                }
+            }
+        }
+
+        Ok(FileNode {
+            path,
+            fcp,
+            content: None,
+            children,
+            deactivated,
+        })
+    }
+
+    /// Lists the file identifiers to probe in the DF according to the convention of the first byte of the file
+    /// identifiers by the level: '2F' and '7F' under the MF, '6F' and '5F' under the 1st level DF or the ADF,
+    /// and '4F' under the 2nd level DF.
+    fn candidates(&self, path: &Path) -> Vec<u16> {
+        let prefixes: &[u16] = match path.get_file_ids().len() {
+            1 => &[0x2f, 0x7f],
+            2 => &[0x6f, 0x5f],
+            _ => &[0x4f],
+        };
+
+        let mut ids: Vec<u16> = self
+            .config
+            .known_file_ids
+            .iter()
+            .copied()
+            .filter(|id| prefixes.contains(&(id >> 8)))
+            .collect();
+        if self.config.brute_force {
+            for prefix in prefixes {
+                ids.extend((0x00..=0xff).map(|lower| (prefix << 8) | lower));
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        ids.retain(|id| {
+            *id != CURRENT_ADF_ID
+                && *id != RESERVED_PATH_FILE_ID
+                && *id != RESERVED_FUTURE_USE_FILE_ID
+                && seen.insert(*id)
+        });
+        ids
+    }
+
+    /// Selects the file and returns its FCP and whether it is deactivated, i.e. SELECT is answered by '6283';
+    /// `None` when the file cannot be selected.
+    fn select(
+        &mut self,
+        selection: FileSelection,
+    ) -> Result<Option<(FileControlParameters, bool)>, WalkerError> {
+        let class = new_basic_class(ClassTypeForStandardLogicalChannels::ISOIEC7816_4);
+        let command = new_select_file_command(selection, SelectResponse::FCP);
+        let mut response = self.transmit(&command.to_command_apdu(&class))?;
+        let deactivated = response.is_selected_file_invalidated();
+        if !response.is_normal_ending() && !deactivated {
+            return Ok(None);
+        }
+        if deactivated && response.get_data().is_empty() {
+            // the FCP is retrieved by GET RESPONSE after the warning, e.g. by T=0
+            response = self.transmit(&new_command_apdu(
+                &class,
+                &GetResponse {},
+                0x00,
+                0x00,
+                Some(0x00),
+                None,
+            ))?;
+            if !response.is_normal_ending() {
+                return Ok(None);
+            }
+        }
+        Ok(Some((parse_fcp(response.get_data())?, deactivated)))
+    }
+
+    fn read_content(
+        &mut self,
+        fcp: &FileControlParameters,
+    ) -> Result<Option<FileContent>, WalkerError> {
+        let descriptor = fcp.get_file_descriptor();
+        match descriptor.get_structure() {
+            EFStructure::Transparent => {
+                let size = fcp.get_file_size().unwrap_or(0) as usize;
+                let mut data = Vec::with_capacity(size);
+                while data.len() < size {
+                    let le = (size - data.len()).min(0xff) as u8;
+                    let command = match new_read_binary_command(data.len() as u16, le) {
+                        Ok(command) => command,
+                        Err(_) => break,
+                    };
+                    let response = self.transmit(&command.to_command_apdu(&new_basic_class(
+                        ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+                    )))?;
+                    if !response.is_normal_ending() {
+                        return Ok(Some(FileContent::NotRead {
+                            status_word: response.get_status_word(),
+                        }));
+                    }
+                    if response.get_data().is_empty() {
+                        break;
+                    }
+                    data.extend_from_slice(response.get_data());
+                }
+                if data.len() < size {
+                    return Ok(Some(FileContent::Truncated { data, size }));
+                }
+                Ok(Some(FileContent::Transparent { data }))
+            }
+            EFStructure::LinearFixed | EFStructure::Cyclic => {
+                let record_length = descriptor.get_record_length().unwrap_or(0);
+                let le = if record_length > 0xff {
+                    0x00
+                } else {
+                    record_length as u8
+                };
+                let mut records = Vec::new();
+                for record_number in 1..=descriptor.get_number_of_records().unwrap_or(0) {
+                    let command = new_read_record_command(
+                        record_number,
+                        RecordMode::AbsoluteOrCurrent,
+                        None,
+                        le,
+                    )?;
+                    let response = self.transmit(&command.to_command_apdu(&new_basic_class(
+                        ClassTypeForStandardLogicalChannels::ISOIEC7816_4,
+                    )))?;
+                    if !response.is_normal_ending() {
+                        return Ok(Some(FileContent::NotRead {
+                            status_word: response.get_status_word(),
+                        }));
+                    }
+                    records.push(response.into_data());
+                }
+                Ok(Some(FileContent::Records { records }))
+            }
+            EFStructure::BerTlv | EFStructure::NoInformation => Ok(None),
+        }
+    }
+
+    /// Transmits the command and issues GET RESPONSE when the UICC answers '61XX'.
+    fn transmit(&mut self, command: &CommandAPDU) -> Result<ResponseAPDU, WalkerError> {
+        Ok(self.session.transmit(command)?)
+    }
+}
+
+impl FileNode {
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_fcp(&self) -> &FileControlParameters {
+        &self.fcp
+    }
+
+    pub fn get_content(&self) -> Option<&FileContent> {
+        self.content.as_ref()
+    }
+
+    pub fn get_children(&self) -> &[FileNode] {
+        &self.children
+    }
+
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivated
+    }
+}
+
+impl ApplicationNode {
+    pub fn get_template(&self) -> &ApplicationTemplate {
+        &self.template
+    }
+
+    pub fn get_root(&self) -> &FileNode {
+        &self.root
+    }
+}
+
+impl FileSystemTree {
+    pub fn get_master_file(&self) -> &FileNode {
+        &self.master_file
+    }
+
+    pub fn get_applications(&self) -> &[ApplicationNode] {
+        &self.applications
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::transport::{Transport, TransportError};
+    use crate::walker::{new_file_system_walker, FileContent, WalkerConfig, WalkerError};
+
+    /// A card that knows files by the command data of SELECT by path from the MF.
+    struct FakeCard {
+        files: HashMap<Vec<u8>, (Vec<u8>, Vec<u8>)>,
+        selected: Option<Vec<u8>>,
+        pending: Vec<u8>,
+        /// The files that answer SELECT with '6283', whose FCP is retrieved by GET RESPONSE.
+        deactivated: Vec<Vec<u8>>,
+    }
+
+    impl Transport for FakeCard {
+        fn transmit(&mut self, command: &[u8]) -> Result<Vec<u8>, TransportError> {
+            let respond = |mut data: Vec<u8>| {
+                data.extend_from_slice(&[0x90, 0x00]);
+                Ok(data)
+            };
+            match (command[1], command[2]) {
+                (0xa4, _) => {
+                    let key = command[5..5 + command[4] as usize].to_vec();
+                    match self.files.get(&key) {
+                        Some((fcp, _)) if self.deactivated.contains(&key) => {
+                            self.selected = Some(key);
+                            self.pending = fcp.clone();
+                            Ok(Vec::from([0x62, 0x83]))
+                        }
+                        Some((fcp, _)) => {
+                            self.selected = Some(key);
+                            self.pending = fcp.clone();
+                            Ok(Vec::from([0x61, fcp.len() as u8]))
+                        }
+                        None => Ok(Vec::from([0x6a, 0x82])),
+                    }
+                }
+                (0xc0, _) => respond(std::mem::take(&mut self.pending)),
+                (0xb0, _) => {
+                    let (_, content) = &self.files[self.selected.as_ref().unwrap()];
+                    let offset = u16::from_be_bytes([command[2], command[3]]) as usize;
+                    respond(content[offset..offset + command[4] as usize].to_vec())
+                }
+                (0xb2, _) => {
+                    let (_, content) = &self.files[self.selected.as_ref().unwrap()];
+                    let len = command[4] as usize;
+                    let start = (command[2] as usize - 1) * len;
+                    respond(content[start..start + len].to_vec())
+                }
+                _ => Ok(Vec::from([0x6d, 0x00])),
+            }
+        }
+    }
+
+    fn fake_card() -> FakeCard {
+        let mut files = HashMap::new();
+        // MF
+        files.insert(
+            Vec::from([0x3f, 0x00]),
+            (
+                Vec::from([0x62, 0x08, 0x82, 0x02, 0x78, 0x21, 0x83, 0x02, 0x3f, 0x00]),
+                Vec::new(),
+            ),
+        );
+        // EF.DIR with a USIM record and an empty record
+        let mut ef_dir = Vec::from([
+            0x61, 0x0f, 0x4f, 0x07, 0xa0, 0x00, 0x00, 0x00, 0x87, 0x10, 0x02, 0x50, 0x04, 0x55,
+            0x53, 0x49, 0x4d, 0xff, 0xff, 0xff,
+        ]);
+        ef_dir.extend_from_slice(&[0xff; 20]);
+        files.insert(
+            Vec::from([0x2f, 0x00]),
+            (
+                Vec::from([
+                    0x62, 0x0b, 0x82, 0x05, 0x42, 0x21, 0x00, 0x14, 0x02, 0x83, 0x02, 0x2f, 0x00,
+                ]),
+                ef_dir,
+            ),
+        );
+        // EF.ICCID
+        files.insert(
+            Vec::from([0x2f, 0xe2]),
+            (
+                Vec::from([
+                    0x62, 0x0b, 0x82, 0x02, 0x41, 0x21, 0x83, 0x02, 0x2f, 0xe2, 0x80, 0x01, 0x0a,
+                ]),
+                Vec::from([0x98, 0x94, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf1]),
+            ),
+        );
+        // USIM ADF and EF.IMSI under it
+        files.insert(
+            Vec::from([0xa0, 0x00, 0x00, 0x00, 0x87, 0x10, 0x02]),
+            (
+                Vec::from([
+                    0x62, 0x0d, 0x82, 0x02, 0x78, 0x21, 0x84, 0x07, 0xa0, 0x00, 0x00, 0x00, 0x87,
+                    0x10, 0x02,
+                ]),
+                Vec::new(),
+            ),
+        );
+        files.insert(
+            Vec::from([0x7f, 0xff, 0x6f, 0x07]),
+            (
+                Vec::from([
+                    0x62, 0x0b, 0x82, 0x02, 0x41, 0x21, 0x83, 0x02, 0x6f, 0x07, 0x80, 0x01, 0x09,
+                ]),
+                Vec::from([0x08, 0x09, 0x10, 0x10, 0x10, 0x32, 0x54, 0x76, 0x98]),
+            ),
+        );
+
+        FakeCard {
+            files,
+            selected: None,
+            pending: Vec::new(),
+            deactivated: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn should_walk_file_system() {
+        let mut card = fake_card();
+        let tree = new_file_system_walker(&mut card, WalkerConfig::default())
+            .walk()
+            .unwrap();
+
+        let mf = tree.get_master_file();
+        assert_eq!(mf.get_path().to_string(), "3F00");
+        let paths: Vec<String> = mf
+            .get_children()
+            .iter()
+            .map(|child| child.get_path().to_string())
+            .collect();
+        assert_eq!(paths, Vec::from(["3F00/2F00", "3F00/2FE2"]));
+        assert_eq!(
+            mf.get_children()[1].get_content(),
+            Some(&FileContent::Transparent {
+                data: Vec::from([0x98, 0x94, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf1])
+            })
+        );
+
+        let applications = tree.get_applications();
+        assert_eq!(applications.len(), 1);
+        assert_eq!(applications[0].get_template().get_label(), Some("USIM"));
+        let imsi = &applications[0].get_root().get_children()[0];
+        assert_eq!(imsi.get_path().to_string(), "3F00/7FFF/6F07");
+
+        let json = tree.to_json().unwrap();
+        assert!(json.contains("\"path\": \"3F00/7FFF/6F07\""));
+        assert!(json.contains("\"data\": \"080910101032547698\""));
+    }
+
+    #[test]
+    fn should_mark_content_beyond_read_binary_offset_truncated() {
+        let mut card = fake_card();
+        // EF.PL of 36864 bytes, which is beyond the offset '7FFF' of READ BINARY
+        card.files.insert(
+            Vec::from([0x2f, 0x05]),
+            (
+                Vec::from([
+                    0x62, 0x0c, 0x82, 0x02, 0x41, 0x21, 0x83, 0x02, 0x2f, 0x05, 0x80, 0x02, 0x90,
+                    0x00,
+                ]),
+                vec![0x5a; 0x9000],
+            ),
+        );
+        let tree = new_file_system_walker(&mut card, WalkerConfig::default())
+            .walk()
+            .unwrap();
+        let ef = tree
+            .get_master_file()
+            .get_children()
+            .iter()
+            .find(|child| child.get_path().to_string() == "3F00/2F05")
+            .unwrap();
+        match ef.get_content() {
+            Some(FileContent::Truncated { data, size }) => {
+                assert_eq!(*size, 0x9000);
+                assert_eq!(data.len(), 129 * 0xff);
+            }
+            content => panic!("unexpected content: {:?}", content),
+        }
+    }
+
+    #[test]
+    fn should_keep_deactivated_files() {
+        let mut card = fake_card();
+        card.deactivated.push(Vec::from([0x2f, 0xe2]));
+        let tree = new_file_system_walker(&mut card, WalkerConfig::default())
+            .walk()
+            .unwrap();
+        let iccid = &tree.get_master_file().get_children()[1];
+        assert_eq!(iccid.get_path().to_string(), "3F00/2FE2");
+        assert!(iccid.is_deactivated());
+        assert_eq!(iccid.get_fcp().get_file_size(), Some(0x0a));
+        assert_eq!(iccid.get_content(), None);
+        assert!(!tree.get_master_file().get_children()[0].is_deactivated());
+        assert!(tree.to_json().unwrap().contains("\"deactivated\": true"));
+    }
+
+    #[test]
+    fn should_fail_walk_without_mf() {
+        let mut card = fake_card();
+        card.files.clear();
+        assert_eq!(
+            new_file_system_walker(&mut card, WalkerConfig::default())
+                .walk()
+                .unwrap_err(),
+            WalkerError::MasterFileNotSelectable(0x6a82)
+        );
+    }
+}